@@ -0,0 +1,56 @@
+const ESC: char = '\u{1b}';
+const BEL: char = '\u{7}';
+
+/// Whether the text carries terminal escape sequences (colors, cursor movement, titles)
+pub fn contains_ansi(text: &str) -> bool {
+    text.contains(ESC)
+}
+
+/// Remove ANSI escape sequences, keeping only the printable text
+pub fn strip_ansi(text: &str) -> String {
+    if !contains_ansi(text) {
+        return text.to_string();
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != ESC {
+            out.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            // CSI: parameter and intermediate bytes up to a final byte in '@'..='~'
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC: terminated by BEL or ST (ESC \)
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == BEL {
+                        break;
+                    }
+                    if c == ESC {
+                        if chars.peek() == Some(&'\\') {
+                            chars.next();
+                        }
+                        break;
+                    }
+                }
+            }
+            // charset selection takes one more character, e.g. ESC ( B
+            Some('(') | Some(')') => {
+                chars.next();
+            }
+            _ => {}
+        }
+    }
+
+    out
+}
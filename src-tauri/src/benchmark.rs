@@ -0,0 +1,150 @@
+use crate::database;
+use crate::llm;
+use crate::shortcut::Clip;
+use crate::AppState;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::time::Instant;
+use tauri::State;
+
+/// Clips with an obvious category, sent through the configured model to compare
+/// providers and models against each other
+const SAMPLES: &[(&str, &str)] = &[
+    (
+        "code_snippet",
+        "fn fibonacci(n: u64) -> u64 {\n    match n {\n        0 | 1 => n,\n        _ => fibonacci(n - 1) + fibonacci(n - 2),\n    }\n}",
+    ),
+    ("url", "https://doc.rust-lang.org/book/ch08-02-strings.html"),
+    (
+        "error_log",
+        "thread 'main' panicked at src/main.rs:14:5:\ncalled `Option::unwrap()` on a `None` value\nnote: run with `RUST_BACKTRACE=1` environment variable to display a backtrace",
+    ),
+    ("command", "docker run -d -p 8080:80 --name web nginx:latest"),
+    (
+        "data",
+        "id,name,email,signup_date\n1,Ada Lovelace,ada@example.com,2024-01-12\n2,Alan Turing,alan@example.com,2024-02-03",
+    ),
+    (
+        "communication",
+        "Hi Sam,\n\nThanks for sending the draft over. I left a few comments on the second section, could we go through them on Thursday?\n\nBest,\nRobin",
+    ),
+    (
+        "academic",
+        "Abstract. We study the convergence of stochastic gradient descent on non-convex objectives and show that, under mild smoothness assumptions, the expected gradient norm decreases at a rate of O(1/sqrt(T)).",
+    ),
+];
+
+#[derive(Debug, Serialize)]
+pub struct BenchmarkResult {
+    pub expected_category: String,
+    pub category: Option<String>,
+    pub tags: Vec<String>,
+    /// the model answered with the JSON the category prompt asks for
+    pub valid_json: bool,
+    pub correct: bool,
+    pub latency_ms: u64,
+    /// the model that answered, differs from the configured one after a fallback
+    pub model: Option<String>,
+    pub input_tokens: Option<u32>,
+    pub output_tokens: Option<u32>,
+    pub cost: Option<f64>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BenchmarkReport {
+    pub provider: String,
+    pub results: Vec<BenchmarkResult>,
+    pub valid_json_rate: f64,
+    pub accuracy: f64,
+    pub average_latency_ms: u64,
+    /// None when no request had a known price
+    pub total_cost: Option<f64>,
+}
+
+/// Model, tokens and cost of the category requests recorded after `after_id`
+fn usage_since(
+    conn: &Connection,
+    after_id: i64,
+) -> rusqlite::Result<(Option<String>, Option<u32>, Option<u32>, Option<f64>)> {
+    conn.query_row(
+        "SELECT (SELECT model FROM llm_usage WHERE id > ?1 AND stage = 'category' ORDER BY id DESC LIMIT 1),
+                SUM(input_tokens), SUM(output_tokens), SUM(cost)
+         FROM llm_usage WHERE id > ?1 AND stage = 'category'",
+        params![after_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    )
+}
+
+fn last_usage_id(conn: &Connection) -> rusqlite::Result<i64> {
+    conn.query_row("SELECT COALESCE(MAX(id), 0) FROM llm_usage", [], |row| {
+        row.get(0)
+    })
+}
+
+/// Categorize a fixed set of sample clips with the configured provider and model,
+/// reporting per clip whether the answer was valid JSON, whether the category was
+/// the expected one, and the latency and cost. The requests are recorded in
+/// llm_usage like any other, which is also where tokens and cost are read from.
+#[tauri::command]
+pub async fn benchmark_llm(state: State<'_, AppState>) -> Result<BenchmarkReport, String> {
+    if !llm::has_credentials() {
+        return Err("No API key configured for the current LLM provider".to_string());
+    }
+
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    let mut results = Vec::with_capacity(SAMPLES.len());
+    for (expected_category, text) in SAMPLES {
+        let clip = Clip::Text {
+            plain: text.to_string(),
+        };
+
+        let after_id =
+            last_usage_id(&conn).map_err(|e| format!("Failed to read LLM usage: {e}"))?;
+        let started = Instant::now();
+        let response = llm::get_llm_category(&clip).await;
+        let latency_ms = started.elapsed().as_millis() as u64;
+        let (model, input_tokens, output_tokens, cost) =
+            usage_since(&conn, after_id).map_err(|e| format!("Failed to read LLM usage: {e}"))?;
+
+        let (category, tags, valid_json, error) = match response {
+            Ok(response) => (
+                Some(response.category),
+                response.tags,
+                !response.unparsed,
+                None,
+            ),
+            Err(e) => (None, Vec::new(), false, Some(e.to_string())),
+        };
+
+        results.push(BenchmarkResult {
+            expected_category: expected_category.to_string(),
+            correct: valid_json && category.as_deref() == Some(*expected_category),
+            category,
+            tags,
+            valid_json,
+            latency_ms,
+            model,
+            input_tokens,
+            output_tokens,
+            cost,
+            error,
+        });
+    }
+
+    let count = results.len() as f64;
+    let rate = |matches: usize| matches as f64 / count;
+    let costs: Vec<f64> = results.iter().filter_map(|result| result.cost).collect();
+
+    Ok(BenchmarkReport {
+        provider: llm::setting("llm_provider"),
+        valid_json_rate: rate(results.iter().filter(|result| result.valid_json).count()),
+        accuracy: rate(results.iter().filter(|result| result.correct).count()),
+        average_latency_ms: results.iter().map(|result| result.latency_ms).sum::<u64>()
+            / results.len() as u64,
+        total_cost: (!costs.is_empty()).then(|| costs.iter().sum()),
+        results,
+    })
+}
@@ -1,160 +1,1500 @@
-use crate::shortcut::{save_clip, Clip};
+use crate::database;
+use crate::llm;
+use crate::maintenance;
+use crate::settings::SettingsManagerState;
+use crate::shortcut::{
+    clip_json_string, emit_save_result, is_url, process_clip, save_clip, truncate_chars, Clip,
+    ClipMeta,
+};
+use crate::stats;
+use crate::suggest;
 use crate::AppState;
-use rusqlite::{params, Connection};
+use base64::{engine::general_purpose, Engine};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
-use base64::{engine::general_purpose, Engine};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tauri::{Emitter, Manager, State};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClipItem {
+    pub id: String,
+    pub clip: Clip,
+    pub category: Option<String>,
+    pub summary: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub created_at: String,
+    pub expires_at: Option<String>,
+    pub content_type: Option<String>,
+    pub raw_content: Option<String>,
+    pub source_app: Option<String>,
+    pub use_count: i64,
+    pub content_hash: Option<String>,
+    pub remind_at: Option<String>,
+    /// name the user gave the clip, if any
+    pub title: Option<String>,
+    /// user title, or one derived from the content for list display
+    pub display_title: String,
+    /// workspace that was active when the clip was captured
+    pub workspace: Option<String>,
+    /// sampled by scheduled capture
+    pub scheduled: bool,
+    /// pinned clips are kept out of bulk cleanup
+    pub is_pinned: bool,
+    /// when the content was first seen on the clipboard, which can be well before
+    /// created_at for scheduled captures; None for hotkey captures
+    pub copied_at: Option<String>,
+    /// the text was cut down by `capture_line_limit` when captured
+    pub is_truncated: bool,
+    /// lines the text had before it was cut down
+    pub original_line_count: Option<i64>,
+    /// base64 PNG thumbnail of image clips, only filled in by queries selecting it
+    pub thumbnail_data: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CategoryInfo {
+    pub category: String,
+    pub color: Option<String>,
+    pub icon: Option<String>,
+}
+
+/// Columns every ClipItem query selects, in the order `clip_item_from_row` reads them
+pub(crate) const CLIP_ITEM_COLUMNS: &str =
+    "id, clip, created_at, category, summary, tags, expires_at, content_type, source_app, use_count, content_hash, remind_at, title, workspace, scheduled, is_pinned, copied_at, is_truncated, original_line_count";
+
+/// CLIP_ITEM_COLUMNS for list views: image clips with a thumbnail leave out their
+/// full-size data, which get_item loads, and carry the thumbnail instead
+pub(crate) const LIST_ITEM_COLUMNS: &str =
+    "id, CASE WHEN content_type = 'image' AND thumbnail IS NOT NULL THEN json_set(clip, '$.content', '') ELSE clip END, created_at, category, summary, tags, expires_at, content_type, source_app, use_count, content_hash, remind_at, title, workspace, scheduled, is_pinned, copied_at, is_truncated, original_line_count, thumbnail";
+
+/// Expired clips stay in the table until the pruning task runs, hide them everywhere
+pub(crate) const VISIBLE_CLIPS: &str = "(expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)";
+
+pub(crate) fn clip_item_from_row(row: &rusqlite::Row) -> rusqlite::Result<ClipItem> {
+    let id: i64 = row.get(0)?;
+    let clip_json: String = row.get(1)?;
+    let created_at: String = row.get(2)?;
+    let category: Option<String> = row.get(3).ok();
+    let summary: Option<String> = row.get(4).ok();
+    let tags_json: Option<String> = row.get(5).ok();
+    let expires_at: Option<String> = row.get(6).ok();
+    let content_type: Option<String> = row.get(7).ok();
+    let source_app: Option<String> = row.get(8).ok();
+    let use_count: i64 = row.get::<_, Option<i64>>(9)?.unwrap_or(1);
+    let content_hash: Option<String> = row.get(10).ok();
+    let remind_at: Option<String> = row.get(11).ok();
+    let title: Option<String> = row.get(12).ok().flatten();
+    let workspace: Option<String> = row.get(13).ok().flatten();
+    let scheduled: bool = row.get::<_, Option<bool>>(14)?.unwrap_or(false);
+    let is_pinned: bool = row.get::<_, Option<bool>>(15)?.unwrap_or(false);
+    let copied_at: Option<String> = row.get(16).ok().flatten();
+    let is_truncated: bool = row.get::<_, Option<bool>>(17)?.unwrap_or(false);
+    let original_line_count: Option<i64> = row.get(18).ok().flatten();
+    let thumbnail_data: Option<String> = row.get(19).ok().flatten();
+
+    let tags: Option<Vec<String>> = if let Some(tags_str) = tags_json {
+        serde_json::from_str(&tags_str).unwrap_or_default()
+    } else {
+        None
+    };
+
+    // a corrupt row shows up as "Invalid clip type" rather than failing the whole list
+    let clip_value: serde_json::Value = serde_json::from_str(&clip_json).unwrap_or_else(|e| {
+        eprintln!("Clip {} has invalid JSON: {}", id, e);
+        serde_json::Value::Null
+    });
+
+    let clip = clip_from_value(&clip_value);
+
+    let raw_content = clip_value["raw"].as_str().map(|raw| raw.to_string());
+
+    let display_title = title
+        .clone()
+        .unwrap_or_else(|| derived_title(&clip, &clip_value));
+
+    Ok(ClipItem {
+        id: id.to_string(),
+        clip,
+        created_at,
+        category,
+        summary,
+        tags,
+        expires_at,
+        content_type,
+        raw_content,
+        source_app,
+        use_count,
+        content_hash,
+        remind_at,
+        title,
+        display_title,
+        workspace,
+        scheduled,
+        is_pinned,
+        copied_at,
+        is_truncated,
+        original_line_count,
+        thumbnail_data,
+    })
+}
+
+/// Page size of get_items when the caller does not ask for one
+const DEFAULT_PAGE_SIZE: u32 = 50;
+
+/// One page of clips, `total_count` counts every clip the filters match.
+/// `next_cursor` is passed as `cursor` for the following page, None on the last one.
+#[derive(Debug, Serialize)]
+pub struct ClipPage {
+    pub items: Vec<ClipItem>,
+    pub total_count: i64,
+    pub has_more: bool,
+    pub next_cursor: Option<String>,
+}
+
+/// Where the next page starts: the sort key of the last clip seen, pinned state,
+/// id and capture time, as "pinned:id:created_at". Ids alone do not follow the
+/// list order, bumped clips move to the top with their old id.
+fn encode_cursor(item: &ClipItem) -> String {
+    format!(
+        "{}:{}:{}",
+        u8::from(item.is_pinned),
+        item.id,
+        item.created_at
+    )
+}
+
+fn decode_cursor(cursor: &str) -> Option<(i64, i64, String)> {
+    let mut parts = cursor.splitn(3, ':');
+    let is_pinned = parts.next()?.parse().ok()?;
+    let id = parts.next()?.parse().ok()?;
+    let created_at = parts.next()?.to_string();
+    Some((is_pinned, id, created_at))
+}
+
+/// One page of the clips matching `conditions`, pinned ones first and newest first
+/// after that. Continues after `cursor` when given, else skips `offset` clips.
+fn query_page(
+    conn: &Connection,
+    mut conditions: Vec<String>,
+    mut values: Vec<Value>,
+    limit: usize,
+    offset: u32,
+    cursor: Option<&str>,
+) -> Result<ClipPage, String> {
+    let total_count = count_clips(conn, &conditions, &values)
+        .map_err(|e| format!("Failed to count clips: {e}"))?;
+
+    let offset = if let Some(cursor) = cursor {
+        let (is_pinned, id, created_at) =
+            decode_cursor(cursor).ok_or_else(|| format!("Invalid cursor '{}'", cursor))?;
+        values.push(Value::Integer(is_pinned));
+        values.push(Value::Text(created_at));
+        values.push(Value::Integer(id));
+        let n = values.len();
+        conditions.push(format!(
+            "(is_pinned, created_at, id) < (?{}, ?{}, ?{})",
+            n - 2,
+            n - 1,
+            n
+        ));
+        0
+    } else {
+        offset
+    };
+
+    // one row past the page tells whether another page follows
+    values.push(Value::Integer(limit as i64 + 1));
+    values.push(Value::Integer(i64::from(offset)));
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {LIST_ITEM_COLUMNS} FROM clips WHERE {} ORDER BY is_pinned DESC, created_at DESC, id DESC LIMIT ?{} OFFSET ?{}",
+            conditions.join(" AND "),
+            values.len() - 1,
+            values.len()
+        ))
+        .map_err(|e| format!("Failed to prepare statement: {e}"))?;
+
+    let mut items = stmt
+        .query_map(params_from_iter(values.iter()), clip_item_from_row)
+        .map_err(|e| format!("Failed to execute query: {e}"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to process row: {e}"))?;
+
+    let has_more = items.len() > limit;
+    items.truncate(limit);
+
+    let next_cursor = if has_more {
+        items.last().map(encode_cursor)
+    } else {
+        None
+    };
+
+    Ok(ClipPage {
+        items,
+        total_count,
+        has_more,
+        next_cursor,
+    })
+}
+
+/// Clips carrying the tag bound to ?{n}, which has to be lowercased and trimmed.
+/// NULL or malformed tags match no tag.
+fn has_tag_condition(n: usize) -> String {
+    format!(
+        "EXISTS (SELECT 1 FROM json_each(CASE WHEN json_valid(tags) THEN tags ELSE '[]' END) WHERE lower(trim(value)) = ?{n})"
+    )
+}
+
+/// WHERE conditions and their values for the filters get_items and count_items
+/// share. Clips need every tag in `tags`, or any one of them with `any_tag`.
+fn item_conditions(
+    workspace: Option<&str>,
+    category: Option<&str>,
+    tags: &[String],
+    any_tag: bool,
+) -> (Vec<String>, Vec<Value>) {
+    let mut conditions = vec![VISIBLE_CLIPS.to_string()];
+    let mut values: Vec<Value> = Vec::new();
+
+    if let Some(workspace) = workspace {
+        values.push(Value::Text(workspace.to_string()));
+        conditions.push(format!("workspace = ?{}", values.len()));
+    }
+
+    if let Some(category) = category {
+        values.push(Value::Text(category.to_string()));
+        conditions.push(format!("category = ?{}", values.len()));
+    }
+
+    let mut tag_conditions = Vec::new();
+    for tag in tags {
+        values.push(Value::Text(tag.trim().to_lowercase()));
+        tag_conditions.push(has_tag_condition(values.len()));
+    }
+    if !tag_conditions.is_empty() {
+        let joiner = if any_tag { " OR " } else { " AND " };
+        conditions.push(format!("({})", tag_conditions.join(joiner)));
+    }
+
+    (conditions, values)
+}
+
+fn count_clips(
+    conn: &Connection,
+    conditions: &[String],
+    values: &[Value],
+) -> rusqlite::Result<i64> {
+    conn.query_row(
+        &format!(
+            "SELECT COUNT(*) FROM clips WHERE {}",
+            conditions.join(" AND ")
+        ),
+        params_from_iter(values.iter()),
+        |row| row.get(0),
+    )
+}
+
+/// Visible clips, pinned ones first and newest first after that, a page at a time
+/// and limited to one workspace, category and set of tags when given.
+/// `match_any_tag` relaxes the tags from all of them to any one of them. `from`
+/// and `to` are RFC 3339 timestamps bounding the capture time, both inclusive
+/// and either one optional.
+/// Pages continue after `cursor`, the `next_cursor` of the previous page, when given
+/// and else skip `offset` clips; a cursor keeps pages stable while new clips come in.
+/// Image clips come with their thumbnail instead of the full image, see LIST_ITEM_COLUMNS.
+// every filter is its own optional command argument
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+pub async fn get_items(
+    state: State<'_, AppState>,
+    workspace: Option<String>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+    cursor: Option<String>,
+    category: Option<String>,
+    tags: Option<Vec<String>>,
+    match_any_tag: Option<bool>,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<ClipPage, String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    items_page(
+        &conn,
+        &ClipFilter {
+            workspace,
+            category,
+            tags,
+            match_any_tag,
+            from,
+            to,
+            limit: limit.map(|limit| limit as usize),
+            offset,
+            cursor,
+            ..ClipFilter::default()
+        },
+    )
+}
+
+/// Filters of get_items and get_items_filtered, every one given has to match
+#[derive(Debug, Default, Deserialize)]
+pub struct ClipFilter {
+    pub workspace: Option<String>,
+    pub category: Option<String>,
+    /// clips carrying all of these tags, compared case-insensitively
+    pub tags: Option<Vec<String>>,
+    /// any one of `tags` is enough
+    pub match_any_tag: Option<bool>,
+    /// RFC 3339 timestamps bounding the capture time, both inclusive
+    pub from: Option<String>,
+    pub to: Option<String>,
+    /// first and last local day of capture, both inclusive
+    pub from_date: Option<String>,
+    pub to_date: Option<String>,
+    pub limit: Option<usize>,
+    /// clips to skip, ignored when `cursor` is given
+    pub offset: Option<u32>,
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
+}
+
+/// The page of clips `filter` selects, pinned ones first and newest first after that
+fn items_page(conn: &Connection, filter: &ClipFilter) -> Result<ClipPage, String> {
+    let (mut conditions, mut values) = item_conditions(
+        filter.workspace.as_deref(),
+        filter.category.as_deref(),
+        filter.tags.as_deref().unwrap_or_default(),
+        filter.match_any_tag.unwrap_or(false),
+    );
+
+    if let Some(from) = &filter.from {
+        values.push(Value::Text(stats::normalize_timestamp(conn, from)?));
+        conditions.push(format!("created_at >= ?{}", values.len()));
+    }
+
+    if let Some(to) = &filter.to {
+        values.push(Value::Text(stats::normalize_timestamp(conn, to)?));
+        conditions.push(format!("created_at <= ?{}", values.len()));
+    }
+
+    if let Some(from_date) = &filter.from_date {
+        values.push(Value::Text(stats::normalize_date(conn, from_date)?));
+        conditions.push(format!("created_date >= ?{}", values.len()));
+    }
+
+    if let Some(to_date) = &filter.to_date {
+        values.push(Value::Text(stats::normalize_date(conn, to_date)?));
+        conditions.push(format!("created_date <= ?{}", values.len()));
+    }
+
+    query_page(
+        conn,
+        conditions,
+        values,
+        filter.limit.unwrap_or(DEFAULT_PAGE_SIZE as usize),
+        filter.offset.unwrap_or(0),
+        filter.cursor.as_deref(),
+    )
+}
+
+/// Visible clips matching a category, tags and a range of days, in get_items order
+/// and a page at a time
+#[tauri::command]
+pub async fn get_items_filtered(
+    state: State<'_, AppState>,
+    filter: ClipFilter,
+) -> Result<ClipPage, String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+    items_page(&conn, &filter)
+}
+
+/// Number of clips get_items pages through, for page controls
+#[tauri::command]
+pub async fn count_items(
+    state: State<'_, AppState>,
+    workspace: Option<String>,
+    category: Option<String>,
+    tags: Option<Vec<String>>,
+    match_any_tag: Option<bool>,
+) -> Result<u64, String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    let (conditions, values) = item_conditions(
+        workspace.as_deref(),
+        category.as_deref(),
+        tags.as_deref().unwrap_or_default(),
+        match_any_tag.unwrap_or(false),
+    );
+
+    count_clips(&conn, &conditions, &values)
+        .map(|count| count as u64)
+        .map_err(|e| format!("Failed to count clips: {e}"))
+}
+
+/// All clips sharing a content hash, newest first, for reviewing duplicates
+#[tauri::command]
+pub async fn get_clips_by_hash(
+    state: State<'_, AppState>,
+    hash: String,
+) -> Result<Vec<ClipItem>, String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {CLIP_ITEM_COLUMNS} FROM clips WHERE content_hash = ? AND {VISIBLE_CLIPS} ORDER BY created_at DESC"
+        ))
+        .map_err(|e| format!("Failed to prepare statement: {e}"))?;
+
+    let items = stmt
+        .query_map([hash], clip_item_from_row)
+        .map_err(|e| format!("Failed to execute query: {e}"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to process row: {e}"))?;
+
+    Ok(items)
+}
+
+/// Clips captured since the app was started, newest first
+#[tauri::command]
+pub async fn get_session_clips(state: State<'_, AppState>) -> Result<Vec<ClipItem>, String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {CLIP_ITEM_COLUMNS} FROM clips WHERE created_at >= datetime(?, 'unixepoch') AND {VISIBLE_CLIPS} ORDER BY created_at DESC"
+        ))
+        .map_err(|e| format!("Failed to prepare statement: {e}"))?;
+
+    let items = stmt
+        .query_map([state.session_started_at], clip_item_from_row)
+        .map_err(|e| format!("Failed to execute query: {e}"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to process row: {e}"))?;
+
+    Ok(items)
+}
+
+const TITLE_CHARS: usize = 80;
+
+/// Title for clips the user has not named: the page title or address of a link,
+/// the first line of text, or the dimensions of an image
+fn derived_title(clip: &Clip, clip_value: &serde_json::Value) -> String {
+    match clip {
+        Clip::Text { plain } if is_url(plain.trim()) => clip_value["page_title"]
+            .as_str()
+            .map(|title| title.to_string())
+            .or_else(|| {
+                let url = url::Url::parse(plain.trim()).ok()?;
+                let host = url.host_str()?.trim_start_matches("www.");
+                Some(format!("{}{}", host, url.path().trim_end_matches('/')))
+            })
+            .unwrap_or_else(|| plain.trim().to_string()),
+        Clip::Text { plain } => {
+            let line = plain.lines().map(str::trim).find(|line| !line.is_empty());
+            match line {
+                Some(line) => truncate_chars(line, TITLE_CHARS),
+                None => "Empty text".to_string(),
+            }
+        }
+        Clip::Image { width, height, .. } => format!("Image {}x{}", width, height),
+    }
+}
+
+/// Default and largest number of clips get_recent_clips returns
+const DEFAULT_RECENT_CLIPS: u32 = 10;
+const MAX_RECENT_CLIPS: u32 = 100;
+/// Text read per clip for its preview, enough to find the first line
+const RECENT_PREVIEW_SOURCE_CHARS: i64 = 2000;
+
+/// A clip as the quick paste menu lists it
+#[derive(Debug, Serialize)]
+pub struct RecentClip {
+    pub id: String,
+    pub preview: String,
+    pub content_type: String,
+}
+
+/// The newest clips with just enough to list them, image data and full text stay in the database
+#[tauri::command]
+pub async fn get_recent_clips(
+    state: State<'_, AppState>,
+    limit: Option<u32>,
+) -> Result<Vec<RecentClip>, String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+    let limit = limit
+        .unwrap_or(DEFAULT_RECENT_CLIPS)
+        .clamp(1, MAX_RECENT_CLIPS);
+
+    let mut stmt = conn
+        .prepare(&format!(
+            r#"
+            SELECT id, content_type, title,
+                   CASE WHEN content_type = 'image' THEN NULL ELSE substr(json_extract(clip, '$.content'), 1, ?1) END,
+                   json_extract(clip, '$.width'), json_extract(clip, '$.height'), json_extract(clip, '$.page_title')
+            FROM clips
+            WHERE {VISIBLE_CLIPS}
+            ORDER BY created_at DESC
+            LIMIT ?2
+            "#
+        ))
+        .map_err(|e| format!("Failed to prepare statement: {e}"))?;
+
+    let clips = stmt
+        .query_map(params![RECENT_PREVIEW_SOURCE_CHARS, limit], |row| {
+            let content_type: String = row
+                .get::<_, Option<String>>(1)?
+                .unwrap_or_else(|| "text".to_string());
+            let title: Option<String> = row.get(2)?;
+
+            let clip = if content_type == "image" {
+                Clip::Image {
+                    data: String::new(),
+                    width: row.get::<_, Option<i64>>(4)?.unwrap_or(0) as usize,
+                    height: row.get::<_, Option<i64>>(5)?.unwrap_or(0) as usize,
+                    text: None,
+                }
+            } else {
+                Clip::Text {
+                    plain: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                }
+            };
+            let clip_value = serde_json::json!({ "page_title": row.get::<_, Option<String>>(6)? });
+
+            Ok(RecentClip {
+                id: row.get::<_, i64>(0)?.to_string(),
+                preview: title.unwrap_or_else(|| derived_title(&clip, &clip_value)),
+                content_type,
+            })
+        })
+        .map_err(|e| format!("Failed to execute query: {e}"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to process row: {e}"))?;
+
+    Ok(clips)
+}
+
+const DEFAULT_PREVIEW_CHARS: usize = 200;
+
+/// What the clip list shows for a clip
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ClipPreview {
+    Text {
+        text: String,
+    },
+    Link {
+        url: String,
+        title: Option<String>,
+    },
+    /// the thumbnail is missing for clips saved before thumbnails existed
+    Image {
+        thumbnail: Option<String>,
+        width: usize,
+        height: usize,
+    },
+}
+
+/// Preview of a clip, text cut to `max_len` characters
+#[tauri::command]
+pub fn get_clip_preview(
+    state: State<'_, AppState>,
+    item_id: String,
+    max_len: Option<usize>,
+) -> Result<ClipPreview, String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    let (clip_json, title, thumbnail): (String, Option<String>, Option<String>) = conn
+        .query_row(
+            "SELECT clip, title, thumbnail FROM clips WHERE id = ?",
+            params![item_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => "Item not found".to_string(),
+            e => format!("Failed to get clip: {e}"),
+        })?;
+
+    let clip_value: serde_json::Value =
+        serde_json::from_str(&clip_json).map_err(|e| format!("Failed to parse clip JSON: {e}"))?;
+
+    let max_len = max_len.unwrap_or(DEFAULT_PREVIEW_CHARS);
+
+    Ok(match clip_from_value(&clip_value) {
+        Clip::Text { plain } if is_url(plain.trim()) => ClipPreview::Link {
+            url: plain.trim().to_string(),
+            title: title.or_else(|| clip_value["page_title"].as_str().map(str::to_string)),
+        },
+        Clip::Text { plain } => ClipPreview::Text {
+            text: truncate_chars(plain.trim(), max_len),
+        },
+        Clip::Image { width, height, .. } => ClipPreview::Image {
+            thumbnail,
+            width,
+            height,
+        },
+    })
+}
+
+/// Rebuild a Clip from the JSON stored in the clip column, anything that is
+/// neither a text nor an image clip becomes the text "Invalid clip type"
+fn clip_from_value(clip_value: &serde_json::Value) -> Clip {
+    match clip_value["type"].as_str() {
+        Some("text") => Clip::Text {
+            plain: clip_value["content"].as_str().unwrap_or("").to_string(),
+        },
+        Some("image") => {
+            let base64_data = clip_value["content"].as_str().unwrap_or("");
+            let width = clip_value["width"].as_u64().unwrap_or(0) as usize;
+            let height = clip_value["height"].as_u64().unwrap_or(0) as usize;
+
+            Clip::Image {
+                data: base64_data.to_string(),
+                width,
+                height,
+                text: clip_value["text"].as_str().map(|text| text.to_string()),
+            }
+        }
+        _ => Clip::Text {
+            plain: "Invalid clip type".to_string(),
+        },
+    }
+}
+
+pub(crate) fn load_clip(conn: &Connection, item_id: &str) -> Result<Clip, String> {
+    let clip_json: String = conn
+        .query_row(
+            "SELECT clip FROM clips WHERE id = ?",
+            params![item_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => "Item not found".to_string(),
+            e => format!("Failed to get clip: {e}"),
+        })?;
+
+    let clip_value: serde_json::Value =
+        serde_json::from_str(&clip_json).map_err(|e| format!("Failed to parse clip JSON: {e}"))?;
+
+    Ok(clip_from_value(&clip_value))
+}
+
+/// A single clip with its full-size image data, for when a list item is opened
+#[tauri::command]
+pub fn get_item(state: State<'_, AppState>, item_id: String) -> Result<ClipItem, String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    conn.query_row(
+        &format!("SELECT {CLIP_ITEM_COLUMNS}, thumbnail FROM clips WHERE id = ?"),
+        params![item_id],
+        clip_item_from_row,
+    )
+    .map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => "Item not found".to_string(),
+        e => format!("Failed to get item: {e}"),
+    })
+}
+
+/// The clip column of a clip as stored, reformatted for reading. Image data is
+/// replaced by its length unless `include_data` is set.
+#[tauri::command]
+pub fn get_raw_clip(
+    state: State<'_, AppState>,
+    item_id: String,
+    pretty: Option<bool>,
+    include_data: Option<bool>,
+) -> Result<String, String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    let clip_json: String = conn
+        .query_row(
+            "SELECT clip FROM clips WHERE id = ?",
+            params![item_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => "Item not found".to_string(),
+            e => format!("Failed to get clip: {e}"),
+        })?;
+
+    let mut clip_value: serde_json::Value =
+        serde_json::from_str(&clip_json).map_err(|e| format!("Failed to parse clip JSON: {e}"))?;
+
+    if clip_value["type"].as_str() == Some("image") && !include_data.unwrap_or(false) {
+        let length = clip_value["content"].as_str().map_or(0, str::len);
+        clip_value["content"] = serde_json::json!(format!("<{} bytes of base64>", length));
+    }
+
+    clip_json_string(&clip_value, pretty.unwrap_or(true))
+        .map_err(|e| format!("Failed to format clip JSON: {e}"))
+}
+
+#[tauri::command]
+pub async fn submit_clip(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    user_category: String,
+    summary: String,
+    clip_json: String,
+    tags: Vec<String>,
+    expires_in_minutes: Option<u32>,
+    meta: ClipMeta,
+) -> Result<(), String> {
+    let db_path = &state.db_path;
+
+    let clip: Clip = serde_json::from_str(&clip_json)
+        .map_err(|e| format!("Failed to deserialize clip: {}", e))?;
+
+    // the expiry is the one picked in the toolbar
+    let meta = ClipMeta {
+        expires_in_minutes,
+        ..meta
+    };
+
+    let saved = save_clip(
+        &app_handle,
+        db_path,
+        &clip,
+        &user_category,
+        &summary,
+        &tags,
+        &meta,
+    )
+    .await
+    .map_err(|e| format!("Failed to save clip: {}", e))?;
+    emit_save_result(&app_handle, saved);
+
+    // the category confirmed in the popup trains the local suggestions
+    match database::open_connection(db_path) {
+        Ok(conn) => {
+            if let Err(e) = suggest::record_category_choice(
+                &conn,
+                &clip,
+                meta.source_app.as_deref(),
+                &user_category,
+            ) {
+                eprintln!("Failed to record category choice: {}", e);
+            }
+        }
+        Err(e) => eprintln!("Failed to open database: {}", e),
+    }
+
+    // Close the popup window
+    crate::toolbar::close_toolbar_window(app_handle).ok();
+
+    Ok(())
+}
+
+/// Summarize a clip now and store the summary, for clips saved without one
+/// while `summarize_on_capture` was off
+#[tauri::command]
+pub async fn summarize_clip(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    item_id: String,
+) -> Result<String, String> {
+    let clip = {
+        let conn = database::open_connection(&state.db_path)
+            .map_err(|e| format!("Failed to open database: {e}"))?;
+        load_clip(&conn, &item_id)?
+    };
+
+    let summary = llm::get_clip_summary(&clip)
+        .await
+        .map_err(|e| format!("Failed to summarize clip: {e}"))?;
+
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+    let rows_affected = conn
+        .execute(
+            "UPDATE clips SET summary = ?1, summary_updated_at = CURRENT_TIMESTAMP, clip = json_set(clip, '$.summary', ?1) WHERE id = ?2",
+            params![summary, item_id],
+        )
+        .map_err(|e| format!("Failed to save summary: {e}"))?;
+
+    if rows_affected == 0 {
+        return Err("Item not found".to_string());
+    }
+
+    app_handle.emit("clip-updated", &item_id).ok();
+
+    Ok(summary)
+}
+
+/// Correct the category, tags or summary of a clip after capture. Only the
+/// fields given change, in the columns as well as the copies in the clip JSON.
+#[tauri::command]
+pub fn update_item(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    item_id: String,
+    category: Option<String>,
+    tags: Option<Vec<String>>,
+    summary: Option<String>,
+) -> Result<ClipItem, String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    let mut assignments = Vec::new();
+    let mut values: Vec<Value> = Vec::new();
+    let mut clip_expr = "clip".to_string();
+
+    if let Some(category) = &category {
+        values.push(Value::Text(category.trim().to_string()));
+        let n = values.len();
+        assignments.push(format!("category = ?{n}"));
+        clip_expr = format!("json_set({clip_expr}, '$.category', ?{n})");
+    }
+
+    if let Some(summary) = &summary {
+        values.push(Value::Text(summary.clone()));
+        let n = values.len();
+        assignments.push(format!("summary = ?{n}"));
+        assignments.push("summary_updated_at = CURRENT_TIMESTAMP".to_string());
+        clip_expr = format!("json_set({clip_expr}, '$.summary', ?{n})");
+    }
+
+    if let Some(tags) = &tags {
+        let tags_json = serde_json::to_string(&maintenance::clean_tags(tags))
+            .map_err(|e| format!("Failed to serialize tags: {e}"))?;
+        values.push(Value::Text(tags_json));
+        let n = values.len();
+        // the clips_tags_update trigger keeps clip_tags in sync
+        assignments.push(format!("tags = ?{n}"));
+        clip_expr = format!(
+            "CASE WHEN json_type(clip, '$.tags') IS NULL THEN {clip_expr} ELSE json_set({clip_expr}, '$.tags', json(?{n})) END"
+        );
+    }
+
+    if !assignments.is_empty() {
+        assignments.push(format!("clip = {clip_expr}"));
+        values.push(Value::Text(item_id.clone()));
+
+        let rows_affected = conn
+            .execute(
+                &format!(
+                    "UPDATE clips SET {} WHERE id = ?{}",
+                    assignments.join(", "),
+                    values.len()
+                ),
+                params_from_iter(values.iter()),
+            )
+            .map_err(|e| format!("Failed to update item: {e}"))?;
+
+        if rows_affected == 0 {
+            return Err("Item not found".to_string());
+        }
+    }
+
+    let item = conn
+        .query_row(
+            &format!("SELECT {CLIP_ITEM_COLUMNS} FROM clips WHERE id = ?"),
+            params![item_id],
+            clip_item_from_row,
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => "Item not found".to_string(),
+            e => format!("Failed to get item: {e}"),
+        })?;
+
+    if assignments.is_empty() {
+        return Ok(item);
+    }
+
+    // a corrected category trains the local suggestions like one picked in the popup
+    if let Some(category) = item.category.as_deref().filter(|_| category.is_some()) {
+        if let Err(e) =
+            suggest::record_category_choice(&conn, &item.clip, item.source_app.as_deref(), category)
+        {
+            eprintln!("Failed to record category choice: {}", e);
+        }
+    }
+
+    app_handle.emit("clip-updated", &item_id).ok();
+    if tags.is_some() {
+        app_handle.emit("tags-changed", ()).ok();
+    }
+
+    Ok(item)
+}
+
+#[tauri::command]
+pub fn delete_item(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    item_id: String,
+) -> Result<(), String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    let rows_affected = conn
+        .execute("DELETE FROM clips WHERE id = ?", params![item_id])
+        .map_err(|error| format!("Failed to delete item: {}", error))?;
+
+    if rows_affected == 0 {
+        return Err("Item not found".to_string());
+    }
+
+    app_handle
+        .emit("clip-deleted", &item_id)
+        .map_err(|e| format!("Failed to emit event: {}", e))?;
+    app_handle.emit("tags-changed", ()).ok();
+
+    Ok(())
+}
+
+/// Clear the clipboard and delete the most recent capture, returns the deleted clip's id
+#[tauri::command]
+pub fn panic_clear(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Option<String>, String> {
+    Ok(crate::shortcut::panic_clear(&app_handle, &state.db_path)?.map(|id| id.to_string()))
+}
+
+/// Delete the listed clips in one statement and return the ids that existed
+fn delete_clips(conn: &Connection, item_ids: &[String]) -> rusqlite::Result<Vec<String>> {
+    let placeholders = vec!["?"; item_ids.len()].join(", ");
+    let mut stmt = conn.prepare(&format!(
+        "DELETE FROM clips WHERE id IN ({placeholders}) RETURNING id"
+    ))?;
+
+    let deleted = stmt
+        .query_map(params_from_iter(item_ids.iter()), |row| {
+            row.get::<_, i64>(0).map(|id| id.to_string())
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(deleted)
+}
+
+/// Delete the listed clips in one transaction, all of them or none when something fails
+fn delete_clips_atomically(
+    conn: &mut Connection,
+    item_ids: &[String],
+) -> rusqlite::Result<Vec<String>> {
+    let tx = conn.transaction()?;
+    let deleted = delete_clips(&tx, item_ids)?;
+    tx.commit()?;
+    Ok(deleted)
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeleteReport {
+    pub deleted: usize,
+    /// requested ids no clip had, they do not fail the rest of the batch
+    pub missing: Vec<String>,
+}
+
+/// Delete several clips in one transaction, all of them or none when something
+/// fails. Sends a single clip-bulk-deleted event listing the deleted ids.
+#[tauri::command]
+pub fn bulk_delete_items(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    item_ids: Vec<String>,
+) -> Result<DeleteReport, String> {
+    if item_ids.is_empty() {
+        return Err("No clips to delete".to_string());
+    }
+
+    let mut conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+    let deleted = delete_clips_atomically(&mut conn, &item_ids)
+        .map_err(|e| format!("Failed to delete items: {e}"))?;
+
+    let missing = item_ids
+        .into_iter()
+        .filter(|id| !deleted.contains(id))
+        .collect();
+
+    if !deleted.is_empty() {
+        app_handle.emit("clip-bulk-deleted", &deleted).ok();
+        app_handle.emit("tags-changed", ()).ok();
+    }
+
+    Ok(DeleteReport {
+        deleted: deleted.len(),
+        missing,
+    })
+}
+
+#[tauri::command]
+pub fn set_expiry(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    item_id: String,
+    minutes: Option<u32>,
+) -> Result<(), String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    // passing no minutes clears the expiry so the clip is kept forever
+    let expiry_modifier = minutes.map(|m| format!("+{} minutes", m));
+
+    let rows_affected = conn
+        .execute(
+            "UPDATE clips SET expires_at = CASE WHEN ?1 IS NULL THEN NULL ELSE datetime('now', ?1) END WHERE id = ?2",
+            params![expiry_modifier, item_id],
+        )
+        .map_err(|e| format!("Failed to set expiry: {e}"))?;
+
+    if rows_affected == 0 {
+        return Err("Item not found".to_string());
+    }
+
+    app_handle
+        .emit("clip-updated", &item_id)
+        .map_err(|e| format!("Failed to emit event: {}", e))?;
+
+    Ok(())
+}
+
+/// Apply `edit` to the tags of every listed clip in one transaction, keeping the
+/// JSON copies in sync. Returns how many clips actually changed.
+fn edit_clip_tags(
+    db_path: &Path,
+    item_ids: &[String],
+    edit: impl Fn(&mut Vec<String>) -> bool,
+) -> Result<usize, String> {
+    let mut conn =
+        database::open_connection(db_path).map_err(|e| format!("Failed to open database: {e}"))?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {e}"))?;
+
+    let mut updated = 0;
+    for item_id in item_ids {
+        let tags_json: Option<String> = match tx.query_row(
+            "SELECT tags FROM clips WHERE id = ?",
+            params![item_id],
+            |row| row.get(0),
+        ) {
+            Ok(tags_json) => tags_json,
+            Err(rusqlite::Error::QueryReturnedNoRows) => continue,
+            Err(e) => return Err(format!("Failed to get tags: {e}")),
+        };
+
+        let mut tags: Vec<String> = tags_json
+            .and_then(|tags| serde_json::from_str(&tags).ok())
+            .unwrap_or_default();
+
+        if !edit(&mut tags) {
+            continue;
+        }
+
+        let tags_json = serde_json::to_string(&maintenance::clean_tags(&tags))
+            .map_err(|e| format!("Failed to serialize tags: {e}"))?;
+        // the clips_tags_update trigger keeps clip_tags in sync
+        tx.execute(
+            "UPDATE clips SET tags = ?1, clip = CASE WHEN json_type(clip, '$.tags') IS NULL THEN clip ELSE json_set(clip, '$.tags', json(?1)) END WHERE id = ?2",
+            params![tags_json, item_id],
+        )
+        .map_err(|e| format!("Failed to update tags: {e}"))?;
+        updated += 1;
+    }
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {e}"))?;
+
+    Ok(updated)
+}
+
+#[tauri::command]
+pub fn add_tag_to_clips(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    item_ids: Vec<String>,
+    tag: String,
+) -> Result<usize, String> {
+    let tag = tag.trim().to_lowercase();
+    if tag.is_empty() {
+        return Err("Tag cannot be empty".to_string());
+    }
+
+    let updated = edit_clip_tags(&state.db_path, &item_ids, |tags| {
+        if tags
+            .iter()
+            .any(|existing| existing.trim().to_lowercase() == tag)
+        {
+            return false;
+        }
+        tags.push(tag.clone());
+        true
+    })?;
+
+    if updated > 0 {
+        app_handle.emit("clips-updated", &item_ids).ok();
+        app_handle.emit("tags-changed", ()).ok();
+    }
+
+    Ok(updated)
+}
+
+#[tauri::command]
+pub fn remove_tag_from_clips(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    item_ids: Vec<String>,
+    tag: String,
+) -> Result<usize, String> {
+    let tag = tag.trim().to_lowercase();
+
+    let updated = edit_clip_tags(&state.db_path, &item_ids, |tags| {
+        let before = tags.len();
+        tags.retain(|existing| existing.trim().to_lowercase() != tag);
+        tags.len() != before
+    })?;
+
+    if updated > 0 {
+        app_handle.emit("clips-updated", &item_ids).ok();
+        app_handle.emit("tags-changed", ()).ok();
+    }
+
+    Ok(updated)
+}
+
+/// Name a clip, an empty or missing title goes back to the derived one
+#[tauri::command]
+pub fn set_title(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    item_id: String,
+    title: Option<String>,
+) -> Result<(), String> {
+    let title = title
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty());
+
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    let rows_affected = conn
+        .execute(
+            "UPDATE clips SET title = ? WHERE id = ?",
+            params![title, item_id],
+        )
+        .map_err(|e| format!("Failed to set title: {e}"))?;
+
+    if rows_affected == 0 {
+        return Err("Item not found".to_string());
+    }
+
+    app_handle
+        .emit("clip-updated", &item_id)
+        .map_err(|e| format!("Failed to emit event: {}", e))?;
+
+    Ok(())
+}
 
-use tauri::{Emitter, Manager, State};
+#[tauri::command]
+pub fn set_pinned(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    item_id: String,
+    pinned: bool,
+) -> Result<(), String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ClipItem {
-    pub id: String,
-    pub clip: Clip,
-    pub category: Option<String>,
-    pub summary: Option<String>,
-    pub tags: Option<Vec<String>>,
-    pub created_at: String,
+    let rows_affected = conn
+        .execute(
+            "UPDATE clips SET is_pinned = ? WHERE id = ?",
+            params![pinned, item_id],
+        )
+        .map_err(|e| format!("Failed to set pinned: {e}"))?;
+
+    if rows_affected == 0 {
+        return Err("Item not found".to_string());
+    }
+
+    app_handle
+        .emit("clip-updated", &item_id)
+        .map_err(|e| format!("Failed to emit event: {}", e))?;
+
+    Ok(())
+}
+
+/// Flip whether a clip is pinned and return the new state
+#[tauri::command]
+pub fn toggle_pin(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    item_id: String,
+) -> Result<bool, String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    let pinned = conn
+        .query_row(
+            "UPDATE clips SET is_pinned = 1 - is_pinned WHERE id = ? RETURNING is_pinned",
+            params![item_id],
+            |row| row.get::<_, bool>(0),
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => "Item not found".to_string(),
+            e => format!("Failed to toggle pin: {e}"),
+        })?;
+
+    app_handle
+        .emit("clip-updated", &item_id)
+        .map_err(|e| format!("Failed to emit event: {}", e))?;
+
+    Ok(pinned)
+}
+
+/// Pin or unpin several clips at once. Returns how many clips changed, clips
+/// that were already in the requested state or do not exist are not counted.
+#[tauri::command]
+pub fn set_pinned_bulk(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    item_ids: Vec<String>,
+    pinned: bool,
+) -> Result<usize, String> {
+    let mut conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {e}"))?;
+
+    let mut updated = 0;
+    {
+        let mut stmt = tx
+            .prepare("UPDATE clips SET is_pinned = ?1 WHERE id = ?2 AND is_pinned != ?1")
+            .map_err(|e| format!("Failed to prepare statement: {e}"))?;
+        for item_id in &item_ids {
+            updated += stmt
+                .execute(params![pinned, item_id])
+                .map_err(|e| format!("Failed to set pinned: {e}"))?;
+        }
+    }
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {e}"))?;
+
+    if updated > 0 {
+        app_handle.emit("clips-updated", &item_ids).ok();
+    }
+
+    Ok(updated)
 }
 
 #[tauri::command]
-pub async fn get_items(state: State<'_, AppState>) -> Result<Vec<ClipItem>, String> {
-    let conn =
-        Connection::open(&state.db_path).map_err(|e| format!("Failed to open database: {e}"))?;
+pub fn get_categories(state: State<'_, AppState>) -> Result<Vec<CategoryInfo>, String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
 
+    // defined categories plus any free-form ones users typed in the popup
     let mut stmt = conn
         .prepare(
             r#"
-        SELECT
-          id,
-          clip,
-          created_at,
-          category,
-          summary,
-          tags
-        FROM clips
-        ORDER BY created_at DESC
+        SELECT name, color, icon FROM categories
+        UNION
+        SELECT DISTINCT category, NULL, NULL FROM clips
+        WHERE category IS NOT NULL
+          AND category != ''
+          AND category NOT IN (SELECT name FROM categories)
+        ORDER BY 1
         "#,
         )
         .map_err(|e| format!("Failed to prepare statement: {e}"))?;
 
-    let clip_iter = stmt
+    let categories = stmt
         .query_map([], |row| {
-            let id: i64 = row.get(0)?;
-            let clip_json: String = row.get(1)?;
-            let created_at: String = row.get(2)?;
-            let category: Option<String> = row.get(3).ok();
-            let summary: Option<String> = row.get(4).ok();
-            let tags_json: Option<String> = row.get(5).ok();
-
-            let tags: Option<Vec<String>> = if let Some(tags_str) = tags_json {
-                serde_json::from_str(&tags_str).unwrap_or_default()
-            } else {
-                None
-            };
+            Ok(CategoryInfo {
+                category: row.get(0)?,
+                color: row.get(1)?,
+                icon: row.get(2)?,
+            })
+        })
+        .map_err(|e| format!("Failed to execute query: {e}"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to process row: {e}"))?;
 
-            let clip_value: serde_json::Value = serde_json::from_str(&clip_json).map_err(|_| {
-                rusqlite::Error::InvalidColumnType(
-                    0,
-                    "Invalid JSON".to_string(),
-                    rusqlite::types::Type::Text,
-                )
-            })?;
-
-            let clip = match clip_value["type"].as_str() {
-                Some("text") => Clip::Text {
-                    plain: clip_value["content"].as_str().unwrap_or("").to_string(),
-                },
-                Some("image") => {
-                    let base64_data = clip_value["content"].as_str().unwrap_or("");
-                    let width = clip_value["width"].as_u64().unwrap_or(0) as usize;
-                    let height = clip_value["height"].as_u64().unwrap_or(0) as usize;
-
-                    Clip::Image {
-                        data: base64_data.to_string(),
-                        width,
-                        height,
-                    }
-                }
-                _ => Clip::Text {
-                    plain: "Invalid clip type".to_string(),
-                },
-            };
+    Ok(categories)
+}
+
+#[derive(Debug, Serialize)]
+pub struct CategoryCount {
+    pub category: String,
+    pub count: i64,
+}
+
+/// Categories the visible clips are in with how many each has, most used first.
+/// Clips without a category count as "uncategorized", trashed ones not at all.
+#[tauri::command]
+pub fn get_category_counts(state: State<'_, AppState>) -> Result<Vec<CategoryCount>, String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            r#"
+        SELECT COALESCE(NULLIF(category, ''), 'uncategorized') AS name, COUNT(*)
+        FROM clips
+        WHERE deleted_at IS NULL AND {VISIBLE_CLIPS}
+        GROUP BY name
+        ORDER BY COUNT(*) DESC, name
+        "#
+        ))
+        .map_err(|e| format!("Failed to prepare statement: {e}"))?;
 
-            Ok(ClipItem {
-                id: id.to_string(),
-                clip,
-                created_at,
-                category,
-                summary,
-                tags,
+    let counts = stmt
+        .query_map([], |row| {
+            Ok(CategoryCount {
+                category: row.get(0)?,
+                count: row.get(1)?,
             })
         })
-        .map_err(|e| format!("Failed to execute query: {e}"))?;
+        .map_err(|e| format!("Failed to execute query: {e}"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to process row: {e}"))?;
 
-    let mut items: Vec<ClipItem> = Vec::new();
-    for item in clip_iter {
-        items.push(item.map_err(|e| format!("Failed to process row: {e}"))?);
-    }
+    Ok(counts)
+}
 
-    Ok(items)
+#[derive(Debug, Serialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: i64,
 }
 
+/// Tags of the visible clips with how many clips have each, most used first.
+/// Tags are stored lowercased and trimmed, so differently typed copies count as
+/// one. With `prefix` only tags starting with it are returned, for autocomplete.
 #[tauri::command]
-pub async fn submit_clip(
-    app_handle: tauri::AppHandle,
+pub fn get_tags(
     state: State<'_, AppState>,
-    user_category: String,
-    summary: String,
-    clip_json: String,
-    tags: Vec<String>,
-) -> Result<(), String> {
-    let db_path = &state.db_path;
+    prefix: Option<String>,
+) -> Result<Vec<TagCount>, String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
 
-    let clip: Clip = serde_json::from_str(&clip_json)
-        .map_err(|e| format!("Failed to deserialize clip: {}", e))?;
+    let prefix = prefix
+        .map(|prefix| prefix.trim().to_lowercase())
+        .unwrap_or_default();
 
-    save_clip(&app_handle, db_path, &clip, &user_category, &summary, &tags)
-        .await
-        .map_err(|e| format!("Failed to save clip: {}", e))?;
+    // clip_tags is filled from the tags JSON by triggers, which skip malformed lists
+    let mut stmt = conn
+        .prepare(&format!(
+            r#"
+        SELECT tags.name, COUNT(*)
+        FROM tags
+        JOIN clip_tags ON clip_tags.tag_id = tags.id
+        JOIN clips ON clips.id = clip_tags.clip_id
+        WHERE substr(tags.name, 1, length(?1)) = ?1 AND {VISIBLE_CLIPS}
+        GROUP BY tags.id
+        ORDER BY COUNT(*) DESC, tags.name
+        "#
+        ))
+        .map_err(|e| format!("Failed to prepare statement: {e}"))?;
 
-    // Close the popup window
-    if let Some(window) = app_handle.get_webview_window("clip-toolbar") {
-        window.close().ok();
-    }
+    let tags = stmt
+        .query_map(params![prefix], |row| {
+            Ok(TagCount {
+                tag: row.get(0)?,
+                count: row.get(1)?,
+            })
+        })
+        .map_err(|e| format!("Failed to execute query: {e}"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to process row: {e}"))?;
 
-    Ok(())
+    Ok(tags)
 }
 
 #[tauri::command]
-pub fn delete_item(
+pub fn set_category_appearance(
     app_handle: tauri::AppHandle,
     state: State<'_, AppState>,
-    item_id: String,
+    name: String,
+    color: Option<String>,
+    icon: Option<String>,
 ) -> Result<(), String> {
-    let conn =
-        Connection::open(&state.db_path).map_err(|e| format!("Failed to open database: {e}"))?;
-
-    let rows_affected = conn
-        .execute("DELETE FROM clips WHERE id = ?", params![item_id])
-        .map_err(|error| format!("Failed to delete item: {}", error))?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Category name cannot be empty".to_string());
+    }
 
-    if rows_affected == 0 {
-        return Err("Item not found".to_string());
+    if let Some(color) = &color {
+        if !is_hex_color(color) {
+            return Err(format!(
+                "Invalid color '{}', expected #RGB or #RRGGBB",
+                color
+            ));
+        }
     }
 
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    conn.execute(
+        "INSERT INTO categories (name, color, icon) VALUES (?1, ?2, ?3)
+         ON CONFLICT(name) DO UPDATE SET color = excluded.color, icon = excluded.icon",
+        params![name, color, icon],
+    )
+    .map_err(|e| format!("Failed to save category appearance: {e}"))?;
+
     app_handle
-        .emit("clip-deleted", &item_id)
+        .emit("categories-changed", name)
         .map_err(|e| format!("Failed to emit event: {}", e))?;
 
     Ok(())
 }
 
+pub(crate) fn is_hex_color(value: &str) -> bool {
+    match value.strip_prefix('#') {
+        Some(hex) => {
+            (hex.len() == 3 || hex.len() == 6) && hex.chars().all(|c| c.is_ascii_hexdigit())
+        }
+        None => false,
+    }
+}
+
+#[tauri::command]
+pub fn estimate_cost(
+    state: State<'_, AppState>,
+    settings_manager: State<'_, SettingsManagerState>,
+    clip_json: Option<String>,
+    item_id: Option<String>,
+    model: Option<String>,
+) -> Result<llm::CostEstimate, String> {
+    let clip: Clip = match (clip_json, item_id) {
+        (Some(clip_json), _) => serde_json::from_str(&clip_json)
+            .map_err(|e| format!("Failed to deserialize clip: {}", e))?,
+        (None, Some(item_id)) => {
+            let conn = database::open_connection(&state.db_path)
+                .map_err(|e| format!("Failed to open database: {e}"))?;
+            load_clip(&conn, &item_id)?
+        }
+        (None, None) => return Err("Either clip_json or item_id is required".to_string()),
+    };
+
+    let price_overrides = llm::price_overrides(&settings_manager.0);
+
+    let model = model.unwrap_or_else(llm::active_model);
+
+    Ok(llm::estimate_cost(&clip, &model, &price_overrides))
+}
+
 #[tauri::command]
 pub fn get_image_data(state: State<'_, AppState>, item_id: String) -> Result<String, String> {
-    let conn =
-        Connection::open(&state.db_path).map_err(|e| format!("Failed to open database: {e}"))?;
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
 
     let mut stmt = conn
         .prepare("SELECT clip FROM clips WHERE id = ?")
@@ -164,10 +1504,12 @@ pub fn get_image_data(state: State<'_, AppState>, item_id: String) -> Result<Str
         .query_row(params![item_id], |row| row.get(0))
         .map_err(|e| format!("Failed to get clip: {e}"))?;
 
-    let clip_value: serde_json::Value = serde_json::from_str(&clip_json)
-        .map_err(|e| format!("Failed to parse clip JSON: {e}"))?;
+    let clip_value: serde_json::Value =
+        serde_json::from_str(&clip_json).map_err(|e| format!("Failed to parse clip JSON: {e}"))?;
 
-    if clip_value["type"].as_str() == Some("image") {
+    if clip_value["stripped"].as_bool() == Some(true) {
+        Err("Image data was removed to save space".to_string())
+    } else if clip_value["type"].as_str() == Some("image") {
         let base64_data = clip_value["content"].as_str().unwrap_or("");
         Ok(base64_data.to_string())
     } else {
@@ -175,6 +1517,79 @@ pub fn get_image_data(state: State<'_, AppState>, item_id: String) -> Result<Str
     }
 }
 
+/// Image files larger than this are refused before decoding
+const MAX_IMAGE_FILE_BYTES: u64 = 50 * 1024 * 1024;
+
+/// Longest edge of imported images, larger ones are scaled down before saving
+const MAX_IMPORTED_IMAGE_DIMENSION: u32 = 4096;
+
+/// Add an image file as a clip, categorized and summarized like a capture
+#[tauri::command]
+pub async fn add_image_file(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<(), String> {
+    let size = fs::metadata(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?
+        .len();
+    if size > MAX_IMAGE_FILE_BYTES {
+        return Err(format!(
+            "Image file is too large ({} MB, the limit is {} MB)",
+            size / (1024 * 1024),
+            MAX_IMAGE_FILE_BYTES / (1024 * 1024)
+        ));
+    }
+
+    let reader = image::ImageReader::open(&path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    if reader.format().is_none() {
+        return Err("Unsupported image file".to_string());
+    }
+
+    let mut image = reader
+        .decode()
+        .map_err(|e| format!("Failed to decode image: {e}"))?;
+    if image.width() > MAX_IMPORTED_IMAGE_DIMENSION || image.height() > MAX_IMPORTED_IMAGE_DIMENSION
+    {
+        image = image.resize(
+            MAX_IMPORTED_IMAGE_DIMENSION,
+            MAX_IMPORTED_IMAGE_DIMENSION,
+            image::imageops::FilterType::Lanczos3,
+        );
+    }
+
+    // stored the same way as clipboard images, base64 encoded PNG
+    let mut png_data = Vec::new();
+    image
+        .write_to(
+            &mut std::io::Cursor::new(&mut png_data),
+            image::ImageFormat::Png,
+        )
+        .map_err(|e| format!("Failed to encode image: {e}"))?;
+
+    let clip = Clip::Image {
+        data: general_purpose::STANDARD.encode(&png_data),
+        width: image.width() as usize,
+        height: image.height() as usize,
+        text: None,
+    };
+    if clip.is_empty() {
+        return Err("Image has no pixels".to_string());
+    }
+
+    process_clip(
+        &app_handle,
+        &state.db_path,
+        &clip,
+        None,
+        &ClipMeta::default(),
+    )
+    .await
+    .map_err(|e| format!("Failed to save clip: {}", e))
+}
+
 #[tauri::command]
 pub fn save_image_to_file(
     state: State<'_, AppState>,
@@ -182,7 +1597,7 @@ pub fn save_image_to_file(
     file_path: String,
 ) -> Result<(), String> {
     let image_data = get_image_data(state, item_id)?;
-    
+
     let image_bytes = general_purpose::STANDARD
         .decode(&image_data)
         .map_err(|e| format!("Failed to decode base64: {e}"))?;
@@ -193,36 +1608,581 @@ pub fn save_image_to_file(
     Ok(())
 }
 
-#[tauri::command]
-pub fn copy_image_to_clipboard(
-    state: State<'_, AppState>,
-    item_id: String,
-) -> Result<(), String> {
-    let image_data = get_image_data(state, item_id)?;
-    
+/// Decode a stored base64 PNG into the raw RGBA pixels the clipboard takes, the
+/// reverse of raw_pixels_to_png. Corrupt data gives an error, never a panic.
+fn clipboard_image(image_data: &str) -> Result<arboard::ImageData<'static>, String> {
     let image_bytes = general_purpose::STANDARD
-        .decode(&image_data)
-        .map_err(|e| format!("Failed to decode base64: {e}"))?;
+        .decode(image_data)
+        .map_err(|e| format!("Stored image data is corrupt, it is not valid base64: {e}"))?;
 
-    // Convert PNG bytes back to raw RGBA for clipboard
     let img = image::load_from_memory(&image_bytes)
-        .map_err(|e| format!("Failed to load image: {e}"))?;
-    
+        .map_err(|e| format!("Stored image data is corrupt, it is not a readable image: {e}"))?;
+
     let rgba_img = img.to_rgba8();
     let (width, height) = rgba_img.dimensions();
-    
-    let image_data = arboard::ImageData {
+
+    Ok(arboard::ImageData {
         width: width as usize,
         height: height as usize,
         bytes: rgba_img.into_raw().into(),
-    };
+    })
+}
+
+#[tauri::command]
+pub fn copy_image_to_clipboard(state: State<'_, AppState>, item_id: String) -> Result<(), String> {
+    let image_data = clipboard_image(&get_image_data(state, item_id)?)?;
+
+    let mut clipboard =
+        arboard::Clipboard::new().map_err(|e| format!("Failed to access clipboard: {e}"))?;
 
-    let mut clipboard = arboard::Clipboard::new()
-        .map_err(|e| format!("Failed to access clipboard: {e}"))?;
-    
     clipboard
         .set_image(image_data)
         .map_err(|e| format!("Failed to set clipboard image: {e}"))?;
 
     Ok(())
 }
+
+/// What copying a saved clip puts on the clipboard
+enum ClipboardContent {
+    Text(String),
+    Image(arboard::ImageData<'static>),
+}
+
+fn clipboard_content(conn: &Connection, item_id: &str) -> Result<ClipboardContent, String> {
+    match load_clip(conn, item_id)? {
+        Clip::Text { plain } => Ok(ClipboardContent::Text(plain)),
+        Clip::Image { data, .. } => Ok(ClipboardContent::Image(clipboard_image(&data)?)),
+    }
+}
+
+/// Put a saved clip on the system clipboard, text as text and images as images
+fn put_clip_on_clipboard(db_path: &Path, item_id: &str) -> Result<(), String> {
+    let content = {
+        let conn = database::open_connection(db_path)
+            .map_err(|e| format!("Failed to open database: {e}"))?;
+        clipboard_content(&conn, item_id)?
+    };
+
+    let mut clipboard =
+        arboard::Clipboard::new().map_err(|e| format!("Failed to access clipboard: {e}"))?;
+
+    match content {
+        ClipboardContent::Text(plain) => clipboard
+            .set_text(plain)
+            .map_err(|e| format!("Failed to set clipboard text: {e}"))?,
+        ClipboardContent::Image(image) => clipboard
+            .set_image(image)
+            .map_err(|e| format!("Failed to set clipboard image: {e}"))?,
+    }
+
+    Ok(())
+}
+
+/// Put a saved clip back on the system clipboard, text as text and images as
+/// images at their stored size
+#[tauri::command]
+pub fn copy_item_to_clipboard(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    item_id: String,
+) -> Result<(), String> {
+    put_clip_on_clipboard(&state.db_path, &item_id)?;
+
+    app_handle.emit("clip-copied", &item_id).ok();
+
+    Ok(())
+}
+
+/// Time for focus to go back to the previous application once spiegel is hidden
+fn paste_delay(settings_manager: &SettingsManagerState) -> Duration {
+    Duration::from_millis(settings_manager.0.get_u32("paste_delay_ms").into())
+}
+
+/// Hide spiegel so the application used before it gets focus back
+fn hide_app(app_handle: &tauri::AppHandle) {
+    if let Some(window) = app_handle.get_webview_window("main") {
+        window.hide().ok();
+    }
+    #[cfg(target_os = "macos")]
+    app_handle.hide().ok();
+}
+
+/// Copy a saved clip and paste it into the application that had focus before spiegel
+#[tauri::command]
+pub async fn paste_clip(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    settings_manager: State<'_, SettingsManagerState>,
+    item_id: String,
+) -> Result<(), String> {
+    copy_for_paste(&app_handle, &state.db_path, &item_id)?;
+    send_paste(paste_delay(&settings_manager)).await
+}
+
+/// Time the target app gets to read the pasted clip before the previous clipboard
+/// content is put back, even with a paste_delay_ms of 0
+const MIN_RESTORE_DELAY: Duration = Duration::from_millis(250);
+
+/// Put a saved clip on the clipboard and hide spiegel, so the paste goes to the
+/// application used before it
+fn copy_for_paste(
+    app_handle: &tauri::AppHandle,
+    db_path: &Path,
+    item_id: &str,
+) -> Result<(), String> {
+    put_clip_on_clipboard(db_path, item_id)?;
+    app_handle.emit("clip-copied", item_id).ok();
+    hide_app(app_handle);
+    Ok(())
+}
+
+/// Send the paste keystroke once `delay` has given focus back
+async fn send_paste(delay: Duration) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        std::thread::sleep(delay);
+        crate::shortcut::simulate_paste()
+    })
+    .await
+    .map_err(|e| format!("Failed to paste: {e}"))?
+}
+
+/// What was on the clipboard before a paste replaced it
+enum SavedClipboard {
+    Text(String),
+    Image(arboard::ImageData<'static>),
+}
+
+fn save_clipboard(clipboard: &mut arboard::Clipboard) -> Option<SavedClipboard> {
+    if let Ok(text) = clipboard.get_text() {
+        return Some(SavedClipboard::Text(text));
+    }
+    clipboard.get_image().ok().map(SavedClipboard::Image)
+}
+
+fn restore_clipboard(saved: SavedClipboard) -> Result<(), arboard::Error> {
+    let mut clipboard = arboard::Clipboard::new()?;
+    match saved {
+        SavedClipboard::Text(text) => clipboard.set_text(text),
+        SavedClipboard::Image(image) => clipboard.set_image(image),
+    }
+}
+
+/// Paste a saved clip into the application that had focus before spiegel, then
+/// put back what was on the clipboard. When the keystroke can't be simulated the
+/// clip stays on the clipboard and a warning is returned instead of an error.
+#[tauri::command]
+pub async fn paste_item(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    settings_manager: State<'_, SettingsManagerState>,
+    item_id: String,
+) -> Result<Option<String>, String> {
+    let previous = arboard::Clipboard::new()
+        .ok()
+        .and_then(|mut clipboard| save_clipboard(&mut clipboard));
+
+    copy_for_paste(&app_handle, &state.db_path, &item_id)?;
+
+    let delay = paste_delay(&settings_manager);
+    if let Err(e) = send_paste(delay).await {
+        return Ok(Some(format!(
+            "Clip copied to the clipboard, but pasting failed: {e}"
+        )));
+    }
+
+    let Some(previous) = previous else {
+        return Ok(None);
+    };
+    // give the target app time to read the clipboard before it changes again
+    tauri::async_runtime::spawn_blocking(move || {
+        std::thread::sleep(delay.max(MIN_RESTORE_DELAY));
+        if let Err(e) = restore_clipboard(previous) {
+            eprintln!("Failed to restore clipboard after paste: {e}");
+        }
+    })
+    .await
+    .map_err(|e| format!("Failed to paste: {e}"))?;
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        database::init_schema(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn pages_cover_every_clip_once_in_list_order() {
+        let conn = test_db();
+        // capture times out of id order and with ties, a few clips pinned
+        for i in 0..200 {
+            conn.execute(
+                "INSERT INTO clips (clip, created_at, is_pinned, content_hash)
+                 VALUES (?1, datetime('2024-01-01', ?2 || ' minutes'), ?3, ?4)",
+                params![
+                    format!(r#"{{"type":"text","content":"clip {i}"}}"#),
+                    (i * 37) % 100,
+                    i % 25 == 0,
+                    format!("hash{i}")
+                ],
+            )
+            .unwrap();
+        }
+
+        let expected: Vec<String> = conn
+            .prepare("SELECT id FROM clips ORDER BY is_pinned DESC, created_at DESC, id DESC")
+            .unwrap()
+            .query_map([], |row| row.get::<_, i64>(0))
+            .unwrap()
+            .map(|id| id.unwrap().to_string())
+            .collect();
+        assert_eq!(expected.len(), 200);
+
+        let mut seen = Vec::new();
+        let mut page_sizes = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let page = query_page(
+                &conn,
+                vec![VISIBLE_CLIPS.to_string()],
+                Vec::new(),
+                60,
+                0,
+                cursor.as_deref(),
+            )
+            .unwrap();
+            assert_eq!(page.total_count, 200);
+            page_sizes.push(page.items.len());
+            seen.extend(page.items.into_iter().map(|item| item.id));
+            assert_eq!(page.has_more, page.next_cursor.is_some());
+            if !page.has_more {
+                break;
+            }
+            cursor = page.next_cursor;
+        }
+
+        assert_eq!(page_sizes, vec![60, 60, 60, 20]);
+        assert_eq!(seen, expected);
+    }
+
+    #[test]
+    fn combines_category_tag_and_date_filters() {
+        let conn = test_db();
+        let clips = [
+            ("code", r#"["rust","cli"]"#, "2024-03-10"),
+            ("code", r#"["Rust"]"#, "2024-03-12"),
+            ("code", r#"["rust"]"#, "2024-04-01"),
+            ("code", r#"["python"]"#, "2024-03-11"),
+            ("notes", r#"["rust"]"#, "2024-03-11"),
+        ];
+        for (i, (category, tags, day)) in clips.iter().enumerate() {
+            conn.execute(
+                "INSERT INTO clips (clip, category, tags, created_at, created_date, content_hash)
+                 VALUES (?1, ?2, ?3, ?4 || ' 12:00:00', ?4, ?5)",
+                params![
+                    format!(r#"{{"type":"text","content":"clip {i}"}}"#),
+                    category,
+                    tags,
+                    day,
+                    format!("hash{i}")
+                ],
+            )
+            .unwrap();
+        }
+
+        let page = items_page(
+            &conn,
+            &ClipFilter {
+                category: Some("code".to_string()),
+                tags: Some(vec![" RUST ".to_string()]),
+                from_date: Some("2024-03-01".to_string()),
+                to_date: Some("2024-03-31".to_string()),
+                ..ClipFilter::default()
+            },
+        )
+        .unwrap();
+
+        let days: Vec<&str> = page
+            .items
+            .iter()
+            .map(|item| &item.created_at[..10])
+            .collect();
+        assert_eq!(days, vec!["2024-03-12", "2024-03-10"]);
+        assert_eq!(page.total_count, 2);
+        assert!(!page.has_more);
+    }
+
+    #[test]
+    fn null_and_malformed_tags_match_no_tag() {
+        let conn = test_db();
+        let tags = [
+            None,
+            Some("not json"),
+            Some(r#"["rust""#),
+            Some(r#"["rust"]"#),
+        ];
+        for (i, tags) in tags.iter().enumerate() {
+            conn.execute(
+                "INSERT INTO clips (clip, tags, content_hash) VALUES (?1, ?2, ?3)",
+                params![
+                    format!(r#"{{"type":"text","content":"clip {i}"}}"#),
+                    tags,
+                    format!("hash{i}")
+                ],
+            )
+            .unwrap();
+        }
+
+        let tagged = items_page(
+            &conn,
+            &ClipFilter {
+                tags: Some(vec!["rust".to_string()]),
+                ..ClipFilter::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(tagged.total_count, 1);
+        assert_eq!(tagged.items[0].tags, Some(vec!["rust".to_string()]));
+
+        let any_tag = items_page(
+            &conn,
+            &ClipFilter {
+                tags: Some(vec!["rust".to_string(), "not json".to_string()]),
+                match_any_tag: Some(true),
+                ..ClipFilter::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(any_tag.total_count, 1);
+
+        // unfiltered, the broken rows are still listed, just without tags
+        let all = items_page(&conn, &ClipFilter::default()).unwrap();
+        assert_eq!(all.total_count, 4);
+        assert_eq!(
+            all.items.iter().filter(|item| item.tags.is_none()).count(),
+            3
+        );
+    }
+
+    #[test]
+    fn time_ranges_can_be_open_ended() {
+        let conn = test_db();
+        for (i, created_at) in [
+            "2024-05-01 08:00:00",
+            "2024-05-02 08:00:00",
+            "2024-05-03 08:00:00",
+        ]
+        .iter()
+        .enumerate()
+        {
+            conn.execute(
+                "INSERT INTO clips (clip, created_at, content_hash) VALUES (?1, ?2, ?3)",
+                params![
+                    format!(r#"{{"type":"text","content":"clip {i}"}}"#),
+                    created_at,
+                    format!("hash{i}")
+                ],
+            )
+            .unwrap();
+        }
+        let captured = |filter: ClipFilter| -> Vec<String> {
+            items_page(&conn, &filter)
+                .unwrap()
+                .items
+                .into_iter()
+                .map(|item| item.created_at)
+                .collect()
+        };
+
+        assert_eq!(
+            captured(ClipFilter {
+                from: Some("2024-05-02T08:00:00Z".to_string()),
+                ..ClipFilter::default()
+            }),
+            vec!["2024-05-03 08:00:00", "2024-05-02 08:00:00"]
+        );
+        assert_eq!(
+            captured(ClipFilter {
+                to: Some("2024-05-02T10:00:00+02:00".to_string()),
+                ..ClipFilter::default()
+            }),
+            vec!["2024-05-02 08:00:00", "2024-05-01 08:00:00"]
+        );
+        assert!(items_page(
+            &conn,
+            &ClipFilter {
+                from: Some("yesterday".to_string()),
+                ..ClipFilter::default()
+            }
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn bulk_delete_rolls_back_on_failure() {
+        let mut conn = test_db();
+        for i in 1..=4 {
+            conn.execute(
+                "INSERT INTO clips (clip, content_hash) VALUES (?1, ?2)",
+                params![
+                    format!(r#"{{"type":"text","content":"clip {i}"}}"#),
+                    format!("hash{i}")
+                ],
+            )
+            .unwrap();
+        }
+        // FAIL keeps the rows the statement already deleted, only the transaction undoes them
+        conn.execute_batch(
+            "CREATE TEMP TRIGGER fail_delete BEFORE DELETE ON clips WHEN old.id = 3
+             BEGIN SELECT RAISE(FAIL, 'delete failed'); END;",
+        )
+        .unwrap();
+
+        let ids: Vec<String> = (1..=4).map(|id| id.to_string()).collect();
+        assert!(delete_clips_atomically(&mut conn, &ids).is_err());
+
+        let remaining: i64 = conn
+            .query_row("SELECT COUNT(*) FROM clips", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(remaining, 4);
+
+        conn.execute_batch("DROP TRIGGER fail_delete").unwrap();
+        let mut deleted = delete_clips_atomically(&mut conn, &ids[..2]).unwrap();
+        deleted.sort();
+        assert_eq!(deleted, vec!["1", "2"]);
+    }
+
+    /// Store `clip` the way save_clip does and return its id
+    fn save(conn: &Connection, clip: &Clip) -> String {
+        let json = crate::shortcut::clip_json(clip, "notes", "", &["saved".to_string()]);
+        conn.execute(
+            "INSERT INTO clips (clip, content_hash) VALUES (?1, ?2)",
+            params![
+                clip_json_string(&json, false).unwrap(),
+                crate::shortcut::content_hash(clip)
+            ],
+        )
+        .unwrap();
+        conn.last_insert_rowid().to_string()
+    }
+
+    #[test]
+    fn saved_clips_copy_back_as_they_were_captured() {
+        let conn = test_db();
+
+        let text = "first line\n\tsecond line ✓";
+        let id = save(
+            &conn,
+            &Clip::Text {
+                plain: text.to_string(),
+            },
+        );
+        match clipboard_content(&conn, &id).unwrap() {
+            ClipboardContent::Text(copied) => assert_eq!(copied, text),
+            ClipboardContent::Image(_) => panic!("text clip copied as an image"),
+        }
+        let item = conn
+            .query_row(
+                &format!("SELECT {CLIP_ITEM_COLUMNS}, thumbnail FROM clips WHERE id = ?"),
+                params![id],
+                clip_item_from_row,
+            )
+            .unwrap();
+        assert!(matches!(item.clip, Clip::Text { plain } if plain == text));
+
+        let pixels =
+            image::RgbaImage::from_fn(2, 3, |x, y| image::Rgba([x as u8, y as u8, 7, 255]));
+        let mut png = std::io::Cursor::new(Vec::new());
+        pixels.write_to(&mut png, image::ImageFormat::Png).unwrap();
+        let id = save(
+            &conn,
+            &Clip::Image {
+                data: general_purpose::STANDARD.encode(png.into_inner()),
+                width: 2,
+                height: 3,
+                text: None,
+            },
+        );
+        match clipboard_content(&conn, &id).unwrap() {
+            ClipboardContent::Image(copied) => {
+                assert_eq!((copied.width, copied.height), (2, 3));
+                assert_eq!(copied.bytes.as_ref(), pixels.as_raw().as_slice());
+            }
+            ClipboardContent::Text(_) => panic!("image clip copied as text"),
+        }
+    }
+
+    fn list_item(conn: &Connection, id: i64) -> ClipItem {
+        conn.query_row(
+            &format!("SELECT {LIST_ITEM_COLUMNS} FROM clips WHERE id = ?"),
+            params![id],
+            clip_item_from_row,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn reads_text_image_and_corrupt_rows() {
+        let conn = test_db();
+        conn.execute_batch(
+            r#"
+            INSERT INTO clips (id, clip, content_type, category, tags, title, is_pinned)
+            VALUES (1, '{"type":"text","content":"hello","raw":"\u001b[1mhello"}', 'text', 'notes', '["a","b"]', NULL, 1);
+            INSERT INTO clips (id, clip, content_type, tags, thumbnail)
+            VALUES (2, '{"type":"image","content":"full","width":4,"height":2,"text":"ocr"}', 'image', 'not json', 'thumb');
+            INSERT INTO clips (id, clip, use_count) VALUES (3, '{"type":', NULL);
+            "#,
+        )
+        .unwrap();
+
+        let text = list_item(&conn, 1);
+        assert!(matches!(&text.clip, Clip::Text { plain } if plain == "hello"));
+        assert_eq!(text.category.as_deref(), Some("notes"));
+        assert_eq!(text.tags, Some(vec!["a".to_string(), "b".to_string()]));
+        assert_eq!(text.raw_content.as_deref(), Some("\u{1b}[1mhello"));
+        assert_eq!(text.display_title, "hello");
+        assert!(text.is_pinned);
+        assert_eq!(text.use_count, 1);
+
+        // list views carry the thumbnail and leave the full image to get_item
+        let image = list_item(&conn, 2);
+        match &image.clip {
+            Clip::Image {
+                data,
+                width,
+                height,
+                text,
+            } => {
+                assert_eq!(data, "");
+                assert_eq!((*width, *height), (4, 2));
+                assert_eq!(text.as_deref(), Some("ocr"));
+            }
+            Clip::Text { .. } => panic!("image row read as text"),
+        }
+        assert_eq!(image.thumbnail_data.as_deref(), Some("thumb"));
+        assert_eq!(image.tags, None);
+
+        let corrupt = list_item(&conn, 3);
+        assert!(matches!(&corrupt.clip, Clip::Text { plain } if plain == "Invalid clip type"));
+        assert_eq!(corrupt.raw_content, None);
+        assert_eq!(corrupt.use_count, 1);
+    }
+
+    #[test]
+    fn rejects_a_malformed_cursor() {
+        let conn = test_db();
+        let result = query_page(
+            &conn,
+            vec![VISIBLE_CLIPS.to_string()],
+            Vec::new(),
+            10,
+            0,
+            Some("not a cursor"),
+        );
+        assert!(result.is_err());
+    }
+}
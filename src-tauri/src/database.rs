@@ -1,12 +1,164 @@
+use crate::heuristic;
+use crate::llm;
+use crate::settings::SettingsManagerState;
+use crate::shortcut::{hash_content, is_url};
+use crate::urls;
 use rusqlite::Connection;
+use serde::Serialize;
 use std::fs;
 use std::io::{Error, ErrorKind};
-use std::path::PathBuf;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
 use tauri::AppHandle;
-use tauri::Manager;
+use tauri::{Emitter, Manager};
+
+mod migrations;
 
 type AppResult<T> = Result<T, Box<dyn std::error::Error>>;
 
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How long a connection waits on another one's lock before failing with SQLITE_BUSY
+const BUSY_TIMEOUT: Duration = Duration::from_millis(5000);
+
+/// Page cache per connection, negative means KiB rather than pages
+const CACHE_SIZE_KIB: i64 = -8000;
+
+fn apply_pragmas(conn: &Connection) -> rusqlite::Result<()> {
+    // WAL lets the UI read while background tasks write
+    conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+    conn.pragma_update(None, "synchronous", "NORMAL")?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    conn.pragma_update(None, "cache_size", CACHE_SIZE_KIB)
+}
+
+/// Set up a freshly opened connection for concurrent use by commands and
+/// background tasks
+pub fn configure_connection(conn: &Connection) -> AppResult<()> {
+    apply_pragmas(conn)?;
+    Ok(())
+}
+
+/// Connections handed out by open_connection that are still alive
+static OPEN_CONNECTIONS: Mutex<u32> = Mutex::new(0);
+static CONNECTION_RELEASED: Condvar = Condvar::new();
+static MAX_OPEN_CONNECTIONS: AtomicU32 = AtomicU32::new(MAX_DB_CONNECTIONS);
+
+/// Limit how many connections open_connection hands out at once, from `max_db_connections`
+pub fn set_max_connections(max: u32) {
+    MAX_OPEN_CONNECTIONS.store(
+        max.clamp(MIN_DB_CONNECTIONS, MAX_DB_CONNECTIONS),
+        Ordering::SeqCst,
+    );
+    CONNECTION_RELEASED.notify_all();
+}
+
+/// Wait for one of the `max_db_connections` slots, at most as long as a connection
+/// waits on a lock. The caller may itself hold the connections everyone waits on,
+/// so once the wait is over the connection is opened without a slot instead of
+/// deadlocking. Returns whether a slot was taken.
+fn acquire_connection_slot() -> bool {
+    let open = OPEN_CONNECTIONS.lock().unwrap();
+    let (mut open, wait) = CONNECTION_RELEASED
+        .wait_timeout_while(open, BUSY_TIMEOUT, |open| {
+            *open >= MAX_OPEN_CONNECTIONS.load(Ordering::SeqCst)
+        })
+        .unwrap();
+
+    if wait.timed_out() {
+        eprintln!(
+            "All {} database connections are in use, opening one more",
+            *open
+        );
+        return false;
+    }
+
+    *open += 1;
+    true
+}
+
+fn release_connection_slot() {
+    *OPEN_CONNECTIONS.lock().unwrap() -= 1;
+    CONNECTION_RELEASED.notify_one();
+}
+
+/// A connection from open_connection, holding its `max_db_connections` slot until dropped
+pub struct DbConnection {
+    conn: Connection,
+    has_slot: bool,
+}
+
+impl Deref for DbConnection {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        &self.conn
+    }
+}
+
+impl DerefMut for DbConnection {
+    fn deref_mut(&mut self) -> &mut Connection {
+        &mut self.conn
+    }
+}
+
+impl Drop for DbConnection {
+    fn drop(&mut self) {
+        if self.has_slot {
+            release_connection_slot();
+        }
+    }
+}
+
+/// Open the database with `configure_connection` applied, use this instead of
+/// `Connection::open`. Blocks while `max_db_connections` connections are open.
+pub fn open_connection<P: AsRef<Path>>(path: P) -> rusqlite::Result<DbConnection> {
+    let has_slot = acquire_connection_slot();
+    let conn = match Connection::open(path) {
+        Ok(conn) => DbConnection { conn, has_slot },
+        Err(e) => {
+            if has_slot {
+                release_connection_slot();
+            }
+            return Err(e);
+        }
+    };
+    apply_pragmas(&conn)?;
+    Ok(conn)
+}
+
+/// Upper bounds for the `max_clip_age_days` and `max_clip_count` settings, 0 turns them off
+pub const MAX_CLIP_AGE_DAYS: u32 = 36_500;
+pub const MAX_CLIP_COUNT: u32 = 1_000_000;
+
+/// Bounds for the `max_db_connections` setting
+pub const MIN_DB_CONNECTIONS: u32 = 1;
+pub const MAX_DB_CONNECTIONS: u32 = 32;
+
+/// Built-in categories the LLM picks from, with their default color and icon
+const BUILTIN_CATEGORIES: &[(&str, &str, &str)] = &[
+    ("code_snippet", "#6366f1", "code"),
+    ("technical_advice", "#0ea5e9", "wrench"),
+    ("documentation", "#14b8a6", "book-open"),
+    ("url", "#3b82f6", "link"),
+    ("credentials", "#ef4444", "key-round"),
+    ("data", "#8b5cf6", "database"),
+    ("communication", "#22c55e", "message-square"),
+    ("notes", "#eab308", "sticky-note"),
+    ("reference", "#64748b", "bookmark"),
+    ("creative", "#ec4899", "pen-tool"),
+    ("business", "#f97316", "briefcase"),
+    ("academic", "#a855f7", "graduation-cap"),
+    ("error_log", "#dc2626", "bug"),
+    ("command", "#10b981", "terminal"),
+    ("image", "#06b6d4", "image"),
+    ("other", "#9ca3af", "folder"),
+];
+
 /// Initialize the database and return the path to the created database file
 pub fn init_database(app_handle: AppHandle) -> AppResult<std::path::PathBuf> {
     let app_data_dir: PathBuf = match app_handle.path().app_data_dir() {
@@ -51,35 +203,874 @@ pub fn init_database(app_handle: AppHandle) -> AppResult<std::path::PathBuf> {
         }
     };
 
-    let links_table = r#"
-        create table if not exists clips (
-        id INTEGER PRIMARY KEY AUTOINCREMENT,
-        clip TEXT,
-        category TEXT,
-        summary TEXT,
-        tags TEXT,
-        created_at DATETIME DEFAULT CURRENT_TIMESTAMP
-        );"#;
-
-    let settings_table = r#"
-        CREATE TABLE if not exists settings (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            key TEXT UNIQUE,
-            value TEXT NOT NULL,
-            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
-        );"#;
-
-    let statements = vec![links_table, settings_table];
-
-    for (i, stmt) in statements.iter().enumerate() {
+    if let Err(e) = configure_connection(&conn) {
+        let error_msg = format!("Failed to configure database connection: {}", e);
+        eprintln!("{}", error_msg);
+        return Err(Box::new(Error::new(ErrorKind::Other, error_msg)));
+    }
+
+    init_schema(&conn)?;
+
+    println!("Database initialized");
+    Ok(db_path)
+}
+
+/// Bring a database up to the current schema: migrations, columns added since the
+/// first release, triggers, indexes and backfills. Safe to run on every start.
+pub(crate) fn init_schema(conn: &Connection) -> AppResult<()> {
+    // Databases from before schema versioning first get the columns added before it,
+    // and hashes for their clips; later migrations rely on both. Fresh databases get
+    // their tables from the first migration. Closed: new columns go in a migration,
+    // see the migrations module.
+    let added_columns = vec![
+        ("clips", "expires_at", "DATETIME"),
+        ("clips", "content_type", "TEXT"),
+        ("clips", "created_date", "TEXT"),
+        ("clips", "source_app", "TEXT"),
+        ("clips", "normalized_url", "TEXT"),
+        ("clips", "use_count", "INTEGER NOT NULL DEFAULT 1"),
+        ("clips", "summary_updated_at", "DATETIME"),
+        ("clips", "content_hash", "TEXT"),
+        ("clips", "remind_at", "DATETIME"),
+        ("clips", "thumbnail", "TEXT"),
+        ("clips", "is_pinned", "INTEGER NOT NULL DEFAULT 0"),
+        ("clips", "title", "TEXT"),
+        ("clips", "workspace", "TEXT"),
+        ("clips", "code_language", "TEXT"),
+        ("clips", "scheduled", "INTEGER NOT NULL DEFAULT 0"),
+    ];
+
+    let has_clips: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'clips')",
+        [],
+        |row| row.get(0),
+    )?;
+    for (table, column, definition) in added_columns.into_iter().filter(|_| has_clips) {
+        if let Err(e) = ensure_column(conn, table, column, definition) {
+            let error_msg = format!("Error adding column {}.{}: {}", table, column, e);
+            eprintln!("{}", error_msg);
+            return Err(Box::new(Error::new(ErrorKind::Other, error_msg)));
+        }
+    }
+
+    if has_clips {
+        if let Err(e) = backfill_content_hashes(conn) {
+            let error_msg = format!("Error backfilling content hashes: {}", e);
+            eprintln!("{}", error_msg);
+            return Err(Box::new(Error::new(ErrorKind::Other, error_msg)));
+        }
+    }
+
+    if let Err(e) = run_migrations(conn) {
+        let error_msg = format!("Error migrating database: {}", e);
+        eprintln!("{}", error_msg);
+        return Err(Box::new(Error::new(ErrorKind::Other, error_msg)));
+    }
+
+    // statements that depend on the added columns; all of them are safe to re-run.
+    // Triggers and indexes on columns from a migration belong in that migration.
+    let column_statements = vec![
+        // local calendar day of the capture, kept on the row so day grouping can use an index
+        r#"
+        CREATE TRIGGER IF NOT EXISTS clips_created_date AFTER INSERT ON clips
+        WHEN new.created_date IS NULL
+        BEGIN
+            UPDATE clips SET created_date = date(new.created_at, 'localtime') WHERE id = new.id;
+        END;"#,
+        "CREATE INDEX IF NOT EXISTS idx_clips_created_date ON clips(created_date);",
+        "UPDATE clips SET created_date = date(created_at, 'localtime') WHERE created_date IS NULL;",
+        r#"
+        UPDATE clips
+        SET content_type = CASE json_extract(clip, '$.type') WHEN 'image' THEN 'image' ELSE 'text' END
+        WHERE content_type IS NULL;"#,
+        "CREATE INDEX IF NOT EXISTS idx_clip_tags_tag ON clip_tags(tag_id);",
+        "CREATE INDEX IF NOT EXISTS idx_clips_normalized_url ON clips(normalized_url);",
+        "CREATE INDEX IF NOT EXISTS idx_clip_versions_clip ON clip_versions(clip_id);",
+        "CREATE INDEX IF NOT EXISTS idx_clips_remind_at ON clips(remind_at);",
+        "CREATE INDEX IF NOT EXISTS idx_clips_workspace ON clips(workspace);",
+        "CREATE INDEX IF NOT EXISTS idx_clips_category ON clips(category);",
+        "CREATE INDEX IF NOT EXISTS idx_clips_created_at ON clips(created_at);",
+        r#"
+        CREATE TRIGGER IF NOT EXISTS clips_versions_update AFTER UPDATE OF category, summary, tags ON clips
+        WHEN (old.category IS NOT new.category OR old.summary IS NOT new.summary OR old.tags IS NOT new.tags)
+            AND COALESCE((SELECT CAST(value AS INTEGER) FROM settings WHERE key = 'clip_version_retention'), 0) > 0
+        BEGIN
+            INSERT INTO clip_versions (clip_id, category, summary, tags)
+            VALUES (old.id, old.category, old.summary, old.tags);
+            DELETE FROM clip_versions
+            WHERE clip_id = old.id
+              AND id NOT IN (
+                  SELECT id FROM clip_versions WHERE clip_id = old.id ORDER BY id DESC
+                  LIMIT (SELECT CAST(value AS INTEGER) FROM settings WHERE key = 'clip_version_retention')
+              );
+        END;"#,
+        r#"
+        CREATE TRIGGER IF NOT EXISTS clips_versions_delete AFTER DELETE ON clips
+        BEGIN
+            DELETE FROM clip_versions WHERE clip_id = old.id;
+        END;"#,
+        r#"
+        CREATE TRIGGER IF NOT EXISTS clips_tags_insert AFTER INSERT ON clips
+        BEGIN
+            INSERT OR IGNORE INTO tags (name)
+            SELECT lower(trim(value)) FROM json_each(CASE WHEN json_valid(new.tags) THEN new.tags ELSE '[]' END)
+            WHERE trim(value) != '';
+            INSERT OR IGNORE INTO clip_tags (clip_id, tag_id)
+            SELECT new.id, tags.id FROM json_each(CASE WHEN json_valid(new.tags) THEN new.tags ELSE '[]' END) AS t
+            JOIN tags ON tags.name = lower(trim(t.value));
+        END;"#,
+        r#"
+        CREATE TRIGGER IF NOT EXISTS clips_tags_update AFTER UPDATE OF tags ON clips
+        BEGIN
+            DELETE FROM clip_tags WHERE clip_id = new.id;
+            INSERT OR IGNORE INTO tags (name)
+            SELECT lower(trim(value)) FROM json_each(CASE WHEN json_valid(new.tags) THEN new.tags ELSE '[]' END)
+            WHERE trim(value) != '';
+            INSERT OR IGNORE INTO clip_tags (clip_id, tag_id)
+            SELECT new.id, tags.id FROM json_each(CASE WHEN json_valid(new.tags) THEN new.tags ELSE '[]' END) AS t
+            JOIN tags ON tags.name = lower(trim(t.value));
+        END;"#,
+        r#"
+        CREATE TRIGGER IF NOT EXISTS clips_tags_delete AFTER DELETE ON clips
+        BEGIN
+            DELETE FROM clip_tags WHERE clip_id = old.id;
+        END;"#,
+        // rebuild from the JSON column, which stays the source of truth
+        r#"
+        INSERT OR IGNORE INTO tags (name)
+        SELECT lower(trim(t.value))
+        FROM clips, json_each(CASE WHEN json_valid(clips.tags) THEN clips.tags ELSE '[]' END) AS t
+        WHERE trim(t.value) != '';"#,
+        r#"
+        INSERT OR IGNORE INTO clip_tags (clip_id, tag_id)
+        SELECT clips.id, tags.id
+        FROM clips, json_each(CASE WHEN json_valid(clips.tags) THEN clips.tags ELSE '[]' END) AS t
+        JOIN tags ON tags.name = lower(trim(t.value));"#,
+    ];
+
+    for (i, stmt) in column_statements.iter().enumerate() {
         if let Err(e) = conn.execute(stmt, []) {
-            let error_msg = format!("Error executing statement #{}: {}", i + 1, e);
+            let error_msg = format!("Error executing column statement #{}: {}", i + 1, e);
             eprintln!("{}", error_msg);
             return Err(Box::new(Error::new(ErrorKind::Other, error_msg)));
         }
     }
 
-    println!("Database initialized");
-    Ok(db_path)
+    for (name, color, icon) in BUILTIN_CATEGORIES {
+        if let Err(e) = conn.execute(
+            "INSERT OR IGNORE INTO categories (name, color, icon) VALUES (?, ?, ?)",
+            rusqlite::params![name, color, icon],
+        ) {
+            let error_msg = format!("Error seeding category {}: {}", name, e);
+            eprintln!("{}", error_msg);
+            return Err(Box::new(Error::new(ErrorKind::Other, error_msg)));
+        }
+    }
+
+    if let Err(e) = backfill_normalized_urls(conn) {
+        let error_msg = format!("Error backfilling normalized URLs: {}", e);
+        eprintln!("{}", error_msg);
+        return Err(Box::new(Error::new(ErrorKind::Other, error_msg)));
+    }
+
+    if let Err(e) = backfill_code_languages(conn) {
+        let error_msg = format!("Error backfilling code languages: {}", e);
+        eprintln!("{}", error_msg);
+        return Err(Box::new(Error::new(ErrorKind::Other, error_msg)));
+    }
+
+    // optional, search falls back to substring matching without it
+    if let Err(e) = init_full_text_search(conn) {
+        eprintln!("Full-text search unavailable: {}", e);
+    }
+
+    // deletes that bypassed the triggers, e.g. from older versions
+    match remove_orphaned_links(conn) {
+        Ok(report) if report.total() > 0 => {
+            println!("Removed {} orphaned rows", report.total())
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("Failed to check for orphaned rows: {}", e),
+    }
+
+    Ok(())
+}
+
+/// Text of a clips row as indexed for full-text search, image data is base64 so
+/// only its metadata is indexed
+const FTS_VALUES: &str = r#"
+    CASE WHEN new.content_type = 'image' THEN NULL ELSE json_extract(new.clip, '$.content') END,
+    new.category, new.summary, new.tags, new.title"#;
+
+/// Create `clips_fts` and the triggers keeping it in sync with clips. Fails when
+/// the bundled SQLite was built without FTS5, which leaves the database usable.
+/// An index from before titles were searchable is dropped and rebuilt.
+fn init_full_text_search(conn: &Connection) -> rusqlite::Result<()> {
+    let has_title = conn
+        .prepare("PRAGMA table_info(clips_fts)")?
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(Result::ok)
+        .any(|name| name == "title");
+    if !has_title {
+        conn.execute_batch(
+            "DROP TRIGGER IF EXISTS clips_fts_insert;
+             DROP TRIGGER IF EXISTS clips_fts_update;
+             DROP TRIGGER IF EXISTS clips_fts_delete;
+             DROP TABLE IF EXISTS clips_fts;",
+        )?;
+    }
+
+    conn.execute(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS clips_fts USING fts5(content, category, summary, tags, title)",
+        [],
+    )?;
+
+    let statements = [
+        format!(
+            r#"
+            CREATE TRIGGER IF NOT EXISTS clips_fts_insert AFTER INSERT ON clips
+            BEGIN
+                INSERT INTO clips_fts (rowid, content, category, summary, tags, title) VALUES (new.id, {FTS_VALUES});
+            END;"#
+        ),
+        format!(
+            r#"
+            CREATE TRIGGER IF NOT EXISTS clips_fts_update AFTER UPDATE OF clip, category, summary, tags, content_type, title ON clips
+            BEGIN
+                DELETE FROM clips_fts WHERE rowid = old.id;
+                INSERT INTO clips_fts (rowid, content, category, summary, tags, title) VALUES (new.id, {FTS_VALUES});
+            END;"#
+        ),
+        r#"
+        CREATE TRIGGER IF NOT EXISTS clips_fts_delete AFTER DELETE ON clips
+        BEGIN
+            DELETE FROM clips_fts WHERE rowid = old.id;
+        END;"#
+            .to_string(),
+        // clips saved before the index existed
+        format!(
+            r#"
+            INSERT INTO clips_fts (rowid, content, category, summary, tags, title)
+            SELECT new.id, {FTS_VALUES}
+            FROM clips AS new
+            WHERE new.id NOT IN (SELECT rowid FROM clips_fts);"#
+        ),
+    ];
+
+    for stmt in &statements {
+        conn.execute(stmt, [])?;
+    }
+
+    Ok(())
+}
+
+/// Link clips saved before URL normalization existed have no normalized_url yet
+fn backfill_normalized_urls(conn: &Connection) -> rusqlite::Result<()> {
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT id, json_extract(clip, '$.content')
+        FROM clips
+        WHERE normalized_url IS NULL
+          AND content_type != 'image'
+          AND json_extract(clip, '$.content') LIKE 'http%'
+        "#,
+    )?;
+
+    let candidates = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    for (id, content) in candidates {
+        let content = content.unwrap_or_default();
+        if !is_url(&content) {
+            continue;
+        }
+        if let Some(normalized) = urls::normalize_url(&content) {
+            conn.execute(
+                "UPDATE clips SET normalized_url = ?, content_type = 'url' WHERE id = ?",
+                rusqlite::params![normalized, id],
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Apply every migration newer than the version recorded in `schema_version`,
+/// each in its own transaction together with the version it brings the schema to
+pub fn run_migrations(conn: &Connection) -> AppResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (version INTEGER PRIMARY KEY)",
+        [],
+    )?;
+
+    // the first versioned releases created the table without a key, so a version
+    // could be recorded twice
+    let keyed: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM pragma_table_info('schema_version') WHERE name = 'version' AND pk = 1)",
+        [],
+        |row| row.get(0),
+    )?;
+    if !keyed {
+        conn.execute_batch(
+            "BEGIN;
+             CREATE TABLE schema_version_keyed (version INTEGER PRIMARY KEY);
+             INSERT OR IGNORE INTO schema_version_keyed (version) SELECT version FROM schema_version;
+             DROP TABLE schema_version;
+             ALTER TABLE schema_version_keyed RENAME TO schema_version;
+             COMMIT;",
+        )
+        .map_err(|e| {
+            conn.execute_batch("ROLLBACK;").ok();
+            format!("Failed to add a key to schema_version: {}", e)
+        })?;
+    }
+    let current: usize = conn.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM schema_version",
+        [],
+        |row| row.get(0),
+    )?;
+
+    for (index, migration) in migrations::MIGRATIONS.iter().enumerate().skip(current) {
+        let version = index + 1;
+        conn.execute_batch(&format!(
+            "BEGIN;\n{}\nINSERT INTO schema_version (version) VALUES ({});\nCOMMIT;",
+            migration, version
+        ))
+        .map_err(|e| {
+            conn.execute_batch("ROLLBACK;").ok();
+            format!("Migration {} failed: {}", version, e)
+        })?;
+        println!("Migrated database to schema version {}", version);
+    }
+
+    Ok(())
+}
+
+fn ensure_column(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> rusqlite::Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(Result::ok)
+        .any(|name| name == column);
+
+    if !exists {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Hard-delete every clip whose expiry has passed and return the deleted ids,
+/// pinned or not; an expiry is set on purpose, for secrets
+pub fn prune_expired_clips(conn: &Connection) -> rusqlite::Result<Vec<i64>> {
+    let mut stmt = conn.prepare(
+        "DELETE FROM clips WHERE expires_at IS NOT NULL AND expires_at <= CURRENT_TIMESTAMP RETURNING id",
+    )?;
+    let ids = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<i64>>>()?;
+
+    Ok(ids)
+}
+
+/// Delete clips older than `max_age_days`, then the oldest ones past the newest
+/// `max_count`. A limit of 0 is no limit. Pinned clips are never deleted and do
+/// not count towards `max_count`.
+pub fn prune_clips(
+    conn: &Connection,
+    max_age_days: u32,
+    max_count: u32,
+) -> rusqlite::Result<Vec<i64>> {
+    let mut ids = Vec::new();
+
+    if max_age_days > 0 {
+        let mut stmt = conn.prepare(
+            "DELETE FROM clips WHERE is_pinned = 0 AND created_at < datetime('now', ?) RETURNING id",
+        )?;
+        let aged = stmt.query_map([format!("-{} days", max_age_days)], |row| row.get(0))?;
+        for id in aged {
+            ids.push(id?);
+        }
+    }
+
+    if max_count > 0 {
+        let mut stmt = conn.prepare(
+            r#"
+            DELETE FROM clips
+            WHERE id IN (
+                SELECT id FROM clips WHERE is_pinned = 0
+                ORDER BY created_at DESC, id DESC
+                LIMIT -1 OFFSET ?
+            )
+            RETURNING id
+            "#,
+        )?;
+        let surplus = stmt.query_map([max_count], |row| row.get(0))?;
+        for id in surplus {
+            ids.push(id?);
+        }
+    }
+
+    Ok(ids)
+}
+
+/// Rows removed by remove_orphans, per table
+#[derive(Debug, Default, Serialize)]
+pub struct RepairReport {
+    /// links to a clip or tag that no longer exists
+    pub clip_tags: usize,
+    /// versions of deleted clips
+    pub clip_versions: usize,
+    /// tags no clip uses
+    pub tags: usize,
+    /// full-text index entries of deleted clips
+    pub search_index: usize,
+}
+
+impl RepairReport {
+    pub fn total(&self) -> usize {
+        self.clip_tags + self.clip_versions + self.tags + self.search_index
+    }
+}
+
+/// Remove the rows of clip_tags and clip_versions that refer to deleted clips.
+/// Cheap enough to run on every start.
+pub fn remove_orphaned_links(conn: &Connection) -> rusqlite::Result<RepairReport> {
+    Ok(RepairReport {
+        clip_tags: conn.execute(
+            "DELETE FROM clip_tags WHERE clip_id NOT IN (SELECT id FROM clips) OR tag_id NOT IN (SELECT id FROM tags)",
+            [],
+        )?,
+        clip_versions: conn.execute(
+            "DELETE FROM clip_versions WHERE clip_id NOT IN (SELECT id FROM clips)",
+            [],
+        )?,
+        ..Default::default()
+    })
+}
+
+/// Remove every row left behind by a deleted clip, along with unused tags and
+/// full-text index entries. Run it in a transaction so a failure leaves no half repair.
+pub fn remove_orphans(conn: &Connection) -> rusqlite::Result<RepairReport> {
+    let mut report = remove_orphaned_links(conn)?;
+
+    report.tags = conn.execute(
+        "DELETE FROM tags WHERE id NOT IN (SELECT tag_id FROM clip_tags)",
+        [],
+    )?;
+
+    let has_search_index: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'clips_fts')",
+        [],
+        |row| row.get(0),
+    )?;
+    if has_search_index {
+        report.search_index = conn.execute(
+            "DELETE FROM clips_fts WHERE rowid NOT IN (SELECT id FROM clips)",
+            [],
+        )?;
+    }
+
+    Ok(report)
+}
+
+/// Periodically prune expired clips, and clips past the `max_clip_age_days` and
+/// `max_clip_count` settings, in the background for as long as the app runs.
+/// The first pass runs right away at startup.
+pub fn start_pruning_task(app_handle: AppHandle, db_path: PathBuf) {
+    thread::spawn(move || loop {
+        match open_connection(&db_path).and_then(|conn| prune_expired_clips(&conn)) {
+            Ok(ids) if !ids.is_empty() => {
+                println!("Pruned {} expired clips", ids.len());
+                let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+                app_handle.emit("clips-expired", &ids).ok();
+                app_handle.emit("tags-changed", ()).ok();
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("Failed to prune expired clips: {}", e),
+        }
+
+        let settings = app_handle.state::<SettingsManagerState>().0.clone();
+        let max_age_days = settings.get_u32("max_clip_age_days");
+        let max_count = settings.get_u32("max_clip_count");
+        match open_connection(&db_path).and_then(|conn| prune_clips(&conn, max_age_days, max_count))
+        {
+            Ok(ids) if !ids.is_empty() => {
+                println!("Pruned {} clips past the retention limits", ids.len());
+                let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+                app_handle.emit("clip-bulk-deleted", &ids).ok();
+                app_handle.emit("tags-changed", ()).ok();
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("Failed to prune old clips: {}", e),
+        }
+
+        match open_connection(&db_path).and_then(|conn| llm::cache::prune_llm_cache(&conn)) {
+            Ok(0) => {}
+            Ok(count) => println!("Pruned {} stale LLM cache entries", count),
+            Err(e) => eprintln!("Failed to prune the LLM cache: {}", e),
+        }
+
+        thread::sleep(PRUNE_INTERVAL);
+    });
+}
+
+/// Clips saved before content hashing existed have no content_hash yet
+fn backfill_content_hashes(conn: &Connection) -> rusqlite::Result<()> {
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT id, COALESCE(json_extract(clip, '$.type'), 'text'), json_extract(clip, '$.content')
+        FROM clips
+        WHERE content_hash IS NULL
+        "#,
+    )?;
+
+    let candidates = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    for (id, kind, content) in candidates {
+        let hash = hash_content(&kind, &content.unwrap_or_default());
+        conn.execute(
+            "UPDATE clips SET content_hash = ? WHERE id = ?",
+            rusqlite::params![hash, id],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Code clips saved before language detection existed have no code_language yet.
+/// Snippets no language is detected in are looked at again on every start, there
+/// are few enough of them.
+fn backfill_code_languages(conn: &Connection) -> rusqlite::Result<()> {
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT id, json_extract(clip, '$.content')
+        FROM clips
+        WHERE code_language IS NULL
+          AND category = 'code_snippet'
+          AND content_type != 'image'
+        "#,
+    )?;
+
+    let candidates = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    for (id, content) in candidates {
+        if let Some(language) = heuristic::detect_language(&content.unwrap_or_default()) {
+            conn.execute(
+                "UPDATE clips SET code_language = ? WHERE id = ?",
+                rusqlite::params![language, id],
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn versions(conn: &Connection) -> Vec<i64> {
+        conn.prepare("SELECT version FROM schema_version ORDER BY version")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    #[test]
+    fn migrations_run_once() {
+        let conn = Connection::open_in_memory().unwrap();
+        run_migrations(&conn).unwrap();
+        run_migrations(&conn).unwrap();
+        init_schema(&conn).unwrap();
+
+        let expected: Vec<i64> = (1..=migrations::MIGRATIONS.len() as i64).collect();
+        assert_eq!(versions(&conn), expected);
+        assert!(conn
+            .execute("INSERT INTO schema_version (version) VALUES (1)", [])
+            .is_err());
+    }
+
+    #[test]
+    fn keys_an_unkeyed_schema_version() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE schema_version (version INTEGER NOT NULL);
+             INSERT INTO schema_version (version) VALUES (1), (1);",
+        )
+        .unwrap();
+        // the tables of the first migration, as a database at version 1 has them
+        conn.execute_batch(migrations::MIGRATIONS[0]).unwrap();
+
+        run_migrations(&conn).unwrap();
+
+        let expected: Vec<i64> = (1..=migrations::MIGRATIONS.len() as i64).collect();
+        assert_eq!(versions(&conn), expected);
+    }
+
+    #[test]
+    fn merges_duplicate_clips_before_making_the_hash_unique() {
+        let conn = Connection::open_in_memory().unwrap();
+        // a database at version 5, from before content_hash was unique
+        conn.execute_batch("CREATE TABLE schema_version (version INTEGER PRIMARY KEY)")
+            .unwrap();
+        for (version, migration) in migrations::MIGRATIONS[..5].iter().enumerate() {
+            conn.execute_batch(migration).unwrap();
+            conn.execute(
+                "INSERT INTO schema_version (version) VALUES (?)",
+                [version + 1],
+            )
+            .unwrap();
+        }
+        conn.execute_batch(
+            r#"
+            INSERT INTO clips (id, clip, content_hash, tags, use_count, is_pinned, title, expires_at)
+            VALUES (1, '{}', 'same', '["a","b"]', 2, 1, 'Old title', NULL),
+                   (2, '{}', 'other', NULL, 1, 0, NULL, NULL),
+                   (3, '{}', 'same', '["b","c"]', 1, 0, NULL, '2000-01-01 00:00:00');
+            INSERT INTO clip_versions (clip_id, summary) VALUES (1, 'first');
+            "#,
+        )
+        .unwrap();
+
+        run_migrations(&conn).unwrap();
+
+        let ids: Vec<i64> = conn
+            .prepare("SELECT id FROM clips ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(ids, vec![2, 3]);
+
+        let (use_count, is_pinned, title, expires_at, tags): (
+            i64,
+            bool,
+            Option<String>,
+            Option<String>,
+            String,
+        ) = conn
+            .query_row(
+                "SELECT use_count, is_pinned, title, expires_at, tags FROM clips WHERE id = 3",
+                [],
+                |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                    ))
+                },
+            )
+            .unwrap();
+        assert_eq!(use_count, 3);
+        assert!(is_pinned);
+        assert_eq!(title.as_deref(), Some("Old title"));
+        assert_eq!(expires_at, None);
+        let mut tags: Vec<String> = serde_json::from_str(&tags).unwrap();
+        tags.sort();
+        assert_eq!(tags, vec!["a", "b", "c"]);
+
+        let version_of: i64 = conn
+            .query_row("SELECT clip_id FROM clip_versions", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version_of, 3);
+
+        assert!(conn
+            .execute(
+                "INSERT INTO clips (clip, content_hash) VALUES ('{}', 'same')",
+                []
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn moves_the_anthropic_key_to_its_new_name() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE schema_version (version INTEGER PRIMARY KEY)")
+            .unwrap();
+        for (version, migration) in migrations::MIGRATIONS[..6].iter().enumerate() {
+            conn.execute_batch(migration).unwrap();
+            conn.execute(
+                "INSERT INTO schema_version (version) VALUES (?)",
+                [version + 1],
+            )
+            .unwrap();
+        }
+        conn.execute(
+            "INSERT INTO settings (key, value) VALUES ('anthropic_api_key', 'sk-ant-old')",
+            [],
+        )
+        .unwrap();
+
+        run_migrations(&conn).unwrap();
+
+        let keys: Vec<(String, String)> = conn
+            .prepare("SELECT key, value FROM settings")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(
+            keys,
+            vec![(
+                "llm_api_key_anthropic".to_string(),
+                "sk-ant-old".to_string()
+            )]
+        );
+    }
+
+    /// A database file of its own under the temp dir, removed with its WAL files on drop
+    struct TempDb(PathBuf);
+
+    impl TempDb {
+        fn new(name: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("spiegel-{}-{}.db", name, std::process::id()));
+            let db = TempDb(path);
+            db.remove();
+            db
+        }
+
+        fn remove(&self) {
+            for suffix in ["", "-wal", "-shm"] {
+                let mut path = self.0.clone().into_os_string();
+                path.push(suffix);
+                std::fs::remove_file(path).ok();
+            }
+        }
+    }
+
+    impl Drop for TempDb {
+        fn drop(&mut self) {
+            self.remove();
+        }
+    }
+
+    #[test]
+    fn reads_and_writes_in_parallel_without_busy_errors() {
+        let db = TempDb::new("parallel");
+        init_schema(&open_connection(&db.0).unwrap()).unwrap();
+        const WRITES: i64 = 300;
+
+        let writer = {
+            let path = db.0.clone();
+            thread::spawn(move || -> rusqlite::Result<()> {
+                let conn = open_connection(&path)?;
+                for i in 0..WRITES {
+                    conn.execute(
+                        "INSERT INTO clips (clip, tags, content_hash) VALUES (?1, '[\"parallel\"]', ?2)",
+                        rusqlite::params![
+                            format!(r#"{{"type":"text","content":"clip {i}"}}"#),
+                            format!("hash{i}")
+                        ],
+                    )?;
+                }
+                Ok(())
+            })
+        };
+
+        let reader = {
+            let path = db.0.clone();
+            thread::spawn(move || -> rusqlite::Result<()> {
+                let conn = open_connection(&path)?;
+                let deadline = std::time::Instant::now() + Duration::from_secs(30);
+                let mut seen = 0;
+                while seen < WRITES && std::time::Instant::now() < deadline {
+                    let count: i64 =
+                        conn.query_row("SELECT COUNT(*) FROM clip_tags", [], |row| row.get(0))?;
+                    // readers see each committed write whole, never half of it
+                    let clips: i64 =
+                        conn.query_row("SELECT COUNT(*) FROM clips", [], |row| row.get(0))?;
+                    assert!(clips >= count);
+                    assert!(count >= seen);
+                    seen = count;
+                }
+                assert_eq!(seen, WRITES);
+                Ok(())
+            })
+        };
+
+        writer.join().unwrap().unwrap();
+        reader.join().unwrap().unwrap();
+
+        let journal_mode: String = open_connection(&db.0)
+            .unwrap()
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(journal_mode, "wal");
+    }
+
+    fn fts_ids(conn: &Connection, query: &str) -> Vec<i64> {
+        conn.prepare("SELECT rowid FROM clips_fts WHERE clips_fts MATCH ?1 ORDER BY rowid")
+            .unwrap()
+            .query_map([query], |row| row.get(0))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    #[test]
+    fn full_text_index_covers_titles() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO clips (clip, title, content_hash) VALUES ('{\"type\":\"text\",\"content\":\"abc\"}', 'quarterly report', 'h1')",
+            [],
+        )
+        .unwrap();
+        assert_eq!(fts_ids(&conn, "quarterly"), vec![1]);
+
+        conn.execute("UPDATE clips SET title = 'invoice' WHERE id = 1", [])
+            .unwrap();
+        assert!(fts_ids(&conn, "quarterly").is_empty());
+        assert_eq!(fts_ids(&conn, "invoice"), vec![1]);
+    }
+
+    #[test]
+    fn rebuilds_an_index_without_titles() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn.execute_batch(
+            "DROP TRIGGER clips_fts_insert;
+             DROP TRIGGER clips_fts_update;
+             DROP TRIGGER clips_fts_delete;
+             DROP TABLE clips_fts;
+             CREATE VIRTUAL TABLE clips_fts USING fts5(content, category, summary, tags);
+             INSERT INTO clips (clip, title, content_hash) VALUES ('{\"type\":\"text\",\"content\":\"abc\"}', 'quarterly report', 'h1');",
+        )
+        .unwrap();
+
+        init_full_text_search(&conn).unwrap();
+        assert_eq!(fts_ids(&conn, "quarterly"), vec![1]);
+        assert_eq!(fts_ids(&conn, "abc"), vec![1]);
+    }
 }
@@ -0,0 +1,214 @@
+//! Schema changes in the order they are applied. A migration runs once, its
+//! index in `MIGRATIONS` plus one is the version recorded in `schema_version`.
+//! Never edit a migration that has shipped, add a new one instead.
+//!
+//! New columns, tables, indexes and triggers go here. The `ensure_column` list in
+//! `init_schema` only repairs databases from before versioning and does not grow.
+
+/// The schema from before versioning. Tables are created only when missing, so
+/// databases that predate `schema_version` take it as already applied; their
+/// missing columns are still added by `ensure_column`.
+const MIGRATION_1: &str = r#"
+    create table if not exists clips (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    clip TEXT,
+    category TEXT,
+    summary TEXT,
+    tags TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    expires_at DATETIME,
+    content_type TEXT,
+    created_date TEXT,
+    source_app TEXT,
+    normalized_url TEXT,
+    use_count INTEGER NOT NULL DEFAULT 1,
+    summary_updated_at DATETIME,
+    content_hash TEXT,
+    remind_at DATETIME,
+    thumbnail TEXT,
+    is_pinned INTEGER NOT NULL DEFAULT 0,
+    title TEXT,
+    workspace TEXT,
+    code_language TEXT,
+    scheduled INTEGER NOT NULL DEFAULT 0
+    );
+
+    CREATE TABLE if not exists settings (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        key TEXT UNIQUE,
+        value TEXT NOT NULL,
+        created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+        updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+    );
+
+    CREATE TABLE if not exists categories (
+        name TEXT PRIMARY KEY,
+        color TEXT,
+        icon TEXT
+    );
+
+    CREATE TABLE if not exists search_history (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        query TEXT NOT NULL,
+        filters TEXT,
+        result_count INTEGER NOT NULL DEFAULT 0,
+        searched_at DATETIME DEFAULT CURRENT_TIMESTAMP
+    );
+
+    -- how often each category was picked for a clip feature (source app, domain, tag, ...)
+    CREATE TABLE if not exists category_stats (
+        feature TEXT NOT NULL,
+        category TEXT NOT NULL,
+        count INTEGER NOT NULL DEFAULT 0,
+        PRIMARY KEY (feature, category)
+    );
+
+    -- tags normalized out of the clips.tags JSON array, kept in sync by triggers
+    CREATE TABLE if not exists tags (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL UNIQUE
+    );
+
+    CREATE TABLE if not exists clip_tags (
+        clip_id INTEGER NOT NULL,
+        tag_id INTEGER NOT NULL,
+        PRIMARY KEY (clip_id, tag_id)
+    );
+
+    -- previous category/summary/tags of a clip, capped by the clip_version_retention setting
+    CREATE TABLE if not exists clip_versions (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        clip_id INTEGER NOT NULL,
+        category TEXT,
+        summary TEXT,
+        tags TEXT,
+        created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+    );
+
+    -- one row per LLM request, for tracking spend
+    CREATE TABLE if not exists llm_usage (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+        model TEXT NOT NULL,
+        stage TEXT NOT NULL,
+        input_tokens INTEGER,
+        output_tokens INTEGER,
+        cost REAL,
+        latency_ms INTEGER NOT NULL,
+        success INTEGER NOT NULL
+    );
+"#;
+
+/// Favorites, and soft deletes through `deleted_at`
+const MIGRATION_2: &str = r#"
+    ALTER TABLE clips ADD COLUMN is_favorite INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE clips ADD COLUMN deleted_at DATETIME;
+"#;
+
+/// When the content was put on the clipboard, as opposed to captured
+const MIGRATION_3: &str = r#"
+    ALTER TABLE clips ADD COLUMN copied_at DATETIME;
+"#;
+
+/// Text clips cut down by `capture_line_limit`, and how many lines they had
+const MIGRATION_4: &str = r#"
+    ALTER TABLE clips ADD COLUMN is_truncated INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE clips ADD COLUMN original_line_count INTEGER;
+"#;
+
+/// Categories and summaries the LLM gave for a content hash, see `llm::cache`
+const MIGRATION_5: &str = r#"
+    CREATE TABLE llm_cache (
+        content_hash TEXT PRIMARY KEY,
+        category TEXT,
+        tags TEXT,
+        summary TEXT,
+        cached_at DATETIME DEFAULT CURRENT_TIMESTAMP
+    );
+"#;
+
+/// One clip per content: copies saved before the hash was unique are folded into
+/// the newest one, which keeps what the others add, and content_hash becomes
+/// unique so save_clip can bump a clip instead of storing it twice. Relies on
+/// `init_schema` hashing the clips of older databases before migrating.
+const MIGRATION_6: &str = r#"
+    CREATE TEMP TABLE clip_duplicates AS
+        SELECT id, (SELECT MAX(d.id) FROM clips d WHERE d.content_hash = clips.content_hash) AS keep_id
+        FROM clips
+        WHERE content_hash IS NOT NULL
+          AND id < (SELECT MAX(d.id) FROM clips d WHERE d.content_hash = clips.content_hash);
+
+    UPDATE clips
+    SET use_count = (SELECT SUM(COALESCE(d.use_count, 1)) FROM clips d WHERE d.content_hash = clips.content_hash),
+        is_pinned = (SELECT MAX(d.is_pinned) FROM clips d WHERE d.content_hash = clips.content_hash),
+        is_favorite = (SELECT MAX(d.is_favorite) FROM clips d WHERE d.content_hash = clips.content_hash),
+        title = COALESCE(title, (SELECT d.title FROM clips d WHERE d.content_hash = clips.content_hash AND d.title IS NOT NULL ORDER BY d.id DESC LIMIT 1)),
+        summary = COALESCE(NULLIF(summary, ''), (SELECT d.summary FROM clips d WHERE d.content_hash = clips.content_hash AND d.summary != '' ORDER BY d.id DESC LIMIT 1)),
+        workspace = COALESCE(workspace, (SELECT d.workspace FROM clips d WHERE d.content_hash = clips.content_hash AND d.workspace IS NOT NULL ORDER BY d.id DESC LIMIT 1)),
+        -- the earliest reminder, and no expiry if any copy was kept for good
+        remind_at = (SELECT MIN(d.remind_at) FROM clips d WHERE d.content_hash = clips.content_hash),
+        expires_at = (SELECT CASE WHEN COUNT(*) > COUNT(d.expires_at) THEN NULL ELSE MAX(d.expires_at) END FROM clips d WHERE d.content_hash = clips.content_hash),
+        tags = (
+            SELECT CASE WHEN COUNT(*) = 0 THEN clips.tags ELSE json_group_array(DISTINCT t.value) END
+            FROM clips d, json_each(CASE WHEN json_valid(d.tags) THEN d.tags ELSE '[]' END) AS t
+            WHERE d.content_hash = clips.content_hash
+        )
+    WHERE id IN (SELECT keep_id FROM clip_duplicates);
+
+    UPDATE clip_versions
+    SET clip_id = (SELECT keep_id FROM clip_duplicates WHERE clip_duplicates.id = clip_versions.clip_id)
+    WHERE clip_id IN (SELECT id FROM clip_duplicates);
+
+    DELETE FROM clips WHERE id IN (SELECT id FROM clip_duplicates);
+    DROP TABLE clip_duplicates;
+
+    DROP INDEX IF EXISTS idx_clips_content_hash;
+    CREATE UNIQUE INDEX IF NOT EXISTS idx_clips_content_hash_unique ON clips(content_hash);
+"#;
+
+/// The Anthropic key was first stored as `anthropic_api_key`, it lives next to
+/// `llm_api_key` now
+const MIGRATION_7: &str = r#"
+    UPDATE settings SET key = 'llm_api_key_anthropic'
+    WHERE key = 'anthropic_api_key'
+      AND NOT EXISTS (SELECT 1 FROM settings WHERE key = 'llm_api_key_anthropic');
+    DELETE FROM settings WHERE key = 'anthropic_api_key';
+"#;
+
+/// A deleted or expired clip takes its cached LLM answers along, unless another
+/// clip still has the content
+const MIGRATION_8: &str = r#"
+    CREATE TRIGGER IF NOT EXISTS clips_llm_cache_delete AFTER DELETE ON clips
+    WHEN old.content_hash IS NOT NULL
+    BEGIN
+        DELETE FROM llm_cache
+        WHERE content_hash = old.content_hash
+          AND NOT EXISTS (SELECT 1 FROM clips WHERE content_hash = old.content_hash);
+    END;
+"#;
+
+/// `dedupe_urls` lost its "always_new" choice, which behaved as "off": content is
+/// unique, so an identical link is bumped either way
+const MIGRATION_9: &str = r#"
+    UPDATE settings SET value = 'off' WHERE key = 'dedupe_urls' AND value = 'always_new';
+"#;
+
+/// Cached LLM answers outlive their clip again, so copying a deleted link once
+/// more reuses them; they expire with `llm_cache_ttl_hours`, and panic_clear
+/// removes the ones of a secret itself
+const MIGRATION_10: &str = r#"
+    DROP TRIGGER IF EXISTS clips_llm_cache_delete;
+"#;
+
+pub const MIGRATIONS: &[&str] = &[
+    MIGRATION_1,
+    MIGRATION_2,
+    MIGRATION_3,
+    MIGRATION_4,
+    MIGRATION_5,
+    MIGRATION_6,
+    MIGRATION_7,
+    MIGRATION_8,
+    MIGRATION_9,
+    MIGRATION_10,
+];
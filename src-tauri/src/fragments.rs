@@ -0,0 +1,293 @@
+use crate::database;
+use crate::heuristic;
+use crate::settings::SettingsManager;
+use crate::shortcut::{content_hash, is_url, Clip};
+use crate::AppState;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::path::Path;
+use tauri::{AppHandle, Emitter, State};
+
+/// Bounds for the `fragment_window_seconds` setting
+pub const MIN_WINDOW_SECONDS: u32 = 5;
+pub const MAX_WINDOW_SECONDS: u32 = 600;
+
+/// Shortest run of text shared by the end of one capture and the start of the
+/// next that counts as the two overlapping
+const MIN_OVERLAP_CHARS: usize = 20;
+/// Longest overlap looked for, keeps comparing large captures cheap
+const MAX_OVERLAP_BYTES: usize = 4096;
+
+/// Sent in "suggest" mode when a capture looks like it continues the one before it
+#[derive(Debug, Clone, Serialize)]
+pub struct FragmentSuggestion {
+    pub previous_id: i64,
+    pub fragment_id: i64,
+}
+
+/// How `fragment_detection` treats captures that continue the previous one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FragmentMode {
+    Off,
+    Suggest,
+    Merge,
+}
+
+impl FragmentMode {
+    pub fn from_settings(settings: &SettingsManager) -> Self {
+        match settings.get_string("fragment_detection").as_str() {
+            "suggest" => FragmentMode::Suggest,
+            "merge" => FragmentMode::Merge,
+            _ => FragmentMode::Off,
+        }
+    }
+}
+
+/// `previous` with `next` joined on when `next` reads as its continuation: either
+/// the two overlap, as when a selection was extended, or `previous` stops
+/// mid-sentence and `next` picks up in lowercase
+pub fn continuation(previous: &str, next: &str) -> Option<String> {
+    if previous.trim().is_empty() || next.trim().is_empty() || previous == next {
+        return None;
+    }
+
+    // a longer selection of the same text holds the whole previous capture
+    if next.starts_with(previous) {
+        return Some(next.to_string());
+    }
+
+    let overlap = next
+        .char_indices()
+        .map(|(index, _)| index)
+        .chain(std::iter::once(next.len()))
+        .take_while(|&end| end <= previous.len().min(MAX_OVERLAP_BYTES))
+        .filter(|&end| end >= MIN_OVERLAP_CHARS && end < next.len())
+        .filter(|&end| previous.ends_with(&next[..end]))
+        .last();
+    if let Some(end) = overlap {
+        return Some(format!("{}{}", previous, &next[end..]));
+    }
+
+    let stops_mid_sentence = previous
+        .trim_end()
+        .chars()
+        .last()
+        .is_some_and(|c| c.is_alphanumeric() || c == ',');
+    let picks_up_lowercase = next
+        .trim_start()
+        .chars()
+        .next()
+        .is_some_and(char::is_lowercase);
+
+    if stops_mid_sentence && picks_up_lowercase {
+        let separator = if previous.ends_with(char::is_whitespace) {
+            ""
+        } else {
+            " "
+        };
+        return Some(format!("{}{}{}", previous, separator, next.trim_start()));
+    }
+
+    None
+}
+
+/// The newest clip when it is plain text captured within the last `window_seconds`
+fn previous_text_clip(
+    conn: &Connection,
+    window_seconds: u32,
+) -> rusqlite::Result<Option<(i64, String)>> {
+    conn.query_row(
+        r#"
+        SELECT id, json_extract(clip, '$.content')
+        FROM clips
+        WHERE id = (SELECT MAX(id) FROM clips)
+          AND content_type = 'text'
+          AND created_at >= datetime('now', ?)
+        "#,
+        params![format!("-{} seconds", window_seconds)],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .optional()
+}
+
+/// The clip the capture continues and the two merged, when fragment detection
+/// is on and the capture is plain text
+pub fn find_continuation(
+    db_path: &Path,
+    settings: &SettingsManager,
+    clip: &Clip,
+) -> rusqlite::Result<Option<(i64, String)>> {
+    let plain = match clip {
+        Clip::Text { plain } if !is_url(plain) => plain,
+        _ => return Ok(None),
+    };
+    if FragmentMode::from_settings(settings) == FragmentMode::Off {
+        return Ok(None);
+    }
+
+    let conn = database::open_connection(db_path)?;
+    let Some((id, previous)) =
+        previous_text_clip(&conn, settings.get_u32("fragment_window_seconds"))?
+    else {
+        return Ok(None);
+    };
+
+    Ok(continuation(&previous, plain).map(|merged| (id, merged)))
+}
+
+/// Replace the text of a clip with `text`, keeping the columns derived from it in
+/// sync. Content is unique, so another clip that already has this text is folded
+/// into this one, which takes over its use count and pin; its id is returned.
+pub fn set_clip_text(conn: &Connection, id: i64, text: &str) -> rusqlite::Result<Option<i64>> {
+    conn.execute_batch("SAVEPOINT set_clip_text")?;
+    let result = fold_and_set_text(conn, id, text);
+    match result {
+        Ok(_) => conn.execute_batch("RELEASE set_clip_text")?,
+        Err(_) => conn.execute_batch("ROLLBACK TO set_clip_text; RELEASE set_clip_text")?,
+    }
+    result
+}
+
+fn fold_and_set_text(conn: &Connection, id: i64, text: &str) -> rusqlite::Result<Option<i64>> {
+    let hash = content_hash(&Clip::Text {
+        plain: text.to_string(),
+    });
+
+    let duplicate: Option<i64> = conn
+        .query_row(
+            "SELECT id FROM clips WHERE content_hash = ?1 AND id != ?2",
+            params![hash, id],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    if let Some(duplicate) = duplicate {
+        conn.execute(
+            r#"
+            UPDATE clips
+            SET use_count = COALESCE(use_count, 1)
+                    + (SELECT COALESCE(use_count, 1) FROM clips WHERE id = ?2),
+                is_pinned = MAX(is_pinned, (SELECT is_pinned FROM clips WHERE id = ?2))
+            WHERE id = ?1
+            "#,
+            params![id, duplicate],
+        )?;
+        conn.execute("DELETE FROM clips WHERE id = ?", params![duplicate])?;
+    }
+
+    conn.execute(
+        r#"
+        UPDATE clips
+        SET clip = json_remove(json_set(clip, '$.content', ?1), '$.raw'),
+            content_hash = ?2,
+            code_language = ?3
+        WHERE id = ?4
+        "#,
+        params![text, hash, heuristic::detect_language(text), id],
+    )?;
+    Ok(duplicate)
+}
+
+/// Fold `fragment_id` into `target_id`, appending its text and deleting it. The
+/// text is joined as a continuation when it reads as one, on a new line otherwise.
+#[tauri::command]
+pub fn merge_clips(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    target_id: i64,
+    fragment_id: i64,
+) -> Result<(), String> {
+    if target_id == fragment_id {
+        return Err("Cannot merge a clip into itself".to_string());
+    }
+
+    let mut conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    let text_of = |id: i64| -> Result<String, String> {
+        let (content_type, content): (Option<String>, Option<String>) = conn
+            .query_row(
+                "SELECT content_type, json_extract(clip, '$.content') FROM clips WHERE id = ?",
+                params![id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => "Item not found".to_string(),
+                e => format!("Failed to get clip: {e}"),
+            })?;
+        if content_type.as_deref() == Some("image") {
+            return Err("Only text clips can be merged".to_string());
+        }
+        Ok(content.unwrap_or_default())
+    };
+
+    let target = text_of(target_id)?;
+    let fragment = text_of(fragment_id)?;
+    let merged =
+        continuation(&target, &fragment).unwrap_or_else(|| format!("{}\n{}", target, fragment));
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {e}"))?;
+    // the fragment itself is no duplicate, it goes away whatever its content
+    tx.execute("DELETE FROM clips WHERE id = ?", params![fragment_id])
+        .map_err(|e| format!("Failed to delete fragment: {e}"))?;
+    let folded = set_clip_text(&tx, target_id, &merged)
+        .map_err(|e| format!("Failed to merge clips: {e}"))?;
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {e}"))?;
+
+    for id in std::iter::once(fragment_id).chain(folded) {
+        app_handle.emit("clip-deleted", id.to_string()).ok();
+    }
+    app_handle.emit("clip-updated", target_id.to_string()).ok();
+    app_handle.emit("tags-changed", ()).ok();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(conn: &Connection, text: &str, use_count: i64, is_pinned: bool) -> i64 {
+        let clip = Clip::Text {
+            plain: text.to_string(),
+        };
+        conn.execute(
+            "INSERT INTO clips (clip, content_type, content_hash, use_count, is_pinned) VALUES (?1, 'text', ?2, ?3, ?4)",
+            params![
+                serde_json::json!({"type": "text", "content": text}).to_string(),
+                content_hash(&clip),
+                use_count,
+                is_pinned
+            ],
+        )
+        .unwrap();
+        conn.last_insert_rowid()
+    }
+
+    #[test]
+    fn setting_text_another_clip_has_folds_that_clip_in() {
+        let conn = Connection::open_in_memory().unwrap();
+        database::init_schema(&conn).unwrap();
+        let existing = insert(&conn, "hello world", 3, true);
+        let edited = insert(&conn, "hello", 1, false);
+
+        assert_eq!(
+            set_clip_text(&conn, edited, "hello world").unwrap(),
+            Some(existing)
+        );
+
+        let (count, use_count, is_pinned): (i64, i64, bool) = conn
+            .query_row(
+                "SELECT COUNT(*), MAX(use_count), MAX(is_pinned) FROM clips",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!((count, use_count, is_pinned), (1, 4, true));
+
+        assert_eq!(set_clip_text(&conn, edited, "hello again").unwrap(), None);
+    }
+}
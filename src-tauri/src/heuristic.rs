@@ -0,0 +1,289 @@
+use crate::ansi;
+use crate::llm::CategoryResponse;
+use crate::shortcut::{is_url, Clip};
+use std::collections::{HashMap, HashSet};
+use url::Url;
+
+const CODE_KEYWORDS: &[&str] = &[
+    "fn ",
+    "let ",
+    "const ",
+    "function ",
+    "def ",
+    "class ",
+    "import ",
+    "return ",
+    "public ",
+    "private ",
+    "#include",
+    "=>",
+    "::",
+    "SELECT ",
+    "INSERT ",
+    "CREATE TABLE",
+];
+
+const COMMAND_PREFIXES: &[&str] = &[
+    "$ ", "sudo ", "git ", "npm ", "npx ", "yarn ", "pnpm ", "cargo ", "docker ", "kubectl ",
+    "brew ", "pip ", "cd ", "ls ", "curl ", "ssh ", "make ", "apt ",
+];
+
+const ERROR_MARKERS: &[&str] = &[
+    "Traceback (most recent call last)",
+    "Exception",
+    "panicked at",
+    "error[E",
+    "Error:",
+    "ERROR",
+    "FATAL",
+    "Segmentation fault",
+    "Uncaught",
+    "stack trace",
+];
+
+const CREDENTIAL_MARKERS: &[&str] = &[
+    "-----BEGIN",
+    "sk-",
+    "ghp_",
+    "xoxb-",
+    "AKIA",
+    "password",
+    "passwd",
+    "api_key",
+    "apikey",
+    "secret",
+    "token",
+];
+
+const GREETINGS: &[&str] = &[
+    "Hi ",
+    "Hello",
+    "Hey ",
+    "Dear ",
+    "Best regards",
+    "Regards,",
+    "Thanks,",
+    "Cheers,",
+];
+
+/// Score every category this clip plausibly belongs to, highest first, without calling an LLM
+pub fn score_categories(clip: &Clip) -> Vec<(String, f64)> {
+    let mut scores: HashMap<&str, f64> = HashMap::new();
+
+    match clip {
+        Clip::Image { .. } => {
+            scores.insert("image", 1.0);
+        }
+        Clip::Text { plain } => {
+            let text = ansi::strip_ansi(plain);
+            let trimmed = text.trim();
+
+            if is_url(trimmed) {
+                scores.insert("url", 0.95);
+            } else {
+                score_text(trimmed, ansi::contains_ansi(plain), &mut scores);
+            }
+        }
+    }
+
+    scores.entry("other").or_insert(0.1);
+
+    let mut ranked: Vec<(String, f64)> = scores
+        .into_iter()
+        .map(|(category, score)| (category.to_string(), score))
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranked
+}
+
+fn score_text(text: &str, from_terminal: bool, scores: &mut HashMap<&'static str, f64>) {
+    let lines: Vec<&str> = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    let lower = text.to_lowercase();
+
+    if ERROR_MARKERS.iter().any(|marker| text.contains(marker)) {
+        scores.insert("error_log", 0.85);
+    }
+
+    if CREDENTIAL_MARKERS
+        .iter()
+        .any(|marker| lower.contains(&marker.to_lowercase()))
+        && lines.len() <= 5
+    {
+        scores.insert("credentials", 0.8);
+    }
+
+    if lines.len() <= 3
+        && COMMAND_PREFIXES.iter().any(|prefix| {
+            lines
+                .iter()
+                .any(|line| line.trim_start().starts_with(prefix))
+        })
+    {
+        scores.insert("command", 0.8);
+    } else if from_terminal {
+        scores.insert("command", 0.5);
+    }
+
+    let code_score = code_density(text);
+    if code_score > 0.0 {
+        scores.insert("code_snippet", code_score);
+    }
+
+    if serde_json::from_str::<serde_json::Value>(text).is_ok() && text.starts_with(['{', '[']) {
+        scores.insert("data", 0.7);
+    } else if looks_tabular(&lines) {
+        scores.insert("data", 0.65);
+    }
+
+    if GREETINGS.iter().any(|greeting| text.contains(greeting)) {
+        scores.insert("communication", 0.6);
+    }
+
+    if text.contains('@') && text.contains('.') && text.len() < 200 && lines.len() <= 4 {
+        scores.insert("reference", 0.5);
+    }
+
+    if lower.contains("todo") || text.contains("- [ ]") || (lines.len() <= 2 && text.len() < 120) {
+        scores.entry("notes").or_insert(0.35);
+    }
+
+    if text.len() > 600 && code_score < 0.3 {
+        scores.entry("documentation").or_insert(0.3);
+    }
+}
+
+/// Share of code-like signals: punctuation typical for code plus common keywords
+fn code_density(text: &str) -> f64 {
+    if text.is_empty() {
+        return 0.0;
+    }
+
+    let symbols = text
+        .chars()
+        .filter(|c| matches!(c, '{' | '}' | ';' | '(' | ')' | '=' | '<' | '>'))
+        .count() as f64;
+    let symbol_ratio = symbols / text.chars().count() as f64;
+
+    let keywords = CODE_KEYWORDS
+        .iter()
+        .filter(|keyword| text.contains(*keyword))
+        .count() as f64;
+
+    (symbol_ratio * 8.0 + keywords * 0.15).min(0.9)
+}
+
+/// Several lines sharing the same number of commas or tabs
+fn looks_tabular(lines: &[&str]) -> bool {
+    if lines.len() < 3 {
+        return false;
+    }
+
+    [',', '\t'].iter().any(|separator| {
+        let first = lines[0].matches(*separator).count();
+        first > 0
+            && lines
+                .iter()
+                .all(|line| line.matches(*separator).count() == first)
+    })
+}
+
+/// Snippets that each point at one programming language
+const LANGUAGE_MARKERS: &[(&str, &[&str])] = &[
+    ("rust", &["fn ", "let mut ", "impl ", "::"]),
+    ("python", &["def ", "import ", "self.", "elif "]),
+    ("javascript", &["const ", "=>", "function ", "console.log"]),
+    ("sql", &["SELECT ", "INSERT INTO", "CREATE TABLE", "WHERE "]),
+    ("shell", &["sudo ", "#!/bin/", "echo ", "export "]),
+];
+
+/// A single marker is too weak a signal, "::" or "=>" show up in several languages
+const MIN_LANGUAGE_MARKERS: usize = 2;
+
+fn marker_count(text: &str, markers: &[&str]) -> usize {
+    markers
+        .iter()
+        .filter(|marker| text.contains(*marker))
+        .count()
+}
+
+/// The language with the most markers in the text, if any has enough of them
+pub fn detect_language(text: &str) -> Option<&'static str> {
+    let text = ansi::strip_ansi(text);
+    LANGUAGE_MARKERS
+        .iter()
+        .map(|(language, markers)| (*language, marker_count(&text, markers)))
+        .filter(|(_, count)| *count >= MIN_LANGUAGE_MARKERS)
+        .max_by_key(|(_, count)| *count)
+        .map(|(language, _)| language)
+}
+
+/// Tags that can be read off the content itself
+pub fn suggest_tags(clip: &Clip) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+
+    match clip {
+        Clip::Image { .. } => tags.push("screenshot".to_string()),
+        Clip::Text { plain } => {
+            let text = ansi::strip_ansi(plain);
+            let trimmed = text.trim();
+
+            if let Some(domain) = url_domain(trimmed) {
+                tags.extend(
+                    domain
+                        .trim_start_matches("www.")
+                        .split('.')
+                        .next()
+                        .map(|name| name.to_string()),
+                );
+            }
+
+            for (language, markers) in LANGUAGE_MARKERS {
+                if marker_count(trimmed, markers) >= MIN_LANGUAGE_MARKERS {
+                    tags.push(language.to_string());
+                }
+            }
+
+            if ansi::contains_ansi(plain) {
+                tags.push("terminal".to_string());
+            }
+        }
+    }
+
+    // the same tag can come from several markers, keep its first occurrence
+    let mut seen = HashSet::new();
+    tags.retain(|tag| seen.insert(tag.clone()));
+    tags
+}
+
+/// Best local guess used wherever an LLM answer is unavailable
+pub fn categorize(clip: &Clip) -> CategoryResponse {
+    let category = score_categories(clip)
+        .into_iter()
+        .next()
+        .map(|(category, _)| category)
+        .unwrap_or_else(|| "other".to_string());
+
+    let mut tags = suggest_tags(clip);
+    if tags.is_empty() {
+        tags.push("uncategorized".to_string());
+    }
+
+    CategoryResponse {
+        category,
+        tags,
+        ..Default::default()
+    }
+}
+
+pub fn url_domain(text: &str) -> Option<String> {
+    if !is_url(text) {
+        return None;
+    }
+    Url::parse(text)
+        .ok()?
+        .host_str()
+        .map(|host| host.to_lowercase())
+}
@@ -1,17 +1,38 @@
+mod ansi;
+mod benchmark;
 mod commands;
 mod database;
+mod fragments;
+mod heuristic;
 mod llm;
+mod maintenance;
+mod permissions;
+mod providers;
+mod reminders;
+mod rules;
+mod schedule;
+mod search;
 mod settings;
 mod shortcut;
+mod stats;
+mod suggest;
+mod thumbnails;
+mod toolbar;
+mod transforms;
+mod tray;
+mod urls;
+mod versions;
+mod workspaces;
 
-use std::env;
 use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tauri::Manager;
-use tauri_plugin_global_shortcut::GlobalShortcutExt;
 
 #[derive(Clone)]
 pub struct AppState {
     pub db_path: PathBuf,
+    /// unix time the app was started at, clips since then make up the current session
+    pub session_started_at: i64,
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_poPcartint)]
@@ -22,55 +43,146 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(
             tauri_plugin_global_shortcut::Builder::new()
-                .with_handler(move |app, _, event| {
-                    shortcut::handle_shortcut(app.app_handle(), event);
+                .with_handler(move |app, shortcut, event| {
+                    shortcut::handle_shortcut(app.app_handle(), shortcut, event);
                 })
                 .build(),
         )
         .setup(|app| {
             let db_path = database::init_database(app.app_handle().clone())?;
+            let session_started_at = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs() as i64)
+                .unwrap_or(0);
+            settings::init_settings(db_path.clone(), app.app_handle().clone())?;
+            database::set_max_connections(
+                app.state::<settings::SettingsManagerState>()
+                    .0
+                    .get_u32("max_db_connections"),
+            );
             app.manage(AppState {
                 db_path: db_path.clone(),
+                session_started_at,
             });
-            settings::init_settings(db_path, app.app_handle().clone())?;
+            llm::init_llm(
+                db_path.clone(),
+                app.state::<settings::SettingsManagerState>().0.clone(),
+            );
+            database::start_pruning_task(app.app_handle().clone(), db_path.clone());
+            reminders::start_reminder_task(app.app_handle().clone(), db_path.clone());
+            search::init_quick_search(app.app_handle().clone(), db_path.clone());
+            suggest::init_tag_index(app.app_handle().clone(), db_path);
+            maintenance::init_maintenance(app.app_handle());
 
-            let settings_state = app.state::<settings::SettingsManagerState>();
-
-            // openai lib reads openai api key from env var so we need to read from db
-            if let Some(api_key) = settings_state.0.get_setting("llm_api_key") {
-                if !api_key.is_empty() {
-                    env::set_var("OPENAI_API_KEY", api_key);
-                    println!("OpenAI API key loaded from database");
-                } else {
-                    println!("Warning: OpenAI API key is empty in database");
-                }
-            } else {
-                println!("Warning: No OpenAI API key found in database");
+            if !llm::has_credentials() {
+                println!("Warning: No API key configured for the LLM provider, clips are categorized locally");
             }
 
-            let hotkey_str = settings_state.0.get_global_hotkey();
-
-            let shortcut = shortcut::parse_hotkey_string(&hotkey_str)
-                .map_err(|e| format!("Failed to parse hotkey '{}': {}", hotkey_str, e))?;
+            app.manage(shortcut::HotkeyActionsState::default());
+            shortcut::register_hotkeys(app.app_handle())?;
+            shortcut::start_wake_watcher(app.app_handle().clone());
 
-            app.global_shortcut().register(shortcut)?;
+            tray::init_tray(app.app_handle())?;
+            schedule::init_scheduled_capture(app.app_handle());
 
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             commands::get_items,
+            commands::count_items,
+            commands::get_items_filtered,
+            commands::get_recent_clips,
+            commands::get_clips_by_hash,
+            commands::get_session_clips,
+            commands::get_raw_clip,
+            commands::get_clip_preview,
+            commands::get_item,
             commands::submit_clip,
             commands::delete_item,
+            commands::bulk_delete_items,
+            commands::panic_clear,
+            commands::summarize_clip,
+            commands::update_item,
+            commands::set_expiry,
+            commands::set_title,
+            commands::set_pinned,
+            commands::toggle_pin,
+            commands::set_pinned_bulk,
+            commands::add_tag_to_clips,
+            commands::remove_tag_from_clips,
+            commands::get_categories,
+            commands::get_category_counts,
+            commands::get_tags,
+            commands::set_category_appearance,
+            commands::estimate_cost,
+            benchmark::benchmark_llm,
             commands::get_image_data,
+            commands::add_image_file,
             commands::save_image_to_file,
             commands::copy_image_to_clipboard,
+            commands::copy_item_to_clipboard,
+            commands::paste_clip,
+            commands::paste_item,
+            maintenance::reclassify_images,
+            maintenance::normalize_tags,
+            maintenance::dedupe_clip_tags,
+            maintenance::strip_images_in_category,
+            maintenance::backfill_url_summaries,
+            maintenance::reclassify_urls,
+            maintenance::normalize_clips,
+            maintenance::repair_database,
+            maintenance::get_backfill_status,
+            fragments::merge_clips,
+            reminders::set_reminder,
+            reminders::get_upcoming_reminders,
+            schedule::set_scheduled_capture,
+            schedule::get_scheduled_capture,
+            toolbar::show_clip_toolbar,
+            toolbar::get_pending_clip,
+            toolbar::close_toolbar_window,
+            toolbar::get_monitors,
+            permissions::check_permissions,
+            permissions::open_permission_settings,
+            search::quick_search,
+            search::search_items,
+            search::search_clips,
+            search::find_clips_with_url,
+            search::get_search_history,
+            search::delete_search_history_entry,
+            search::clear_search_history,
+            stats::get_activity,
+            stats::export_usage_csv,
+            stats::get_language_stats,
+            stats::get_storage_breakdown,
+            suggest::suggest_categories,
+            suggest::suggest_tags,
+            thumbnails::get_thumbnail,
+            thumbnails::regenerate_thumbnails,
+            thumbnails::set_thumbnail_size,
+            versions::get_clip_versions,
+            versions::revert_clip_version,
+            workspaces::set_active_workspace,
+            workspaces::get_active_workspace,
+            workspaces::get_workspaces,
             settings::get_setting,
+            settings::get_secret_setting,
             settings::set_setting,
             settings::set_global_hotkey,
             settings::get_global_hotkey,
+            settings::reregister_shortcut,
             settings::get_all_settings,
             settings::test_global_hotkey,
+            settings::get_hotkey_actions,
+            settings::set_hotkey_actions,
+            settings::validate_settings,
+            settings::export_settings,
+            settings::import_settings,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                schedule::shutdown(app);
+            }
+        });
 }
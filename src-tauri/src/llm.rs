@@ -1,27 +1,52 @@
+use crate::ansi;
+use crate::database;
+use crate::heuristic;
+use crate::providers::{self, LlmProvider, Provider};
+use crate::settings::SettingsManager;
 use crate::shortcut::Clip;
-use async_openai::{
-    types::{
-        responses::{
-            Content, ContentType, CreateResponseArgs, Input, InputContent, InputImageArgs,
-            InputItem, InputMessageArgs, OutputContent, Role,
-        },
-        ImageDetail,
-    },
-    Client,
-};
+use rusqlite::params;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tiktoken_rs::CoreBPE;
 
-use serde::Deserialize;
+pub mod cache;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 pub struct CategoryResponse {
     pub category: String,
     pub tags: Vec<String>,
+    /// set when the configured model was rate limited and `fallback_model` answered instead
+    #[serde(skip)]
+    pub fallback_model: Option<String>,
+    /// set when the model's answer held no usable JSON and `default_category` stood in
+    #[serde(skip)]
+    pub unparsed: bool,
 }
 
-pub async fn get_llm_category(clip: &Clip) -> Result<CategoryResponse, Box<dyn std::error::Error>> {
-    let client = Client::new();
+/// OpenAI model used while the `openai_model` setting is unset
+pub const DEFAULT_MODEL: &str = "gpt-4o";
+pub const MAX_OUTPUT_TOKENS: u32 = 100;
+/// Bounds for the `llm_max_input_chars` setting
+pub const MIN_INPUT_CHARS_LIMIT: u32 = 100;
+pub const MAX_INPUT_CHARS_LIMIT: u32 = 100_000;
+/// Used before init_llm has run
+const DEFAULT_INPUT_CHARS: usize = 4000;
+
+/// Upper bound for the `llm_retry_attempts` setting
+pub const MAX_RETRY_ATTEMPTS: u32 = 10;
+
+/// Used before init_llm has run
+const DEFAULT_RETRY_ATTEMPTS: u32 = 3;
 
-    let system_prompt = r#"You are a clipboard content categorizer. Your job is to categorize content into a primary category and suggest relevant tags.
+/// Wait before the first retry, doubled for every one after it
+const RETRY_BASE_DELAY_MS: u64 = 500;
+
+const CATEGORY_SYSTEM_PROMPT: &str = r#"You are a clipboard content categorizer. Your job is to categorize content into a primary category and suggest relevant tags.
 
 IMPORTANT: Respond with ONLY a JSON object in this exact format:
 {
@@ -73,238 +98,554 @@ Output: {"category": "image", "tags": ["screenshot", "terminal", "error-message"
 Input: [Image of a website mockup]
 Output: {"category": "image", "tags": ["screenshot", "ui-design", "website", "mockup"]}"#;
 
-    let request_items = match clip {
-        Clip::Text { plain } => {
-            let content = if plain.len() > 2000 {
-                format!("{}...", &plain[..2000])
-            } else {
-                plain.clone()
-            };
-
-            let user_prompt = format!("Categorize this text content:\n\n{}", content);
-
-            vec![
-                InputItem::Message(
-                    InputMessageArgs::default()
-                        .role(Role::System)
-                        .content(system_prompt)
-                        .build()?,
-                ),
-                InputItem::Message(
-                    InputMessageArgs::default()
-                        .role(Role::User)
-                        .content(user_prompt)
-                        .build()?,
-                ),
-            ]
-        }
+const SUMMARY_SYSTEM_PROMPT: &str = r#"You are a concise summarization assistant.
+Provide a clear, bullet-point summary of the key points.
+Do not include citations or extra commentary."#;
+
+/// Default prices in USD per million tokens, overridable through the `llm_prices` setting
+const DEFAULT_PRICES: &[(&str, f64, f64)] = &[
+    ("gpt-4o", 2.5, 10.0),
+    ("gpt-4o-mini", 0.15, 0.6),
+    ("gpt-4.1", 2.0, 8.0),
+    ("gpt-4.1-mini", 0.4, 1.6),
+    ("gpt-4.1-nano", 0.1, 0.4),
+    ("claude-3-5-haiku-latest", 0.8, 4.0),
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CostEstimate {
+    pub model: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub estimated_cost: Option<f64>,
+}
+
+/// What a provider sends: the system prompt, the user prompt and, when images may
+/// leave the machine, the base64 PNG the prompt is about
+pub struct Prompt<'a> {
+    pub system: &'static str,
+    pub user: String,
+    pub image: Option<&'a str>,
+}
+
+/// Returned when the model answers with nothing usable
+pub const NO_SUMMARY: &str = "No summary available";
+
+pub async fn get_llm_category(clip: &Clip) -> Result<CategoryResponse, Box<dyn std::error::Error>> {
+    get_llm_category_with_context(clip, &[]).await
+}
+
+/// Categorize a clip, telling the model about clips captured just before it so
+/// related captures (an error, then the fix) end up with matching tags. Without
+/// an API key the local heuristics categorize it instead, so offline use still
+/// gets useful categories.
+pub async fn get_llm_category_with_context(
+    clip: &Clip,
+    recent_clips: &[String],
+) -> Result<CategoryResponse, Box<dyn std::error::Error>> {
+    let provider = provider();
+    if !provider.has_credentials() {
+        let category_response = heuristic::categorize(clip);
+        println!(
+            "No API key configured, categorized locally as: {} with tags: {:?}",
+            category_response.category, category_response.tags
+        );
+        return Ok(category_response);
+    }
+
+    let category_response = retry_with_backoff(
+        || provider.categorize(clip, recent_clips),
+        retry_attempts(),
+        RETRY_BASE_DELAY_MS,
+    )
+    .await?;
+    println!(
+        "LLM categorized as: {} with tags: {:?}",
+        category_response.category, category_response.tags
+    );
+    Ok(category_response)
+}
+
+pub async fn get_clip_summary(clip: &Clip) -> Result<String, Box<dyn std::error::Error>> {
+    let provider = provider();
+    let summary = retry_with_backoff(
+        || provider.summarize(clip),
+        retry_attempts(),
+        RETRY_BASE_DELAY_MS,
+    )
+    .await?;
+    println!("LLM summary: {}", summary);
+    Ok(summary)
+}
+
+/// Run a request up to `max_attempts` times, waiting `base_delay_ms * 2^n` after
+/// the n-th failure. Only transient failures are retried, see `providers::is_transient`,
+/// so a rejected API key fails on the first attempt.
+pub async fn retry_with_backoff<F, Fut, T>(
+    mut f: F,
+    max_attempts: u32,
+    base_delay_ms: u64,
+) -> Result<T, Box<dyn std::error::Error>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Box<dyn std::error::Error>>>,
+{
+    let max_attempts = max_attempts.max(1);
+    let mut attempt = 1;
+    loop {
+        // the error is dropped before sleeping, it is not Send
+        let delay = match f().await {
+            Ok(value) => {
+                if attempt > 1 {
+                    println!("LLM request succeeded after {} attempts", attempt);
+                }
+                return Ok(value);
+            }
+            Err(e) if attempt >= max_attempts || !providers::is_transient(e.as_ref()) => {
+                eprintln!("LLM request failed after {} attempt(s): {}", attempt, e);
+                return Err(e);
+            }
+            Err(e) => {
+                let delay = Duration::from_millis(base_delay_ms << (attempt - 1).min(16));
+                eprintln!(
+                    "LLM request failed (attempt {}/{}), retrying in {:?}: {}",
+                    attempt, max_attempts, delay, e
+                );
+                delay
+            }
+        };
+
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// How often a failing request is tried, from the `llm_retry_attempts` setting
+fn retry_attempts() -> u32 {
+    LLM_ENV.get().map_or(DEFAULT_RETRY_ATTEMPTS, |env| {
+        env.settings.get_u32("llm_retry_attempts")
+    })
+}
+
+/// Base64 PNG of an image clip, when `send_images_to_llm` lets it be sent
+fn attached_image(clip: &Clip) -> Option<&str> {
+    match clip {
+        Clip::Image { data, .. } if send_images() => Some(data),
+        _ => None,
+    }
+}
+
+pub fn category_prompt<'a>(clip: &'a Clip, recent_clips: &[String]) -> Prompt<'a> {
+    let image = attached_image(clip);
+
+    let user = match clip {
+        Clip::Text { plain } => format!("Categorize this text content:\n\n{}", prompt_text(plain)),
         Clip::Image {
-            data,
             width,
             height,
-        } => {
-            let user_prompt = format!(
-                "Categorize this image content. Image dimensions: {}x{}. Analyze what you see in the image and provide appropriate category and tags.",
+            text,
+            ..
+        } => image_category_prompt(*width, *height, text.as_deref(), image.is_some()),
+    };
+
+    Prompt {
+        system: CATEGORY_SYSTEM_PROMPT,
+        user: with_recent_clips(user, recent_clips),
+        image,
+    }
+}
+
+pub fn summary_prompt(clip: &Clip) -> Prompt<'_> {
+    let image = attached_image(clip);
+
+    let user = match clip {
+        Clip::Text { plain } => format!(
+            "Please summarize the following content. If it came from a URL, provide a short overview of the page's main points.\n\n{:?}",
+            prompt_text(plain)
+        ),
+        Clip::Image {
+            width,
+            height,
+            text,
+            ..
+        } if image.is_some() => with_image_text(
+            format!(
+                "Please provide a brief summary of the image content. Image dimensions: {}x{}. Analyze what you see in the image.",
                 width, height
-            );
-
-            let image_url = format!("data:image/png;base64,{}", data);
-
-            let im = InputImageArgs::default()
-                .image_url(image_url)
-                .detail(ImageDetail::Auto)
-                .build()?;
-
-            let content = InputContent::InputItemContentList(vec![ContentType::InputImage(im)]);
-
-            vec![
-                InputItem::Message(
-                    InputMessageArgs::default()
-                        .role(Role::System)
-                        .content(system_prompt)
-                        .build()?,
-                ),
-                InputItem::Message(
-                    InputMessageArgs::default()
-                        .role(Role::User)
-                        .content(user_prompt)
-                        .build()?,
-                ),
-                InputItem::Message(
-                    InputMessageArgs::default()
-                        .role(Role::User)
-                        .content(content)
-                        .build()?,
-                ),
-            ]
-        }
+            ),
+            text.as_deref(),
+        ),
+        Clip::Image {
+            width,
+            height,
+            text,
+            ..
+        } => with_image_text(
+            format!(
+                "Please provide a brief summary of an image the user copied. The image itself is not shared, only its dimensions: {}x{}. Describe what it most likely is.",
+                width, height
+            ),
+            text.as_deref(),
+        ),
     };
 
-    let request = CreateResponseArgs::default()
-        .max_output_tokens(100u32)
-        .model("gpt-4o")
-        .input(Input::Items(request_items))
-        .build()?;
-
-    let response = client.responses().create(request).await?;
-
-    // Extract the JSON response and parse it
-    for output in response.output {
-        if let Some(content) = extract_content_from_output(&output) {
-            let trimmed_content = content.trim();
-
-            // Try to parse as JSON
-            if let Ok(category_response) = serde_json::from_str::<CategoryResponse>(trimmed_content)
-            {
-                println!(
-                    "LLM categorized as: {} with tags: {:?}",
-                    category_response.category, category_response.tags
-                );
-                return Ok(category_response);
-            }
-            // Fallback: try to extract category if JSON parsing fails
-            if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(trimmed_content) {
-                if let (Some(category), Some(tags)) = (
-                    json_value.get("category").and_then(|v| v.as_str()),
-                    json_value.get("tags").and_then(|v| v.as_array()),
-                ) {
-                    let tag_strings: Vec<String> = tags
-                        .iter()
-                        .filter_map(|tag| tag.as_str().map(|s| s.to_string()))
-                        .collect();
-                    return Ok(CategoryResponse {
-                        category: category.to_string(),
-                        tags: tag_strings,
-                    });
-                }
-            }
-        }
+    Prompt {
+        system: SUMMARY_SYSTEM_PROMPT,
+        user,
+        image,
     }
+}
+
+/// The category and tags in a model's answer, None when it holds no usable JSON
+pub fn parse_category(content: &str) -> Option<CategoryResponse> {
+    let trimmed_content = content.trim();
 
-    // Fallback based on clip type
+    if let Ok(category_response) = serde_json::from_str::<CategoryResponse>(trimmed_content) {
+        return Some(category_response);
+    }
+
+    // tolerate extra fields or tags that are not all strings
+    let json_value = serde_json::from_str::<serde_json::Value>(trimmed_content).ok()?;
+    let category = json_value.get("category")?.as_str()?;
+    let tags = json_value.get("tags")?.as_array()?;
+
+    Some(CategoryResponse {
+        category: category.to_string(),
+        tags: tags
+            .iter()
+            .filter_map(|tag| tag.as_str().map(|s| s.to_string()))
+            .collect(),
+        ..Default::default()
+    })
+}
+
+/// Category for a clip the model could not categorize, based on its type
+pub fn default_category(clip: &Clip) -> CategoryResponse {
     match clip {
-        Clip::Text { .. } => Ok(CategoryResponse {
+        Clip::Text { .. } => CategoryResponse {
             category: "other".to_string(),
             tags: vec!["uncategorized".to_string()],
-        }),
-        Clip::Image { .. } => Ok(CategoryResponse {
+            unparsed: true,
+            ..Default::default()
+        },
+        Clip::Image { .. } => CategoryResponse {
             category: "image".to_string(),
             tags: vec!["screenshot".to_string()],
-        }),
+            unparsed: true,
+            ..Default::default()
+        },
     }
 }
 
-pub async fn get_clip_summary(clip: &Clip) -> Result<String, Box<dyn std::error::Error>> {
-    let client = Client::new();
+/// The `fallback_model` setting, None when it is unset or empty
+pub fn fallback_model() -> Option<String> {
+    let model = LLM_ENV.get()?.settings.get_string("fallback_model");
+    let model = model.trim();
+    (!model.is_empty()).then(|| model.to_string())
+}
 
-    let system_prompt = r#"You are a concise summarization assistant.
-Provide a clear, bullet-point summary of the key points.
-Do not include citations or extra commentary."#;
+/// A setting the providers read on every request, empty before init_llm
+pub fn setting(key: &str) -> String {
+    LLM_ENV
+        .get()
+        .map(|env| env.settings.get_string(key))
+        .unwrap_or_default()
+}
+
+/// The provider `llm_provider` currently picks, read on every request so
+/// switching providers takes effect with the next capture
+fn provider() -> Provider {
+    LLM_ENV
+        .get()
+        .map(|env| Provider::from_settings(&env.settings))
+        .unwrap_or_default()
+}
+
+/// The model the configured provider sends requests to, see `Provider::model`
+pub fn active_model() -> String {
+    provider().model()
+}
+
+/// Whether the configured provider can be called, see `Provider::has_credentials`
+pub fn has_credentials() -> bool {
+    provider().has_credentials()
+}
+
+/// Longest text sent to the model, from the `llm_max_input_chars` setting
+fn max_input_chars() -> usize {
+    LLM_ENV.get().map_or(DEFAULT_INPUT_CHARS, |env| {
+        env.settings.get_u32("llm_max_input_chars") as usize
+    })
+}
+
+/// Text as it is sent to the model: escape sequences stripped and cut at `llm_max_input_chars`
+fn prompt_text(plain: &str) -> String {
+    let plain = ansi::strip_ansi(plain);
+    match plain.char_indices().nth(max_input_chars()) {
+        Some((byte_index, _)) => format!("{}...", &plain[..byte_index]),
+        None => plain,
+    }
+}
+
+/// Categorization prompt for an image, either sent along with the image or, when
+/// images stay local, describing it by its dimensions and any accompanying text
+fn image_category_prompt(
+    width: usize,
+    height: usize,
+    text: Option<&str>,
+    attached: bool,
+) -> String {
+    let prompt = if attached {
+        format!(
+            "Categorize this image content. Image dimensions: {}x{}. Analyze what you see in the image and provide appropriate category and tags.",
+            width, height
+        )
+    } else {
+        format!(
+            "Categorize an image the user copied. The image itself is not shared, only its dimensions: {}x{}. Pick the category and tags from the dimensions and any text that came with it.",
+            width, height
+        )
+    };
+
+    with_image_text(prompt, text)
+}
 
-    let request_items = match clip {
+/// Append the text flavor of a rich clip to an image prompt
+fn with_image_text(prompt: String, text: Option<&str>) -> String {
+    match text {
+        Some(text) if !text.trim().is_empty() => format!(
+            "{}\n\nThe clipboard also held this text alongside the image:\n\n{}",
+            prompt,
+            prompt_text(text)
+        ),
+        _ => prompt,
+    }
+}
+
+/// Append summaries of the clips captured just before this one to a categorization prompt
+fn with_recent_clips(prompt: String, recent_clips: &[String]) -> String {
+    if recent_clips.is_empty() {
+        return prompt;
+    }
+
+    let context = recent_clips
+        .iter()
+        .map(|clip| format!("- {}", clip))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        "{}\n\nFor context, these clips were captured shortly before this one. Only let them influence the tags if they are clearly related:\n{}",
+        prompt, context
+    )
+}
+
+/// Database and settings the LLM requests need, set once at startup
+struct LlmEnv {
+    db_path: PathBuf,
+    settings: Arc<SettingsManager>,
+}
+
+static LLM_ENV: OnceLock<LlmEnv> = OnceLock::new();
+
+/// Record every LLM request in the llm_usage table of this database from now on
+/// and follow the LLM related settings
+pub fn init_llm(db_path: PathBuf, settings: Arc<SettingsManager>) {
+    LLM_ENV.set(LlmEnv { db_path, settings }).ok();
+}
+
+/// Whether image pixels may be sent to the vision model, see `send_images_to_llm`
+fn send_images() -> bool {
+    LLM_ENV
+        .get()
+        .is_none_or(|env| env.settings.get_bool("send_images_to_llm"))
+}
+
+/// Prices from the `llm_prices` setting, validated when it is set. A local Ollama
+/// model costs nothing unless the setting gives it a price.
+pub fn price_overrides(settings: &SettingsManager) -> HashMap<String, ModelPrice> {
+    let mut prices: HashMap<String, ModelPrice> = settings
+        .get_setting("llm_prices")
+        .filter(|prices| !prices.trim().is_empty())
+        .and_then(|prices| serde_json::from_str(&prices).ok())
+        .unwrap_or_default();
+
+    let provider = Provider::from_settings(settings);
+    if matches!(provider, Provider::Ollama(_)) {
+        prices.entry(provider.model()).or_insert(ModelPrice {
+            input: 0.0,
+            output: 0.0,
+        });
+    }
+    prices
+}
+
+/// Store the tokens, cost and latency of a request. Failed requests are kept too,
+/// without tokens, so slow or flaky periods show up in the export.
+pub fn record_usage(
+    stage: &str,
+    model: &str,
+    tokens: Option<(u32, u32)>,
+    success: bool,
+    latency: Duration,
+) {
+    let Some(LlmEnv { db_path, settings }) = LLM_ENV.get() else {
+        return;
+    };
+
+    let cost = tokens.and_then(|(input_tokens, output_tokens)| {
+        price_for(model, &price_overrides(settings)).map(|price| {
+            (input_tokens as f64 * price.input + output_tokens as f64 * price.output) / 1_000_000.0
+        })
+    });
+
+    let recorded = database::open_connection(db_path).and_then(|conn| {
+        conn.execute(
+            "INSERT INTO llm_usage (model, stage, input_tokens, output_tokens, cost, latency_ms, success) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                model,
+                stage,
+                tokens.map(|(input_tokens, _)| input_tokens),
+                tokens.map(|(_, output_tokens)| output_tokens),
+                cost,
+                latency.as_millis() as i64,
+                success
+            ],
+        )
+    });
+
+    if let Err(e) = recorded {
+        eprintln!("Failed to record LLM usage: {}", e);
+    }
+}
+
+fn tokenizer() -> Option<&'static CoreBPE> {
+    static TOKENIZER: OnceLock<Option<CoreBPE>> = OnceLock::new();
+    TOKENIZER
+        .get_or_init(|| tiktoken_rs::o200k_base().ok())
+        .as_ref()
+}
+
+fn count_tokens(text: &str) -> u64 {
+    match tokenizer() {
+        Some(bpe) => bpe.encode_with_special_tokens(text).len() as u64,
+        // rough average for english text when the tokenizer is unavailable
+        None => (text.chars().count() as u64).div_ceil(4),
+    }
+}
+
+/// Vision token cost of an image at high detail: fit into 2048x2048, scale the
+/// shortest side to 768 and charge per 512px tile
+fn count_image_tokens(width: usize, height: usize) -> u64 {
+    if width == 0 || height == 0 {
+        return 85;
+    }
+
+    let (mut w, mut h) = (width as f64, height as f64);
+
+    let fit = (2048.0 / w.max(h)).min(1.0);
+    w *= fit;
+    h *= fit;
+
+    let shrink = (768.0 / w.min(h)).min(1.0);
+    w *= shrink;
+    h *= shrink;
+
+    let tiles = (w / 512.0).ceil() as u64 * (h / 512.0).ceil() as u64;
+    85 + 170 * tiles
+}
+
+pub fn price_for(model: &str, overrides: &HashMap<String, ModelPrice>) -> Option<ModelPrice> {
+    overrides.get(model).copied().or_else(|| {
+        DEFAULT_PRICES
+            .iter()
+            .find(|(name, _, _)| *name == model)
+            .map(|(_, input, output)| ModelPrice {
+                input: *input,
+                output: *output,
+            })
+    })
+}
+
+/// Estimate the tokens and cost of categorizing a clip without calling the API
+pub fn estimate_cost(
+    clip: &Clip,
+    model: &str,
+    price_overrides: &HashMap<String, ModelPrice>,
+) -> CostEstimate {
+    let mut input_tokens = count_tokens(CATEGORY_SYSTEM_PROMPT);
+
+    match clip {
         Clip::Text { plain } => {
-            let content = if plain.len() > 2000 {
-                format!("{}...", &plain[..2000])
-            } else {
-                plain.clone()
-            };
-
-            let user_prompt = format!(
-        "Please summarize the following content. If it came from a URL, provide a short overview of the page's main points.\n\n{:?}",
-        content);
-
-            vec![
-                InputItem::Message(
-                    InputMessageArgs::default()
-                        .role(Role::System)
-                        .content(system_prompt)
-                        .build()?,
-                ),
-                InputItem::Message(
-                    InputMessageArgs::default()
-                        .role(Role::User)
-                        .content(user_prompt)
-                        .build()?,
-                ),
-            ]
+            input_tokens += count_tokens(&format!(
+                "Categorize this text content:\n\n{}",
+                prompt_text(plain)
+            ));
         }
         Clip::Image {
-            data,
             width,
             height,
+            text,
+            ..
         } => {
-            let user_prompt = format!(
-                "Please provide a brief summary of the image content. Image dimensions: {}x{}. Analyze what you see in the image.",
-                width, height
-            );
-
-            let image_url = format!("data:image/png;base64,{}", data);
-
-            let im = InputImageArgs::default()
-                .image_url(image_url)
-                .detail(ImageDetail::Auto)
-                .build()?;
-
-            let content = InputContent::InputItemContentList(vec![ContentType::InputImage(im)]);
-
-            vec![
-                InputItem::Message(
-                    InputMessageArgs::default()
-                        .role(Role::System)
-                        .content(system_prompt)
-                        .build()?,
-                ),
-                InputItem::Message(
-                    InputMessageArgs::default()
-                        .role(Role::User)
-                        .content(user_prompt)
-                        .build()?,
-                ),
-                InputItem::Message(
-                    InputMessageArgs::default()
-                        .role(Role::User)
-                        .content(content)
-                        .build()?,
-                ),
-            ]
+            let attach_image = send_images();
+            input_tokens += count_tokens(&image_category_prompt(
+                *width,
+                *height,
+                text.as_deref(),
+                attach_image,
+            ));
+            if attach_image {
+                input_tokens += count_image_tokens(*width, *height);
+            }
         }
-    };
-
-    let request = CreateResponseArgs::default()
-        .max_output_tokens(100u32)
-        .model("gpt-4o")
-        .input(Input::Items(request_items))
-        .build()?;
+    }
 
-    let response = client.responses().create(request).await?;
+    let output_tokens = MAX_OUTPUT_TOKENS as u64;
+    let estimated_cost = price_for(model, price_overrides).map(|price| {
+        (input_tokens as f64 * price.input + output_tokens as f64 * price.output) / 1_000_000.0
+    });
 
-    for output in response.output {
-        if let Some(content) = extract_content_from_output(&output) {
-            let summary = content.trim();
+    CostEstimate {
+        model: model.to_string(),
+        input_tokens,
+        output_tokens,
+        estimated_cost,
+    }
+}
 
-            if !summary.is_empty() {
-                println!("LLM summary: {}", summary);
-                return Ok(summary.to_string());
-            }
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // LLM_ENV is never set in tests, so prompts are cut at DEFAULT_INPUT_CHARS
+    #[test]
+    fn prompt_text_cuts_long_text() {
+        let text = "a".repeat(10_000);
+        let prompt = prompt_text(&text);
+        assert_eq!(prompt.len(), DEFAULT_INPUT_CHARS + 3);
+        assert!(prompt.ends_with("..."));
+        assert_eq!(&prompt[..DEFAULT_INPUT_CHARS], &text[..DEFAULT_INPUT_CHARS]);
     }
 
-    Ok("No summary available".to_string())
-}
+    #[test]
+    fn prompt_text_counts_chars_not_bytes() {
+        let text = "é".repeat(DEFAULT_INPUT_CHARS + 1);
+        let prompt = prompt_text(&text);
+        assert_eq!(prompt.chars().count(), DEFAULT_INPUT_CHARS + 3);
 
-fn extract_content_from_output(output: &OutputContent) -> Option<String> {
-    match output {
-        OutputContent::Message(message) => {
-            for content_item in &message.content {
-                match content_item {
-                    Content::OutputText(output_text) => {
-                        return Some(output_text.text.clone());
-                    }
-                    _ => continue,
-                }
-            }
-            None
-        }
-        _ => None,
+        let fits = "é".repeat(DEFAULT_INPUT_CHARS);
+        assert_eq!(prompt_text(&fits), fits);
+    }
+
+    #[test]
+    fn prompt_text_strips_escape_sequences() {
+        assert_eq!(prompt_text("\u{1b}[31merror\u{1b}[0m"), "error");
     }
 }
@@ -0,0 +1,165 @@
+//! Answers of the LLM per content hash, so capturing the same content again
+//! does not pay for the same request twice. Entries are used for
+//! `llm_cache_ttl_hours` after they were last written, 0 turns the cache off.
+
+use super::LLM_ENV;
+use rusqlite::{params, Connection, OptionalExtension};
+
+/// Upper bound for the `llm_cache_ttl_hours` setting
+pub const MAX_TTL_HOURS: u32 = 24 * 365;
+
+/// Used before init_llm has run
+const DEFAULT_TTL_HOURS: u32 = 24;
+
+#[derive(Debug, Clone, Default)]
+pub struct CachedResponse {
+    /// category and tags, None when only a summary was cached
+    pub category: Option<(String, Vec<String>)>,
+    pub summary: Option<String>,
+}
+
+fn ttl_hours() -> u32 {
+    LLM_ENV.get().map_or(DEFAULT_TTL_HOURS, |env| {
+        env.settings.get_u32("llm_cache_ttl_hours")
+    })
+}
+
+/// The cached answers for a content hash, None when there are none younger than the TTL
+pub fn check_llm_cache(conn: &Connection, hash: &str) -> Option<CachedResponse> {
+    let ttl_hours = ttl_hours();
+    if ttl_hours == 0 {
+        return None;
+    }
+
+    let cached = conn
+        .query_row(
+            "SELECT category, tags, summary FROM llm_cache WHERE content_hash = ?1 AND cached_at > datetime('now', ?2)",
+            params![hash, format!("-{} hours", ttl_hours)],
+            |row| {
+                Ok((
+                    row.get::<_, Option<String>>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                ))
+            },
+        )
+        .optional();
+
+    match cached {
+        Ok(Some((category, tags, summary))) => Some(CachedResponse {
+            category: category.map(|category| {
+                let tags = tags
+                    .and_then(|tags| serde_json::from_str(&tags).ok())
+                    .unwrap_or_default();
+                (category, tags)
+            }),
+            summary,
+        }),
+        Ok(None) => None,
+        Err(e) => {
+            eprintln!("Failed to read LLM cache: {}", e);
+            None
+        }
+    }
+}
+
+/// Delete the entries older than the TTL, all of them while the cache is off.
+/// Entries of deleted clips stay until then, so the same content captured again
+/// is answered from the cache.
+pub fn prune_llm_cache(conn: &Connection) -> rusqlite::Result<usize> {
+    conn.execute(
+        "DELETE FROM llm_cache WHERE cached_at <= datetime('now', ?1)",
+        params![format!("-{} hours", ttl_hours())],
+    )
+}
+
+/// Forget the answers for a content hash, for content that must not linger like
+/// a secret removed by panic_clear
+pub fn remove_llm_cache(conn: &Connection, hash: &str) -> rusqlite::Result<()> {
+    conn.execute(
+        "DELETE FROM llm_cache WHERE content_hash = ?",
+        params![hash],
+    )?;
+    Ok(())
+}
+
+/// Replace the cached answers for a content hash, nothing is written while the cache is off
+pub fn store_llm_cache(
+    conn: &Connection,
+    hash: &str,
+    response: &CachedResponse,
+) -> rusqlite::Result<()> {
+    if ttl_hours() == 0 {
+        return Ok(());
+    }
+
+    let (category, tags) = match &response.category {
+        Some((category, tags)) => (Some(category.as_str()), serde_json::to_string(tags).ok()),
+        None => (None, None),
+    };
+
+    conn.execute(
+        "INSERT OR REPLACE INTO llm_cache (content_hash, category, tags, summary, cached_at) VALUES (?1, ?2, ?3, ?4, CURRENT_TIMESTAMP)",
+        params![hash, category, tags, response.summary],
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cached_hashes(conn: &Connection) -> Vec<String> {
+        conn.prepare("SELECT content_hash FROM llm_cache ORDER BY content_hash")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn prunes_entries_older_than_the_ttl() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::database::init_schema(&conn).unwrap();
+        conn.execute_batch(&format!(
+            "INSERT INTO llm_cache (content_hash, cached_at) VALUES
+                ('fresh', datetime('now', '-1 hours')),
+                ('stale', datetime('now', '-{} hours'));",
+            DEFAULT_TTL_HOURS + 1
+        ))
+        .unwrap();
+
+        assert_eq!(prune_llm_cache(&conn).unwrap(), 1);
+        assert_eq!(cached_hashes(&conn), vec!["fresh"]);
+    }
+
+    #[test]
+    fn content_captured_again_after_a_delete_is_served_from_the_cache() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::database::init_schema(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO clips (id, clip, content_hash) VALUES (1, '{}', 'link')",
+            [],
+        )
+        .unwrap();
+        let answered = CachedResponse {
+            category: Some(("link".to_string(), vec!["docs".to_string()])),
+            summary: Some("Rust documentation".to_string()),
+        };
+        store_llm_cache(&conn, "link", &answered).unwrap();
+
+        conn.execute("DELETE FROM clips WHERE id = 1", []).unwrap();
+
+        let cached = check_llm_cache(&conn, "link").unwrap();
+        assert_eq!(
+            cached.category,
+            Some(("link".to_string(), vec!["docs".to_string()]))
+        );
+        assert_eq!(cached.summary.as_deref(), Some("Rust documentation"));
+
+        remove_llm_cache(&conn, "link").unwrap();
+        assert!(cached_hashes(&conn).is_empty());
+    }
+}
@@ -0,0 +1,923 @@
+use crate::ansi;
+use crate::commands::load_clip;
+use crate::database::{self, RepairReport};
+use crate::heuristic;
+use crate::llm;
+use crate::settings::SettingsManagerState;
+use crate::shortcut::{content_hash, content_type, is_url, Clip};
+use crate::transforms;
+use crate::urls::{self, FetchPolicy, PageDetails};
+use crate::AppState;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MaintenanceProgress {
+    pub task: String,
+    pub processed: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct MaintenanceReport {
+    pub task: String,
+    pub total: usize,
+    pub updated: usize,
+    pub failed: usize,
+    pub estimated_cost: Option<f64>,
+    pub dry_run: bool,
+    /// id an interrupted earlier run got through, this run continued after it
+    pub resumed_after: Option<i64>,
+}
+
+/// Prefix of the settings keys holding the resume cursor of each maintenance task
+pub const CURSOR_SETTING_PREFIX: &str = "maintenance_cursor.";
+
+/// Names of the maintenance tasks currently running, so the same task cannot be started twice
+#[derive(Default)]
+pub struct MaintenanceState(pub Arc<Mutex<HashSet<String>>>);
+
+/// Marks a task as running for as long as it is alive
+pub struct TaskGuard {
+    task: String,
+    running: Arc<Mutex<HashSet<String>>>,
+}
+
+impl TaskGuard {
+    pub fn acquire(app_handle: &AppHandle, task: &str) -> Result<Self, String> {
+        let running = app_handle.state::<MaintenanceState>().0.clone();
+
+        if !running.lock().unwrap().insert(task.to_string()) {
+            return Err(format!("{} is already running", task));
+        }
+
+        Ok(Self {
+            task: task.to_string(),
+            running,
+        })
+    }
+
+    pub fn progress(&self, app_handle: &AppHandle, processed: usize, total: usize) {
+        let progress = MaintenanceProgress {
+            task: self.task.clone(),
+            processed,
+            total,
+        };
+        app_handle.emit("maintenance-progress", progress).ok();
+    }
+
+    fn cursor_key(&self) -> String {
+        format!("{}{}", CURSOR_SETTING_PREFIX, self.task)
+    }
+
+    /// Last clip id an interrupted earlier run of this task got through. Tasks walk
+    /// clips in id order, so a resumed run only has to look at larger ids.
+    /// `restart` drops the cursor so the task starts over from the first clip.
+    pub fn resume_cursor(&self, app_handle: &AppHandle, restart: bool) -> Option<i64> {
+        if restart {
+            self.clear_cursor(app_handle);
+            return None;
+        }
+
+        app_handle
+            .state::<SettingsManagerState>()
+            .0
+            .get_setting(&self.cursor_key())
+            .and_then(|value| value.parse().ok())
+    }
+
+    /// Record that every clip up to and including `id` has been processed
+    pub fn save_cursor(&self, app_handle: &AppHandle, id: i64) {
+        let settings = app_handle.state::<SettingsManagerState>().0.clone();
+        if let Err(e) = settings.set_setting(&self.cursor_key(), &id.to_string()) {
+            eprintln!("Failed to save {} progress: {}", self.task, e);
+        }
+    }
+
+    /// Forget the cursor once the task has run to completion
+    pub fn clear_cursor(&self, app_handle: &AppHandle) {
+        let settings = app_handle.state::<SettingsManagerState>().0.clone();
+        if settings.get_setting(&self.cursor_key()).is_none() {
+            return;
+        }
+        if let Err(e) = settings.delete_setting(&self.cursor_key()) {
+            eprintln!("Failed to clear {} progress: {}", self.task, e);
+        }
+    }
+}
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        self.running.lock().unwrap().remove(&self.task);
+    }
+}
+
+pub fn init_maintenance(app_handle: &AppHandle) {
+    app_handle.manage(MaintenanceState::default());
+}
+
+/// Ids and dimensions of the image clips after `after_id`, without loading the image data itself
+fn image_clip_dimensions(
+    db_path: &Path,
+    after_id: i64,
+) -> rusqlite::Result<Vec<(i64, usize, usize)>> {
+    let conn = database::open_connection(db_path)?;
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT id, json_extract(clip, '$.width'), json_extract(clip, '$.height')
+        FROM clips
+        WHERE content_type = 'image' AND id > ?
+        ORDER BY id
+        "#,
+    )?;
+
+    let rows = stmt
+        .query_map(params![after_id], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, Option<i64>>(1)?.unwrap_or(0) as usize,
+                row.get::<_, Option<i64>>(2)?.unwrap_or(0) as usize,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(rows)
+}
+
+/// Store a new category and tags, keeping the copy embedded in the clip JSON in sync
+pub fn update_classification(
+    conn: &Connection,
+    id: i64,
+    category: &str,
+    tags: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    conn.execute(
+        "UPDATE clips SET category = ?1, tags = ?2, clip = json_set(clip, '$.category', ?1) WHERE id = ?3",
+        params![category, serde_json::to_string(&clean_tags(tags))?, id],
+    )?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn reclassify_images(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    dry_run: Option<bool>,
+    restart: Option<bool>,
+) -> Result<MaintenanceReport, String> {
+    let task = "reclassify_images";
+    let guard = TaskGuard::acquire(&app_handle, task)?;
+    let db_path = state.db_path.clone();
+    let dry_run = dry_run.unwrap_or(false);
+
+    // a dry run only reports, it leaves the cursor of an interrupted run alone
+    let resumed_after = guard.resume_cursor(&app_handle, restart.unwrap_or(false) && !dry_run);
+
+    let images = image_clip_dimensions(&db_path, resumed_after.unwrap_or(0))
+        .map_err(|e| format!("Failed to load image clips: {e}"))?;
+
+    let settings = app_handle.state::<SettingsManagerState>().0.clone();
+    let model = llm::active_model();
+    let prices = llm::price_overrides(&settings);
+    let estimated_cost = images.iter().try_fold(0.0, |sum, (_, width, height)| {
+        let clip = Clip::Image {
+            data: String::new(),
+            width: *width,
+            height: *height,
+            text: None,
+        };
+        llm::estimate_cost(&clip, &model, &prices)
+            .estimated_cost
+            .map(|cost| sum + cost)
+    });
+
+    let mut report = MaintenanceReport {
+        task: task.to_string(),
+        total: images.len(),
+        estimated_cost,
+        dry_run,
+        resumed_after,
+        ..Default::default()
+    };
+
+    if report.dry_run {
+        return Ok(report);
+    }
+
+    for (processed, (id, _, _)) in images.iter().enumerate() {
+        let clip = database::open_connection(&db_path)
+            .map_err(|e| format!("Failed to open database: {e}"))
+            .and_then(|conn| load_clip(&conn, &id.to_string()));
+
+        let result = match clip {
+            Ok(clip) => match llm::get_llm_category(&clip).await {
+                Ok(response) => database::open_connection(&db_path)
+                    .map_err(|e| e.into())
+                    .and_then(|conn| {
+                        update_classification(&conn, *id, &response.category, &response.tags)
+                    })
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            },
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(()) => report.updated += 1,
+            Err(e) => {
+                eprintln!("Failed to reclassify image clip {}: {}", id, e);
+                report.failed += 1;
+            }
+        }
+
+        guard.save_cursor(&app_handle, *id);
+        guard.progress(&app_handle, processed + 1, report.total);
+    }
+
+    guard.clear_cursor(&app_handle);
+
+    app_handle.emit("clips-updated", ()).ok();
+    app_handle.emit("tags-changed", ()).ok();
+
+    Ok(report)
+}
+
+#[derive(Debug, Serialize)]
+pub struct TagNormalizationReport {
+    pub tags_before: usize,
+    pub tags_after: usize,
+    pub clips_updated: usize,
+}
+
+/// Number of distinct tag spellings across all clips, as stored in the JSON column
+fn distinct_tag_count(conn: &Connection) -> rusqlite::Result<usize> {
+    conn.query_row(
+        r#"
+        SELECT COUNT(DISTINCT t.value)
+        FROM clips, json_each(CASE WHEN json_valid(clips.tags) THEN clips.tags ELSE '[]' END) AS t
+        "#,
+        [],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|count| count as usize)
+}
+
+/// The `tag_synonyms` setting, a JSON object mapping a tag to the tag it should be merged into
+fn tag_synonyms(app_handle: &AppHandle) -> Result<HashMap<String, String>, String> {
+    let Some(value) = app_handle
+        .state::<SettingsManagerState>()
+        .0
+        .get_setting("tag_synonyms")
+    else {
+        return Ok(HashMap::new());
+    };
+
+    let synonyms: HashMap<String, String> = serde_json::from_str(&value)
+        .map_err(|e| format!("Invalid tag_synonyms setting, expected a JSON object: {e}"))?;
+
+    Ok(synonyms
+        .into_iter()
+        .map(|(from, to)| (normalize_tag(&from), normalize_tag(&to)))
+        .filter(|(from, to)| !from.is_empty() && !to.is_empty())
+        .collect())
+}
+
+fn normalize_tag(tag: &str) -> String {
+    tag.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Lowercase every tag, merge synonyms and drop duplicates within a clip, keeping first-seen order
+fn normalize_tag_list(tags: &[String], synonyms: &HashMap<String, String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    tags.iter()
+        .map(|tag| normalize_tag(tag))
+        .filter(|tag| !tag.is_empty())
+        .map(|tag| synonyms.get(&tag).cloned().unwrap_or(tag))
+        .filter(|tag| seen.insert(tag.clone()))
+        .collect()
+}
+
+/// Lowercased, trimmed tags without duplicates, what every tag list is stored as
+pub fn clean_tags(tags: &[String]) -> Vec<String> {
+    normalize_tag_list(tags, &HashMap::new())
+}
+
+#[tauri::command]
+pub async fn normalize_tags(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<TagNormalizationReport, String> {
+    let guard = TaskGuard::acquire(&app_handle, "normalize_tags")?;
+    let synonyms = tag_synonyms(&app_handle)?;
+
+    rewrite_tag_lists(&app_handle, &guard, &state.db_path, &synonyms)
+}
+
+/// Clean up the tag list of each clip on its own: casing, stray whitespace and
+/// repeated tags, without the synonym merging of normalize_tags
+#[tauri::command]
+pub async fn dedupe_clip_tags(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<TagNormalizationReport, String> {
+    let guard = TaskGuard::acquire(&app_handle, "dedupe_clip_tags")?;
+
+    rewrite_tag_lists(&app_handle, &guard, &state.db_path, &HashMap::new())
+}
+
+/// Run every clip's tags through normalize_tag_list in one transaction
+fn rewrite_tag_lists(
+    app_handle: &AppHandle,
+    guard: &TaskGuard,
+    db_path: &Path,
+    synonyms: &HashMap<String, String>,
+) -> Result<TagNormalizationReport, String> {
+    let mut conn =
+        database::open_connection(db_path).map_err(|e| format!("Failed to open database: {e}"))?;
+
+    let tags_before =
+        distinct_tag_count(&conn).map_err(|e| format!("Failed to count tags: {e}"))?;
+
+    let clips = {
+        let mut stmt = conn
+            .prepare("SELECT id, tags FROM clips WHERE tags IS NOT NULL")
+            .map_err(|e| format!("Failed to prepare statement: {e}"))?;
+        let clips = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(|e| format!("Failed to execute query: {e}"))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to process row: {e}"))?;
+        clips
+    };
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {e}"))?;
+
+    let mut clips_updated = 0;
+    for (processed, (id, tags_json)) in clips.iter().enumerate() {
+        let Ok(tags) = serde_json::from_str::<Vec<String>>(tags_json) else {
+            continue;
+        };
+
+        let normalized = normalize_tag_list(&tags, synonyms);
+        if normalized != tags {
+            let normalized_json = serde_json::to_string(&normalized)
+                .map_err(|e| format!("Failed to serialize tags: {e}"))?;
+            // the clips_tags_update trigger keeps clip_tags in sync
+            tx.execute(
+                "UPDATE clips SET tags = ?1, clip = CASE WHEN json_type(clip, '$.tags') IS NULL THEN clip ELSE json_set(clip, '$.tags', json(?1)) END WHERE id = ?2",
+                params![normalized_json, id],
+            )
+            .map_err(|e| format!("Failed to update tags: {e}"))?;
+            clips_updated += 1;
+        }
+
+        guard.progress(app_handle, processed + 1, clips.len());
+    }
+
+    // merged spellings leave tags that no clip uses anymore
+    tx.execute(
+        "DELETE FROM tags WHERE id NOT IN (SELECT tag_id FROM clip_tags)",
+        [],
+    )
+    .map_err(|e| format!("Failed to remove unused tags: {e}"))?;
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {e}"))?;
+
+    let tags_after = distinct_tag_count(&conn).map_err(|e| format!("Failed to count tags: {e}"))?;
+
+    if clips_updated > 0 {
+        app_handle.emit("clips-updated", ()).ok();
+        app_handle.emit("tags-changed", ()).ok();
+    }
+
+    Ok(TagNormalizationReport {
+        tags_before,
+        tags_after,
+        clips_updated,
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub struct StripImagesReport {
+    pub clips_stripped: usize,
+    pub pinned_skipped: usize,
+    pub bytes_reclaimed: i64,
+}
+
+/// Drop the image data of every image clip in a category, keeping the row with its
+/// summary, tags and metadata. Pinned clips are left untouched.
+#[tauri::command]
+pub fn strip_images_in_category(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    category: String,
+    include_thumbnails: Option<bool>,
+) -> Result<StripImagesReport, String> {
+    let include_thumbnails = include_thumbnails.unwrap_or(false);
+
+    let mut conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {e}"))?;
+
+    let pinned_skipped: i64 = tx
+        .query_row(
+            "SELECT COUNT(*) FROM clips WHERE category = ? AND content_type = 'image' AND is_pinned = 1",
+            params![category],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to count pinned clips: {e}"))?;
+
+    // RETURNING only sees the new row, so measure what is about to go first
+    let bytes_reclaimed: i64 = tx
+        .query_row(
+            r#"
+            SELECT COALESCE(SUM(length(json_extract(clip, '$.content'))
+                + CASE WHEN ?2 THEN COALESCE(length(thumbnail), 0) ELSE 0 END), 0)
+            FROM clips
+            WHERE category = ?1 AND content_type = 'image' AND is_pinned = 0
+            "#,
+            params![category, include_thumbnails],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to measure image data: {e}"))?;
+
+    let ids = {
+        let mut stmt = tx
+            .prepare(
+                r#"
+                UPDATE clips
+                SET clip = json_set(clip, '$.content', '', '$.stripped', json('true')),
+                    thumbnail = CASE WHEN ?2 THEN NULL ELSE thumbnail END
+                WHERE category = ?1
+                  AND content_type = 'image'
+                  AND is_pinned = 0
+                  AND (json_extract(clip, '$.content') != '' OR (?2 AND thumbnail IS NOT NULL))
+                RETURNING id
+                "#,
+            )
+            .map_err(|e| format!("Failed to prepare statement: {e}"))?;
+
+        let ids = stmt
+            .query_map(params![category, include_thumbnails], |row| {
+                row.get::<_, i64>(0)
+            })
+            .map_err(|e| format!("Failed to strip images: {e}"))?
+            .map(|id| id.map(|id| id.to_string()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to strip images: {e}"))?;
+        ids
+    };
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {e}"))?;
+
+    if !ids.is_empty() {
+        app_handle.emit("clips-updated", &ids).ok();
+    }
+
+    Ok(StripImagesReport {
+        clips_stripped: ids.len(),
+        pinned_skipped: pinned_skipped as usize,
+        bytes_reclaimed,
+    })
+}
+
+/// Links fetched and summarized at the same time by backfill_url_summaries
+const URL_BACKFILL_CONCURRENCY: usize = 4;
+
+/// Text clips that hold nothing but a URL and have no summary yet
+fn links_without_summary(db_path: &Path) -> rusqlite::Result<Vec<(i64, String)>> {
+    let conn = database::open_connection(db_path)?;
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT id, json_extract(clip, '$.content')
+        FROM clips
+        WHERE content_type != 'image'
+          AND (summary IS NULL OR trim(summary) = '')
+          AND trim(json_extract(clip, '$.content')) LIKE 'http%'
+        ORDER BY id
+        "#,
+    )?;
+
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    // is_url is stricter than the LIKE above, e.g. it rejects text after the link
+    Ok(rows
+        .into_iter()
+        .filter_map(|(id, content)| {
+            let url = content?.trim().to_string();
+            is_url(&url).then_some((id, url))
+        })
+        .collect())
+}
+
+async fn summarize_link(
+    db_path: &Path,
+    policy: &FetchPolicy,
+    id: i64,
+    url: &str,
+) -> Result<(), String> {
+    // links the fetch policy refuses are summarized from the URL alone
+    let page = match policy.check(url).await {
+        Ok(()) => urls::fetch_page_details(url, policy).await?,
+        Err(refusal) => {
+            println!("Not fetching {}: {}", url, refusal);
+            PageDetails::default()
+        }
+    };
+
+    let mut text = url.to_string();
+    if let Some(title) = &page.title {
+        text.push_str(&format!("\n\nTitle: {}", title));
+    }
+    if let Some(description) = &page.description {
+        text.push_str(&format!("\nDescription: {}", description));
+    }
+
+    let summary = llm::get_clip_summary(&Clip::Text { plain: text })
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let conn =
+        database::open_connection(db_path).map_err(|e| format!("Failed to open database: {e}"))?;
+    conn.execute(
+        r#"
+        UPDATE clips
+        SET summary = ?1,
+            summary_updated_at = CURRENT_TIMESTAMP,
+            content_type = 'url',
+            normalized_url = COALESCE(normalized_url, ?2),
+            clip = json_set(clip, '$.summary', ?1, '$.page_title', ?4)
+        WHERE id = ?3
+        "#,
+        params![summary, urls::normalize_url(url), id, page.title],
+    )
+    .map_err(|e| format!("Failed to save summary: {e}"))?;
+
+    Ok(())
+}
+
+/// Summarize link clips saved without a summary. Dead links are counted as
+/// failed and keep their empty summary, so a later run retries them. Needs no
+/// resume cursor, summarized links drop out of the candidates on their own.
+#[tauri::command]
+pub async fn backfill_url_summaries(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<MaintenanceReport, String> {
+    let task = "backfill_url_summaries";
+    let guard = Arc::new(TaskGuard::acquire(&app_handle, task)?);
+    let db_path = state.db_path.clone();
+    let policy = Arc::new(FetchPolicy::from_settings(
+        &app_handle.state::<SettingsManagerState>().0,
+    ));
+
+    let links =
+        links_without_summary(&db_path).map_err(|e| format!("Failed to load link clips: {e}"))?;
+    let total = links.len();
+
+    let queue = Arc::new(Mutex::new(VecDeque::from(links)));
+    let processed = Arc::new(AtomicUsize::new(0));
+    let updated = Arc::new(AtomicUsize::new(0));
+    let failed = Arc::new(AtomicUsize::new(0));
+
+    let workers: Vec<_> = (0..URL_BACKFILL_CONCURRENCY.min(total))
+        .map(|_| {
+            let app_handle = app_handle.clone();
+            let db_path = db_path.clone();
+            let policy = policy.clone();
+            let guard = guard.clone();
+            let queue = queue.clone();
+            let processed = processed.clone();
+            let updated = updated.clone();
+            let failed = failed.clone();
+
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    let next = queue.lock().unwrap().pop_front();
+                    let Some((id, url)) = next else {
+                        break;
+                    };
+
+                    match summarize_link(&db_path, &policy, id, &url).await {
+                        Ok(()) => {
+                            updated.fetch_add(1, Ordering::Relaxed);
+                        }
+                        Err(e) => {
+                            eprintln!("Failed to summarize link clip {}: {}", id, e);
+                            failed.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+
+                    let done = processed.fetch_add(1, Ordering::Relaxed) + 1;
+                    guard.progress(&app_handle, done, total);
+                }
+            })
+        })
+        .collect();
+
+    for worker in workers {
+        worker
+            .await
+            .map_err(|e| format!("Summary worker failed: {e}"))?;
+    }
+
+    let report = MaintenanceReport {
+        task: task.to_string(),
+        total,
+        updated: updated.load(Ordering::Relaxed),
+        failed: failed.load(Ordering::Relaxed),
+        ..Default::default()
+    };
+
+    if report.updated > 0 {
+        app_handle.emit("clips-updated", ()).ok();
+    }
+
+    Ok(report)
+}
+
+/// Text clips with the content type and normalized URL stored for them, the
+/// colored original is used where there is one so terminal output stays terminal
+fn text_clip_types(db_path: &Path) -> rusqlite::Result<Vec<(i64, String, String, Option<String>)>> {
+    let conn = database::open_connection(db_path)?;
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT id, COALESCE(json_extract(clip, '$.raw'), json_extract(clip, '$.content'), ''), content_type, normalized_url
+        FROM clips
+        WHERE content_type != 'image'
+        ORDER BY id
+        "#,
+    )?;
+
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                row.get::<_, Option<String>>(3)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(rows)
+}
+
+/// Recompute the content type and normalized URL of every text clip with the
+/// current URL detection, no LLM calls involved. `backfill_summaries` then
+/// summarizes the clips that turned into links, see backfill_url_summaries.
+#[tauri::command]
+pub async fn reclassify_urls(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    backfill_summaries: Option<bool>,
+) -> Result<MaintenanceReport, String> {
+    let task = "reclassify_urls";
+    let guard = TaskGuard::acquire(&app_handle, task)?;
+    let db_path = state.db_path.clone();
+
+    let clips = text_clip_types(&db_path).map_err(|e| format!("Failed to load text clips: {e}"))?;
+
+    let mut report = MaintenanceReport {
+        task: task.to_string(),
+        total: clips.len(),
+        ..Default::default()
+    };
+
+    let mut conn =
+        database::open_connection(&db_path).map_err(|e| format!("Failed to open database: {e}"))?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {e}"))?;
+
+    for (processed, (id, plain, old_type, old_url)) in clips.iter().enumerate() {
+        let new_type = content_type(&Clip::Text {
+            plain: plain.clone(),
+        });
+        let new_url = if is_url(plain) {
+            urls::normalize_url(plain)
+        } else {
+            None
+        };
+
+        if new_type != old_type || new_url != *old_url {
+            match tx.execute(
+                "UPDATE clips SET content_type = ?1, normalized_url = ?2 WHERE id = ?3",
+                params![new_type, new_url, id],
+            ) {
+                Ok(_) => report.updated += 1,
+                Err(e) => {
+                    eprintln!("Failed to reclassify clip {}: {}", id, e);
+                    report.failed += 1;
+                }
+            }
+        }
+
+        guard.progress(&app_handle, processed + 1, report.total);
+    }
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {e}"))?;
+
+    // the backfill takes its own guard
+    drop(guard);
+
+    if report.updated > 0 {
+        app_handle.emit("clips-updated", ()).ok();
+    }
+
+    if backfill_summaries.unwrap_or(false) {
+        backfill_url_summaries(app_handle, state).await?;
+    }
+
+    Ok(report)
+}
+
+/// Run the stored text clips through the `capture_transforms` setting, for clips
+/// saved before those transforms were configured. Clips the transforms leave as
+/// they are are skipped, so running it again changes nothing. A clip that turns
+/// into a copy of another one is kept as it was and counted as failed.
+#[tauri::command]
+pub async fn normalize_clips(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<MaintenanceReport, String> {
+    let task = "normalize_clips";
+    let guard = TaskGuard::acquire(&app_handle, task)?;
+
+    let configured =
+        transforms::configured_transforms(&app_handle.state::<SettingsManagerState>().0);
+    if configured.is_empty() {
+        return Err("No capture transforms are configured".to_string());
+    }
+
+    let clips =
+        text_clip_types(&state.db_path).map_err(|e| format!("Failed to load text clips: {e}"))?;
+
+    let mut report = MaintenanceReport {
+        task: task.to_string(),
+        total: clips.len(),
+        ..Default::default()
+    };
+
+    let mut conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {e}"))?;
+
+    for (processed, (id, original, _, _)) in clips.iter().enumerate() {
+        let clip = transforms::apply_transforms(
+            &Clip::Text {
+                plain: original.clone(),
+            },
+            &configured,
+        );
+        let Clip::Text { plain } = &clip else {
+            continue;
+        };
+
+        if plain != original {
+            let raw = ansi::contains_ansi(plain).then_some(plain);
+            let normalized_url = if is_url(plain) {
+                urls::normalize_url(plain)
+            } else {
+                None
+            };
+
+            match tx.execute(
+                r#"
+                UPDATE clips
+                SET clip = CASE WHEN ?2 IS NULL
+                        THEN json_remove(json_set(clip, '$.content', ?1), '$.raw')
+                        ELSE json_set(clip, '$.content', ?1, '$.raw', ?2) END,
+                    content_hash = ?3,
+                    code_language = ?4,
+                    content_type = ?5,
+                    normalized_url = ?6
+                WHERE id = ?7
+                "#,
+                params![
+                    ansi::strip_ansi(plain),
+                    raw,
+                    content_hash(&clip),
+                    heuristic::detect_language(plain),
+                    content_type(&clip),
+                    normalized_url,
+                    id
+                ],
+            ) {
+                Ok(_) => report.updated += 1,
+                Err(e) => {
+                    eprintln!("Failed to normalize clip {}: {}", id, e);
+                    report.failed += 1;
+                }
+            }
+        }
+
+        guard.progress(&app_handle, processed + 1, report.total);
+    }
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {e}"))?;
+
+    if report.updated > 0 {
+        app_handle.emit("clips-updated", ()).ok();
+    }
+
+    Ok(report)
+}
+
+/// Find and remove rows left behind by deleted clips across the tables related
+/// to clips, all in one transaction
+#[tauri::command]
+pub async fn repair_database(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<RepairReport, String> {
+    let _guard = TaskGuard::acquire(&app_handle, "repair_database")?;
+
+    let mut conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {e}"))?;
+    let report =
+        database::remove_orphans(&tx).map_err(|e| format!("Failed to repair database: {e}"))?;
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {e}"))?;
+
+    if report.clip_tags > 0 || report.tags > 0 {
+        app_handle.emit("tags-changed", ()).ok();
+    }
+
+    Ok(report)
+}
+
+/// How many clips are missing each enrichment a backfill can add
+#[derive(Debug, Default, Serialize)]
+pub struct BackfillStatus {
+    pub total: usize,
+    /// image clips without a thumbnail, see regenerate_thumbnails
+    pub thumbnail: usize,
+    /// clips without a content_hash, filled in on the next start
+    pub hash: usize,
+    /// clips without a summary, e.g. saved while summarize_on_capture was off
+    pub summary: usize,
+    /// the link clips among them, which backfill_url_summaries picks up
+    pub url_summary: usize,
+}
+
+/// Count what still needs processing, per enrichment. There are no embeddings
+/// or OCR text to backfill yet; they get a field here once they exist.
+#[tauri::command]
+pub async fn get_backfill_status(state: State<'_, AppState>) -> Result<BackfillStatus, String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    let (total, thumbnail, hash, summary): (i64, i64, i64, i64) = conn
+        .query_row(
+            r#"
+            SELECT COUNT(*),
+                   COALESCE(SUM(content_type = 'image' AND thumbnail IS NULL), 0),
+                   COALESCE(SUM(content_hash IS NULL), 0),
+                   COALESCE(SUM(summary IS NULL OR trim(summary) = ''), 0)
+            FROM clips
+            "#,
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .map_err(|e| format!("Failed to count clips: {e}"))?;
+
+    let url_summary = links_without_summary(&state.db_path)
+        .map_err(|e| format!("Failed to load link clips: {e}"))?
+        .len();
+
+    Ok(BackfillStatus {
+        total: total as usize,
+        thumbnail: thumbnail as usize,
+        hash: hash as usize,
+        summary: summary as usize,
+        url_summary,
+    })
+}
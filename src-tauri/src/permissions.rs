@@ -0,0 +1,118 @@
+use crate::settings::SettingsManagerState;
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Minimum time between two permission prompts so repeated hotkey presses don't spam the UI
+const REPORT_INTERVAL: Duration = Duration::from_secs(10);
+
+static LAST_REPORT: Mutex<Option<Instant>> = Mutex::new(None);
+
+#[cfg(target_os = "macos")]
+const ACCESSIBILITY_INSTRUCTIONS: &str = "Spiegel needs Accessibility access to copy your selection. Open System Settings > Privacy & Security > Accessibility, enable Spiegel and press the hotkey again.";
+
+#[cfg(not(target_os = "macos"))]
+const ACCESSIBILITY_INSTRUCTIONS: &str =
+    "Spiegel could not simulate the copy keystroke. Make sure input simulation is allowed for this app.";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PermissionStatus {
+    pub accessibility: bool,
+    pub instructions: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PermissionError {
+    pub permission: String,
+    pub detail: String,
+    pub instructions: String,
+}
+
+#[cfg(target_os = "macos")]
+#[link(name = "ApplicationServices", kind = "framework")]
+extern "C" {
+    fn AXIsProcessTrusted() -> bool;
+}
+
+/// Whether the app may post keyboard events to other applications
+#[cfg(target_os = "macos")]
+pub fn accessibility_granted() -> bool {
+    unsafe { AXIsProcessTrusted() }
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn accessibility_granted() -> bool {
+    true
+}
+
+pub fn status() -> PermissionStatus {
+    let accessibility = accessibility_granted();
+    PermissionStatus {
+        accessibility,
+        instructions: (!accessibility).then(|| ACCESSIBILITY_INSTRUCTIONS.to_string()),
+    }
+}
+
+/// Tell the user a capture failed for lack of a permission, according to the
+/// `permission_error_action` setting: "notify" (default), "open_settings" or "ignore"
+pub fn report_permission_error(app_handle: &AppHandle, permission: &str, detail: &str) {
+    eprintln!(
+        "Capture blocked by missing {} permission: {}",
+        permission, detail
+    );
+
+    let action = app_handle
+        .state::<SettingsManagerState>()
+        .0
+        .get_string("permission_error_action");
+
+    if action == "ignore" {
+        return;
+    }
+
+    {
+        let mut last_report = LAST_REPORT.lock().unwrap();
+        if last_report.is_some_and(|at| at.elapsed() < REPORT_INTERVAL) {
+            return;
+        }
+        *last_report = Some(Instant::now());
+    }
+
+    let error = PermissionError {
+        permission: permission.to_string(),
+        detail: detail.to_string(),
+        instructions: ACCESSIBILITY_INSTRUCTIONS.to_string(),
+    };
+    app_handle.emit("permission-required", error).ok();
+
+    if action == "open_settings" {
+        if let Err(e) = open_settings_pane() {
+            eprintln!("Failed to open permission settings: {}", e);
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn open_settings_pane() -> Result<(), String> {
+    std::process::Command::new("open")
+        .arg("x-apple.systempreferences:com.apple.preference.security?Privacy_Accessibility")
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to open System Settings: {e}"))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn open_settings_pane() -> Result<(), String> {
+    Err("No permission settings to open on this platform".to_string())
+}
+
+#[tauri::command]
+pub fn check_permissions() -> PermissionStatus {
+    status()
+}
+
+#[tauri::command]
+pub fn open_permission_settings() -> Result<(), String> {
+    open_settings_pane()
+}
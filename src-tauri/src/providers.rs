@@ -0,0 +1,613 @@
+use crate::llm::{self, CategoryResponse, Prompt, DEFAULT_MODEL, MAX_OUTPUT_TOKENS};
+use crate::settings::SettingsManager;
+use crate::shortcut::Clip;
+use async_openai::{
+    config::OpenAIConfig,
+    error::OpenAIError,
+    types::{
+        responses::{
+            Content, ContentType, CreateResponse, CreateResponseArgs, Input, InputContent,
+            InputImageArgs, InputItem, InputMessageArgs, OutputContent, Response, Role,
+        },
+        ImageDetail,
+    },
+    Client,
+};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::StatusCode;
+use std::error::Error;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Where categories and summaries come from, the capture pipeline does not care which
+pub trait LlmProvider {
+    async fn categorize(
+        &self,
+        clip: &Clip,
+        recent_clips: &[String],
+    ) -> Result<CategoryResponse, Box<dyn Error>>;
+
+    async fn summarize(&self, clip: &Clip) -> Result<String, Box<dyn Error>>;
+}
+
+/// An API that answered with an error status, the reason is in the body
+#[derive(Debug)]
+pub struct StatusError {
+    pub provider: &'static str,
+    pub status: StatusCode,
+    pub body: String,
+}
+
+impl fmt::Display for StatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} API returned {}: {}",
+            self.provider, self.status, self.body
+        )
+    }
+}
+
+impl Error for StatusError {}
+
+/// Rate limits and server errors pass, anything else (a bad key, a bad request)
+/// fails the same way on the next try
+fn is_transient_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+fn is_transient_reqwest(error: &reqwest::Error) -> bool {
+    match error.status() {
+        Some(status) => is_transient_status(status),
+        None => error.is_timeout() || error.is_connect(),
+    }
+}
+
+/// Whether a failed request is worth repeating: rate limits, server errors,
+/// timeouts and dropped connections are, authentication and parse errors are not.
+/// OpenAI rate limits are not, async-openai has retried them for
+/// RATE_LIMIT_RETRY_WINDOW already and the fallback model has had its turn.
+pub fn is_transient(error: &(dyn Error + 'static)) -> bool {
+    if let Some(error) = error.downcast_ref::<StatusError>() {
+        return is_transient_status(error.status);
+    }
+    if let Some(error) = error.downcast_ref::<reqwest::Error>() {
+        return is_transient_reqwest(error);
+    }
+    match error.downcast_ref::<OpenAIError>() {
+        Some(error) if is_rate_limit(error) => false,
+        Some(OpenAIError::ApiError(api_error)) => {
+            api_error.r#type.as_deref() == Some("server_error")
+        }
+        Some(OpenAIError::Reqwest(error)) => is_transient_reqwest(error),
+        _ => false,
+    }
+}
+
+/// Models each `llm_provider` is known to serve, None for Ollama which runs
+/// whatever the user pulled
+pub fn known_models(provider: &str) -> Option<&'static [&'static str]> {
+    match provider {
+        "openai" => Some(&[
+            "gpt-4o",
+            "gpt-4o-mini",
+            "gpt-4.1",
+            "gpt-4.1-mini",
+            "gpt-4.1-nano",
+        ]),
+        "anthropic" => Some(&[
+            "claude-3-5-haiku-latest",
+            "claude-3-5-sonnet-latest",
+            "claude-3-7-sonnet-latest",
+            "claude-sonnet-4-0",
+            "claude-opus-4-0",
+        ]),
+        _ => None,
+    }
+}
+
+/// The provider picked by the `llm_provider` setting
+#[derive(Debug, Clone)]
+pub enum Provider {
+    OpenAi(OpenAiProvider),
+    Ollama(OllamaProvider),
+    Anthropic(AnthropicProvider),
+}
+
+impl Default for Provider {
+    fn default() -> Self {
+        Provider::OpenAi(OpenAiProvider)
+    }
+}
+
+impl Provider {
+    pub fn from_settings(settings: &SettingsManager) -> Self {
+        match settings.get_string("llm_provider").as_str() {
+            "ollama" => Provider::Ollama(OllamaProvider {
+                base_url: settings.get_string("ollama_url"),
+                model: settings.get_string("ollama_model"),
+            }),
+            "anthropic" => Provider::Anthropic(AnthropicProvider),
+            _ => Provider::default(),
+        }
+    }
+
+    /// The model requests go to, before any fallback
+    pub fn model(&self) -> String {
+        match self {
+            Provider::OpenAi(_) => openai_model(),
+            Provider::Ollama(provider) => provider.model.clone(),
+            Provider::Anthropic(_) => llm::setting("llm_model"),
+        }
+    }
+
+    /// Whether requests can succeed at all, false when the provider needs an API
+    /// key and none is configured. Ollama runs locally without one.
+    pub fn has_credentials(&self) -> bool {
+        match self {
+            Provider::OpenAi(_) => !openai_api_key().trim().is_empty(),
+            Provider::Ollama(_) => true,
+            Provider::Anthropic(_) => !llm::setting("llm_api_key_anthropic").trim().is_empty(),
+        }
+    }
+}
+
+impl LlmProvider for Provider {
+    async fn categorize(
+        &self,
+        clip: &Clip,
+        recent_clips: &[String],
+    ) -> Result<CategoryResponse, Box<dyn Error>> {
+        match self {
+            Provider::OpenAi(provider) => provider.categorize(clip, recent_clips).await,
+            Provider::Ollama(provider) => provider.categorize(clip, recent_clips).await,
+            Provider::Anthropic(provider) => provider.categorize(clip, recent_clips).await,
+        }
+    }
+
+    async fn summarize(&self, clip: &Clip) -> Result<String, Box<dyn Error>> {
+        match self {
+            Provider::OpenAi(provider) => provider.summarize(clip).await,
+            Provider::Ollama(provider) => provider.summarize(clip).await,
+            Provider::Anthropic(provider) => provider.summarize(clip).await,
+        }
+    }
+}
+
+/// The `llm_api_key` setting, or OPENAI_API_KEY from the environment when it is unset
+fn openai_api_key() -> String {
+    let api_key = llm::setting("llm_api_key");
+    if api_key.trim().is_empty() {
+        std::env::var("OPENAI_API_KEY").unwrap_or_default()
+    } else {
+        api_key
+    }
+}
+
+/// The `openai_model` setting, DEFAULT_MODEL before init_llm has run
+fn openai_model() -> String {
+    let model = llm::setting("openai_model");
+    match model.trim() {
+        "" => DEFAULT_MODEL.to_string(),
+        model => model.to_string(),
+    }
+}
+
+/// OpenAI's responses API, with the key and model read on every request, see
+/// `openai_api_key` and `openai_model`
+#[derive(Debug, Clone)]
+pub struct OpenAiProvider;
+
+impl LlmProvider for OpenAiProvider {
+    async fn categorize(
+        &self,
+        clip: &Clip,
+        recent_clips: &[String],
+    ) -> Result<CategoryResponse, Box<dyn Error>> {
+        let configured = openai_model();
+        let request = openai_request(&configured, &llm::category_prompt(clip, recent_clips))?;
+        let (response, model) = create_response("category", request).await?;
+        let fallback_model = (model != configured).then_some(model);
+
+        let parsed = response
+            .output
+            .iter()
+            .filter_map(extract_content_from_output)
+            .find_map(|content| llm::parse_category(&content));
+
+        Ok(match parsed {
+            Some(mut category_response) => {
+                category_response.fallback_model = fallback_model;
+                category_response
+            }
+            None => llm::default_category(clip),
+        })
+    }
+
+    async fn summarize(&self, clip: &Clip) -> Result<String, Box<dyn Error>> {
+        let request = openai_request(&openai_model(), &llm::summary_prompt(clip))?;
+        let (response, _) = create_response("summary", request).await?;
+
+        Ok(response
+            .output
+            .iter()
+            .filter_map(extract_content_from_output)
+            .map(|content| content.trim().to_string())
+            .find(|summary| !summary.is_empty())
+            .unwrap_or_else(|| llm::NO_SUMMARY.to_string()))
+    }
+}
+
+fn openai_request(model: &str, prompt: &Prompt) -> Result<CreateResponse, OpenAIError> {
+    let mut items = vec![
+        InputItem::Message(
+            InputMessageArgs::default()
+                .role(Role::System)
+                .content(prompt.system)
+                .build()?,
+        ),
+        InputItem::Message(
+            InputMessageArgs::default()
+                .role(Role::User)
+                .content(prompt.user.as_str())
+                .build()?,
+        ),
+    ];
+
+    if let Some(data) = prompt.image {
+        let im = InputImageArgs::default()
+            .image_url(format!("data:image/png;base64,{}", data))
+            .detail(ImageDetail::Auto)
+            .build()?;
+
+        items.push(InputItem::Message(
+            InputMessageArgs::default()
+                .role(Role::User)
+                .content(InputContent::InputItemContentList(vec![
+                    ContentType::InputImage(im),
+                ]))
+                .build()?,
+        ));
+    }
+
+    CreateResponseArgs::default()
+        .max_output_tokens(MAX_OUTPUT_TOKENS)
+        .model(model)
+        .input(Input::Items(items))
+        .build()
+}
+
+/// How long a rate limited request is retried before the fallback model gets a turn.
+/// Without a fallback async-openai's own default applies, up to 15 minutes.
+const RATE_LIMIT_RETRY_WINDOW: Duration = Duration::from_secs(30);
+
+fn is_rate_limit(error: &OpenAIError) -> bool {
+    match error {
+        OpenAIError::ApiError(api_error) => {
+            api_error.code.as_deref() == Some("rate_limit_exceeded")
+                || matches!(api_error.r#type.as_deref(), Some("requests" | "tokens"))
+        }
+        _ => false,
+    }
+}
+
+fn record_openai_usage(
+    stage: &str,
+    model: &str,
+    response: &Result<Response, OpenAIError>,
+    latency: Duration,
+) {
+    let tokens = response
+        .as_ref()
+        .ok()
+        .and_then(|response| response.usage.as_ref())
+        .map(|usage| (usage.input_tokens, usage.output_tokens));
+    llm::record_usage(stage, model, tokens, response.is_ok(), latency);
+}
+
+/// Send a request and record its usage. When the model stays rate limited after
+/// the client's retries and a fallback model is configured, the request is sent
+/// once more to the fallback. Returns the response and the model that answered.
+async fn create_response(
+    stage: &str,
+    mut request: CreateResponse,
+) -> Result<(Response, String), OpenAIError> {
+    let fallback = llm::fallback_model();
+
+    let mut client = Client::with_config(OpenAIConfig::new().with_api_key(openai_api_key()));
+    if fallback.is_some() {
+        client = client.with_backoff(backoff::ExponentialBackoff {
+            max_elapsed_time: Some(RATE_LIMIT_RETRY_WINDOW),
+            ..Default::default()
+        });
+    }
+
+    let model = request.model.clone();
+    let started = Instant::now();
+    let response = client.responses().create(request.clone()).await;
+    record_openai_usage(stage, &model, &response, started.elapsed());
+
+    match (response, fallback) {
+        (Err(e), Some(fallback)) if is_rate_limit(&e) && fallback != model => {
+            eprintln!(
+                "{} is rate limited, using {} for this {} request",
+                model, fallback, stage
+            );
+            request.model = fallback.clone();
+
+            let started = Instant::now();
+            let response = client.responses().create(request).await;
+            record_openai_usage(stage, &fallback, &response, started.elapsed());
+            response.map(|response| (response, fallback))
+        }
+        (response, _) => response.map(|response| (response, model)),
+    }
+}
+
+fn extract_content_from_output(output: &OutputContent) -> Option<String> {
+    match output {
+        OutputContent::Message(message) => {
+            for content_item in &message.content {
+                match content_item {
+                    Content::OutputText(output_text) => {
+                        return Some(output_text.text.clone());
+                    }
+                    _ => continue,
+                }
+            }
+            None
+        }
+        _ => None,
+    }
+}
+
+/// Local models are slow on the first request while they load
+const OLLAMA_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// A model served by Ollama's chat API, usually on this machine
+#[derive(Debug, Clone)]
+pub struct OllamaProvider {
+    base_url: String,
+    model: String,
+}
+
+impl OllamaProvider {
+    /// Send one prompt and return the reply, `json` asks the model for a JSON object
+    async fn chat(
+        &self,
+        stage: &str,
+        prompt: &Prompt<'_>,
+        json: bool,
+    ) -> Result<String, Box<dyn Error>> {
+        let mut user = serde_json::json!({ "role": "user", "content": prompt.user });
+        if let Some(data) = prompt.image {
+            user["images"] = serde_json::json!([data]);
+        }
+
+        let mut body = serde_json::json!({
+            "model": self.model,
+            "stream": false,
+            "messages": [{ "role": "system", "content": prompt.system }, user],
+            "options": { "num_predict": MAX_OUTPUT_TOKENS },
+        });
+        if json {
+            body["format"] = serde_json::json!("json");
+        }
+
+        let started = Instant::now();
+        let response = self.send(&body).await;
+
+        let tokens = response.as_ref().ok().and_then(|response| {
+            Some((
+                response["prompt_eval_count"].as_u64()? as u32,
+                response["eval_count"].as_u64()? as u32,
+            ))
+        });
+        llm::record_usage(
+            stage,
+            &self.model,
+            tokens,
+            response.is_ok(),
+            started.elapsed(),
+        );
+
+        Ok(response?["message"]["content"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string())
+    }
+
+    async fn send(&self, body: &serde_json::Value) -> Result<serde_json::Value, Box<dyn Error>> {
+        let client = reqwest::Client::builder().timeout(OLLAMA_TIMEOUT).build()?;
+
+        let response = client
+            .post(format!("{}/api/chat", self.base_url.trim_end_matches('/')))
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.to_string())
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(serde_json::from_str(&response.text().await?)?)
+    }
+}
+
+impl LlmProvider for OllamaProvider {
+    async fn categorize(
+        &self,
+        clip: &Clip,
+        recent_clips: &[String],
+    ) -> Result<CategoryResponse, Box<dyn Error>> {
+        let content = self
+            .chat("category", &llm::category_prompt(clip, recent_clips), true)
+            .await?;
+        Ok(llm::parse_category(&content).unwrap_or_else(|| llm::default_category(clip)))
+    }
+
+    async fn summarize(&self, clip: &Clip) -> Result<String, Box<dyn Error>> {
+        let content = self
+            .chat("summary", &llm::summary_prompt(clip), false)
+            .await?;
+        let summary = content.trim();
+
+        Ok(if summary.is_empty() {
+            llm::NO_SUMMARY.to_string()
+        } else {
+            summary.to_string()
+        })
+    }
+}
+
+const ANTHROPIC_MESSAGES_URL: &str = "https://api.anthropic.com/v1/messages";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+const ANTHROPIC_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Anthropic's Messages API, with the key from `llm_api_key_anthropic` and the model
+/// from `llm_model`, both read on every request
+#[derive(Debug, Clone)]
+pub struct AnthropicProvider;
+
+impl AnthropicProvider {
+    /// Send one prompt and return the text of the reply
+    async fn message(&self, stage: &str, prompt: &Prompt<'_>) -> Result<String, Box<dyn Error>> {
+        let api_key = llm::setting("llm_api_key_anthropic");
+        if api_key.trim().is_empty() {
+            return Err("No Anthropic API key set".into());
+        }
+        let model = llm::setting("llm_model");
+
+        // the image goes first, Claude reads the question about it after seeing it
+        let mut content = Vec::new();
+        if let Some(data) = prompt.image {
+            content.push(serde_json::json!({
+                "type": "image",
+                "source": { "type": "base64", "media_type": "image/png", "data": data },
+            }));
+        }
+        content.push(serde_json::json!({ "type": "text", "text": prompt.user }));
+
+        let body = serde_json::json!({
+            "model": model,
+            "max_tokens": MAX_OUTPUT_TOKENS,
+            "system": prompt.system,
+            "messages": [{ "role": "user", "content": content }],
+        });
+
+        let started = Instant::now();
+        let response = self.send(api_key.trim(), &body).await;
+
+        let tokens = response.as_ref().ok().and_then(|response| {
+            Some((
+                response["usage"]["input_tokens"].as_u64()? as u32,
+                response["usage"]["output_tokens"].as_u64()? as u32,
+            ))
+        });
+        llm::record_usage(stage, &model, tokens, response.is_ok(), started.elapsed());
+
+        let text = response?["content"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|block| block["type"] == "text")
+            .filter_map(|block| block["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("");
+        Ok(text)
+    }
+
+    async fn send(
+        &self,
+        api_key: &str,
+        body: &serde_json::Value,
+    ) -> Result<serde_json::Value, Box<dyn Error>> {
+        let client = reqwest::Client::builder()
+            .timeout(ANTHROPIC_TIMEOUT)
+            .build()?;
+
+        let response = client
+            .post(ANTHROPIC_MESSAGES_URL)
+            .header("x-api-key", api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.to_string())
+            .send()
+            .await?;
+
+        // error responses carry the reason in their body
+        let status = response.status();
+        let text = response.text().await?;
+        if !status.is_success() {
+            return Err(StatusError {
+                provider: "Anthropic",
+                status,
+                body: text,
+            }
+            .into());
+        }
+
+        Ok(serde_json::from_str(&text)?)
+    }
+}
+
+impl LlmProvider for AnthropicProvider {
+    async fn categorize(
+        &self,
+        clip: &Clip,
+        recent_clips: &[String],
+    ) -> Result<CategoryResponse, Box<dyn Error>> {
+        let content = self
+            .message("category", &llm::category_prompt(clip, recent_clips))
+            .await?;
+        Ok(llm::parse_category(&content).unwrap_or_else(|| llm::default_category(clip)))
+    }
+
+    async fn summarize(&self, clip: &Clip) -> Result<String, Box<dyn Error>> {
+        let content = self.message("summary", &llm::summary_prompt(clip)).await?;
+        let summary = content.trim();
+
+        Ok(if summary.is_empty() {
+            llm::NO_SUMMARY.to_string()
+        } else {
+            summary.to_string()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_openai::error::ApiError;
+
+    fn api_error(r#type: &str, code: Option<&str>) -> Box<dyn Error> {
+        Box::new(OpenAIError::ApiError(ApiError {
+            message: "failed".to_string(),
+            r#type: Some(r#type.to_string()),
+            param: None,
+            code: code.map(str::to_string),
+        }))
+    }
+
+    #[test]
+    fn openai_rate_limits_are_not_retried_again() {
+        assert!(!is_transient(
+            api_error("requests", Some("rate_limit_exceeded")).as_ref()
+        ));
+        assert!(!is_transient(api_error("tokens", None).as_ref()));
+        assert!(is_transient(api_error("server_error", None).as_ref()));
+        assert!(!is_transient(
+            api_error("invalid_request_error", Some("invalid_api_key")).as_ref()
+        ));
+    }
+
+    #[test]
+    fn status_errors_retry_rate_limits_and_server_errors() {
+        let status = |status| StatusError {
+            provider: "Anthropic",
+            status,
+            body: String::new(),
+        };
+        assert!(is_transient(&status(StatusCode::TOO_MANY_REQUESTS)));
+        assert!(is_transient(&status(StatusCode::BAD_GATEWAY)));
+        assert!(!is_transient(&status(StatusCode::UNAUTHORIZED)));
+    }
+}
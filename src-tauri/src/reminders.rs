@@ -0,0 +1,102 @@
+use crate::commands::{clip_item_from_row, ClipItem, CLIP_ITEM_COLUMNS, VISIBLE_CLIPS};
+use crate::database;
+use crate::AppState;
+use rusqlite::{params, Connection};
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, State};
+
+const REMINDER_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Clear every reminder that is due and return the clips they belong to. Reminders
+/// that came due while the app was closed are picked up on the first run.
+fn take_due_reminders(conn: &Connection) -> rusqlite::Result<Vec<ClipItem>> {
+    let mut stmt = conn.prepare(&format!(
+        "UPDATE clips SET remind_at = NULL WHERE remind_at IS NOT NULL AND remind_at <= CURRENT_TIMESTAMP AND {VISIBLE_CLIPS} RETURNING {CLIP_ITEM_COLUMNS}"
+    ))?;
+    let items = stmt
+        .query_map([], clip_item_from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(items)
+}
+
+/// Emit a "clip-reminder" event for each reminder as it comes due
+pub fn start_reminder_task(app_handle: AppHandle, db_path: PathBuf) {
+    thread::spawn(move || loop {
+        match database::open_connection(&db_path).and_then(|conn| take_due_reminders(&conn)) {
+            Ok(items) => {
+                for item in items {
+                    let id = item.id.clone();
+                    app_handle.emit("clip-reminder", item).ok();
+                    app_handle.emit("clip-updated", id).ok();
+                }
+            }
+            Err(e) => eprintln!("Failed to check reminders: {}", e),
+        }
+
+        thread::sleep(REMINDER_INTERVAL);
+    });
+}
+
+/// Set or, with no `when`, clear the reminder of a clip. `when` is anything SQLite's
+/// datetime() understands, e.g. an ISO 8601 timestamp with a timezone offset.
+#[tauri::command]
+pub fn set_reminder(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    item_id: String,
+    when: Option<String>,
+) -> Result<(), String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    let remind_at = match when {
+        Some(when) => Some(
+            conn.query_row("SELECT datetime(?)", params![when], |row| {
+                row.get::<_, Option<String>>(0)
+            })
+            .map_err(|e| format!("Failed to parse reminder time: {e}"))?
+            .ok_or_else(|| format!("Invalid reminder time '{}'", when))?,
+        ),
+        None => None,
+    };
+
+    let rows_affected = conn
+        .execute(
+            "UPDATE clips SET remind_at = ? WHERE id = ?",
+            params![remind_at, item_id],
+        )
+        .map_err(|e| format!("Failed to set reminder: {e}"))?;
+
+    if rows_affected == 0 {
+        return Err("Item not found".to_string());
+    }
+
+    app_handle
+        .emit("clip-updated", &item_id)
+        .map_err(|e| format!("Failed to emit event: {}", e))?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_upcoming_reminders(state: State<'_, AppState>) -> Result<Vec<ClipItem>, String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {CLIP_ITEM_COLUMNS} FROM clips WHERE remind_at IS NOT NULL AND {VISIBLE_CLIPS} ORDER BY remind_at"
+        ))
+        .map_err(|e| format!("Failed to prepare statement: {e}"))?;
+
+    let items = stmt
+        .query_map([], clip_item_from_row)
+        .map_err(|e| format!("Failed to execute query: {e}"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to process row: {e}"))?;
+
+    Ok(items)
+}
@@ -0,0 +1,192 @@
+use crate::settings::SettingsManager;
+use crate::shortcut::Clip;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// A user defined rule from the `tag_rules` setting. Every pattern given has to
+/// match for the rule to apply; patterns are regular expressions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagRule {
+    /// matched against the text of a clip, or the text copied along with an image
+    pub content: Option<String>,
+    pub source_app: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// category the clip gets without asking the LLM
+    pub category: Option<String>,
+}
+
+/// What the matching rules add to a capture
+#[derive(Debug, Default)]
+pub struct RuleMatch {
+    pub tags: Vec<String>,
+    /// from the first matching rule that sets one
+    pub category: Option<String>,
+}
+
+fn compile(pattern: &Option<String>) -> Result<Option<Regex>, regex::Error> {
+    pattern.as_deref().map(Regex::new).transpose()
+}
+
+impl TagRule {
+    /// Why the rule can never be applied, None when it is fine
+    pub fn problem(&self) -> Option<String> {
+        if self.content.is_none() && self.source_app.is_none() {
+            return Some("Rules need a content or source_app pattern".to_string());
+        }
+        if self.tags.is_empty() && self.category.is_none() {
+            return Some("Rules need tags or a category to add".to_string());
+        }
+        compile(&self.content)
+            .and(compile(&self.source_app))
+            .err()
+            .map(|e| format!("Invalid pattern: {}", e))
+    }
+
+    /// The rule with its patterns compiled, for matching captures against
+    fn compiled(&self) -> Result<CompiledRule, regex::Error> {
+        Ok(CompiledRule {
+            content: compile(&self.content)?,
+            source_app: compile(&self.source_app)?,
+            tags: self.tags.clone(),
+            category: self.category.clone(),
+        })
+    }
+}
+
+/// A `TagRule` whose patterns are compiled once, not on every capture
+#[derive(Debug)]
+pub struct CompiledRule {
+    content: Option<Regex>,
+    source_app: Option<Regex>,
+    tags: Vec<String>,
+    category: Option<String>,
+}
+
+impl CompiledRule {
+    fn matches(&self, text: Option<&str>, source_app: Option<&str>) -> bool {
+        let content_matches = self
+            .content
+            .as_ref()
+            .is_none_or(|pattern| text.is_some_and(|text| pattern.is_match(text)));
+        let app_matches = self
+            .source_app
+            .as_ref()
+            .is_none_or(|pattern| source_app.is_some_and(|app| pattern.is_match(app)));
+
+        content_matches && app_matches
+    }
+}
+
+/// The `tag_rules` setting, compiled. Empty when unset or invalid; a rule whose
+/// patterns do not compile is left out.
+pub fn configured_rules(settings: &SettingsManager) -> Vec<CompiledRule> {
+    compile_rules(serde_json::from_str(&settings.get_string("tag_rules")).unwrap_or_default())
+}
+
+fn compile_rules(rules: Vec<TagRule>) -> Vec<CompiledRule> {
+    rules
+        .iter()
+        .filter_map(|rule| rule.compiled().ok())
+        .collect()
+}
+
+/// Tags and category of every rule matching the clip, in the order the rules are listed
+pub fn apply_rules(rules: &[CompiledRule], clip: &Clip, source_app: Option<&str>) -> RuleMatch {
+    let text = match clip {
+        Clip::Text { plain } => Some(plain.as_str()),
+        Clip::Image { text, .. } => text.as_deref(),
+    };
+
+    let mut result = RuleMatch::default();
+    for rule in rules.iter().filter(|rule| rule.matches(text, source_app)) {
+        result.tags.extend(rule.tags.iter().cloned());
+        if result.category.is_none() {
+            result.category = rule.category.clone();
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(content: Option<&str>, source_app: Option<&str>, tags: &[&str]) -> TagRule {
+        TagRule {
+            content: content.map(str::to_string),
+            source_app: source_app.map(str::to_string),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            category: None,
+        }
+    }
+
+    fn text(plain: &str) -> Clip {
+        Clip::Text {
+            plain: plain.to_string(),
+        }
+    }
+
+    #[test]
+    fn every_pattern_of_a_rule_has_to_match() {
+        let rules = compile_rules(vec![rule(
+            Some(r"^\d{3}-\d{4}$"),
+            Some("(?i)slack"),
+            &["phone"],
+        )]);
+
+        assert_eq!(
+            apply_rules(&rules, &text("555-1234"), Some("Slack")).tags,
+            vec!["phone"]
+        );
+        assert!(apply_rules(&rules, &text("555-1234"), Some("Mail"))
+            .tags
+            .is_empty());
+        assert!(apply_rules(&rules, &text("555-1234"), None).tags.is_empty());
+        assert!(apply_rules(&rules, &text("call me"), Some("Slack"))
+            .tags
+            .is_empty());
+    }
+
+    #[test]
+    fn collects_tags_in_order_and_the_first_category() {
+        let mut first = rule(Some("invoice"), None, &["billing"]);
+        first.category = Some("finance".to_string());
+        let mut second = rule(None, Some("Mail"), &["email"]);
+        second.category = Some("work".to_string());
+        let rules = compile_rules(vec![first, second]);
+
+        let result = apply_rules(&rules, &text("invoice #12"), Some("Mail"));
+        assert_eq!(result.tags, vec!["billing", "email"]);
+        assert_eq!(result.category.as_deref(), Some("finance"));
+    }
+
+    #[test]
+    fn matches_the_text_copied_with_an_image() {
+        let rules = compile_rules(vec![rule(Some("diagram"), None, &["design"])]);
+        let image = |text: Option<&str>| Clip::Image {
+            data: String::new(),
+            width: 1,
+            height: 1,
+            text: text.map(str::to_string),
+        };
+
+        assert_eq!(
+            apply_rules(&rules, &image(Some("diagram v2")), None).tags,
+            vec!["design"]
+        );
+        assert!(apply_rules(&rules, &image(None), None).tags.is_empty());
+    }
+
+    #[test]
+    fn skips_rules_with_invalid_patterns() {
+        let rules = compile_rules(vec![
+            rule(Some("("), None, &["broken"]),
+            rule(Some("ok"), None, &["fine"]),
+        ]);
+
+        assert_eq!(rules.len(), 1);
+        assert_eq!(apply_rules(&rules, &text("ok"), None).tags, vec!["fine"]);
+        assert!(rule(Some("("), None, &["broken"]).problem().is_some());
+    }
+}
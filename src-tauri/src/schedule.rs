@@ -0,0 +1,251 @@
+use crate::database;
+use crate::settings::SettingsManagerState;
+use crate::shortcut::{
+    content_hash, process_clip, read_clipboard_once, Clip, ClipMeta, ClipboardFlavor,
+};
+use crate::AppState;
+use rusqlite::{Connection, OptionalExtension};
+use serde::Serialize;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{LazyLock, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, State};
+
+/// Bounds for the `scheduled_capture_interval_minutes` setting
+pub const MIN_INTERVAL_MINUTES: u32 = 1;
+pub const MAX_INTERVAL_MINUTES: u32 = 1440;
+
+/// How often the clipboard is looked at between samples, to tell when its content
+/// was copied
+const WATCH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Hash of the clipboard content the sampler last saw and when it first saw it,
+/// in Unix seconds
+static CLIPBOARD_SEEN: LazyLock<Mutex<Option<(String, i64)>>> = LazyLock::new(|| Mutex::new(None));
+
+/// The thread sampling the clipboard and the channel that stops it
+struct Sampler {
+    stop: Sender<()>,
+    handle: JoinHandle<()>,
+}
+
+impl Sampler {
+    fn start(app_handle: &AppHandle, interval: Duration) -> Self {
+        let (stop, stop_requested) = mpsc::channel();
+        let app_handle = app_handle.clone();
+
+        let handle = thread::spawn(move || {
+            let mut next_sample = Instant::now() + interval;
+            loop {
+                let wait = next_sample
+                    .saturating_duration_since(Instant::now())
+                    .min(WATCH_INTERVAL);
+                match stop_requested.recv_timeout(wait) {
+                    Err(RecvTimeoutError::Timeout) if Instant::now() >= next_sample => {
+                        sample_clipboard(&app_handle);
+                        next_sample = Instant::now() + interval;
+                    }
+                    Err(RecvTimeoutError::Timeout) => {
+                        if let Some(clip) = read_clipboard_once(preferred_flavor(&app_handle)) {
+                            clipboard_seen_at(&clip);
+                        }
+                    }
+                    // a stop request, or the state holding the sender is gone
+                    _ => break,
+                }
+            }
+        });
+
+        Self { stop, handle }
+    }
+
+    /// Stop sampling, a capture that is in progress gets saved first
+    fn stop(self) {
+        self.stop.send(()).ok();
+        if self.handle.join().is_err() {
+            eprintln!("Scheduled capture thread panicked");
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ScheduledCaptureState(Mutex<Option<Sampler>>);
+
+#[derive(Debug, Serialize)]
+pub struct ScheduledCaptureStatus {
+    pub enabled: bool,
+    pub interval_minutes: u32,
+}
+
+/// Hash of the newest clip, whatever captured it. A bumped clip keeps its id and
+/// moves to the top by created_at, so that is what counts.
+fn latest_content_hash(conn: &Connection) -> rusqlite::Result<Option<String>> {
+    conn.query_row(
+        "SELECT content_hash FROM clips ORDER BY created_at DESC, id DESC LIMIT 1",
+        [],
+        |row| row.get(0),
+    )
+    .optional()
+    .map(Option::flatten)
+}
+
+fn preferred_flavor(app_handle: &AppHandle) -> ClipboardFlavor {
+    ClipboardFlavor::from_setting(
+        &app_handle
+            .state::<SettingsManagerState>()
+            .0
+            .get_string("preferred_flavor"),
+    )
+}
+
+/// When `clip` was first seen on the clipboard, now if it was not seen before
+fn clipboard_seen_at(clip: &Clip) -> i64 {
+    let hash = content_hash(clip);
+    let mut seen = CLIPBOARD_SEEN.lock().unwrap();
+    match &*seen {
+        Some((seen_hash, seen_at)) if *seen_hash == hash => *seen_at,
+        _ => {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs() as i64);
+            *seen = Some((hash, now));
+            now
+        }
+    }
+}
+
+/// Save whatever is on the clipboard, unless it is empty or what was saved last
+fn sample_clipboard(app_handle: &AppHandle) {
+    let Some(clip) = read_clipboard_once(preferred_flavor(app_handle)) else {
+        return;
+    };
+    if clip.is_empty() {
+        return;
+    }
+    let copied_at = clipboard_seen_at(&clip);
+
+    // the clipboard usually still holds the previous sample or hotkey capture
+    let db_path = app_handle.state::<AppState>().db_path.clone();
+    match database::open_connection(&db_path).and_then(|conn| latest_content_hash(&conn)) {
+        Ok(Some(hash)) if hash == content_hash(&clip) => return,
+        Ok(_) => {}
+        Err(e) => {
+            eprintln!("Failed to look up the latest clip: {}", e);
+            return;
+        }
+    }
+
+    let meta = ClipMeta {
+        scheduled: true,
+        copied_at: Some(copied_at),
+        ..Default::default()
+    };
+
+    if let Err(e) =
+        tauri::async_runtime::block_on(process_clip(app_handle, &db_path, &clip, None, &meta))
+    {
+        eprintln!("Failed to save scheduled capture: {}", e);
+    }
+}
+
+/// Start, restart or stop sampling to match the scheduled capture settings
+pub fn apply_settings(app_handle: &AppHandle) {
+    let settings = app_handle.state::<SettingsManagerState>().0.clone();
+    let enabled = settings.get_bool("scheduled_capture_enabled");
+    let minutes = settings.get_u32("scheduled_capture_interval_minutes");
+
+    let state = app_handle.state::<ScheduledCaptureState>();
+    let mut sampler = state.0.lock().unwrap();
+
+    if let Some(running) = sampler.take() {
+        running.stop();
+    }
+    if enabled {
+        let interval = Duration::from_secs(u64::from(minutes) * 60);
+        *sampler = Some(Sampler::start(app_handle, interval));
+    }
+
+    crate::tray::set_scheduled_capture_checked(app_handle, enabled);
+}
+
+pub fn init_scheduled_capture(app_handle: &AppHandle) {
+    app_handle.manage(ScheduledCaptureState::default());
+    apply_settings(app_handle);
+}
+
+/// Stop sampling when the app exits, so a capture in progress is not cut off halfway
+pub fn shutdown(app_handle: &AppHandle) {
+    let Some(state) = app_handle.try_state::<ScheduledCaptureState>() else {
+        return;
+    };
+    let running = state.0.lock().unwrap().take();
+    if let Some(running) = running {
+        running.stop();
+    }
+}
+
+/// Turn scheduled capture on or off, optionally with a new interval
+#[tauri::command]
+pub async fn set_scheduled_capture(
+    app_handle: AppHandle,
+    settings_manager: State<'_, SettingsManagerState>,
+    enabled: bool,
+    interval_minutes: Option<u32>,
+) -> Result<(), String> {
+    if let Some(minutes) = interval_minutes {
+        if !(MIN_INTERVAL_MINUTES..=MAX_INTERVAL_MINUTES).contains(&minutes) {
+            return Err(format!(
+                "Interval must be between {} and {} minutes",
+                MIN_INTERVAL_MINUTES, MAX_INTERVAL_MINUTES
+            ));
+        }
+        settings_manager
+            .0
+            .set_setting("scheduled_capture_interval_minutes", &minutes.to_string())
+            .map_err(|e| format!("Failed to save interval: {}", e))?;
+    }
+
+    settings_manager
+        .0
+        .set_setting("scheduled_capture_enabled", &enabled.to_string())
+        .map_err(|e| format!("Failed to save scheduled capture: {}", e))?;
+
+    apply_settings(&app_handle);
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_scheduled_capture(
+    settings_manager: State<'_, SettingsManagerState>,
+) -> Result<ScheduledCaptureStatus, String> {
+    Ok(ScheduledCaptureStatus {
+        enabled: settings_manager.0.get_bool("scheduled_capture_enabled"),
+        interval_minutes: settings_manager
+            .0
+            .get_u32("scheduled_capture_interval_minutes"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_bumped_clip_counts_as_the_latest() {
+        let conn = Connection::open_in_memory().unwrap();
+        database::init_schema(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO clips (id, clip, content_hash, created_at) VALUES
+                (1, '{}', 'bumped', '2024-01-02 00:00:00'),
+                (2, '{}', 'older', '2024-01-01 00:00:00');",
+        )
+        .unwrap();
+
+        assert_eq!(
+            latest_content_hash(&conn).unwrap().as_deref(),
+            Some("bumped")
+        );
+    }
+}
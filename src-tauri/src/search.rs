@@ -0,0 +1,569 @@
+use crate::commands::{clip_item_from_row, ClipItem, CLIP_ITEM_COLUMNS, VISIBLE_CLIPS};
+use crate::database;
+use crate::settings::SettingsManagerState;
+use crate::shortcut::Clip;
+use crate::urls;
+use crate::AppState;
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use tauri::{AppHandle, Listener, Manager, State};
+use url::Url;
+
+/// Events after which the quick search index no longer matches the database
+const MUTATION_EVENTS: &[&str] = &[
+    "clip-saved",
+    "clip-deleted",
+    "clip-bulk-deleted",
+    "clip-updated",
+    "clips-updated",
+    "clips-expired",
+];
+
+const PREVIEW_CHARS: usize = 200;
+const DEFAULT_QUICK_LIMIT: usize = 10;
+const DEFAULT_SEARCH_LIMIT: usize = 100;
+const DEFAULT_HISTORY_LIMIT: usize = 20;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QuickSearchResult {
+    pub id: String,
+    pub content_type: Option<String>,
+    pub category: Option<String>,
+    pub preview: String,
+    pub created_at: String,
+}
+
+struct IndexEntry {
+    result: QuickSearchResult,
+    tags: Vec<String>,
+    // lowercased title, category, summary, tags and preview joined for substring matching
+    haystack: String,
+}
+
+/// Metadata of every visible clip kept in memory so keystroke-by-keystroke
+/// queries never touch SQLite. Rebuilt lazily after any mutation.
+pub struct QuickSearchIndex {
+    db_path: PathBuf,
+    entries: RwLock<Vec<IndexEntry>>,
+    stale: AtomicBool,
+}
+
+impl QuickSearchIndex {
+    pub fn new(db_path: PathBuf) -> Self {
+        Self {
+            db_path,
+            entries: RwLock::new(Vec::new()),
+            stale: AtomicBool::new(true),
+        }
+    }
+
+    pub fn invalidate(&self) {
+        self.stale.store(true, Ordering::SeqCst);
+    }
+
+    fn refresh_if_stale(&self) -> rusqlite::Result<()> {
+        if !self.stale.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        match self.load_entries() {
+            Ok(entries) => {
+                *self.entries.write().unwrap() = entries;
+                Ok(())
+            }
+            Err(e) => {
+                self.stale.store(true, Ordering::SeqCst);
+                Err(e)
+            }
+        }
+    }
+
+    fn load_entries(&self) -> rusqlite::Result<Vec<IndexEntry>> {
+        let conn = database::open_connection(&self.db_path)?;
+
+        let mut stmt = conn.prepare(&format!(
+            r#"
+            SELECT
+              id,
+              created_at,
+              category,
+              summary,
+              tags,
+              content_type,
+              title,
+              CASE WHEN content_type = 'image' THEN NULL ELSE substr(json_extract(clip, '$.content'), 1, ?1) END
+            FROM clips
+            WHERE {VISIBLE_CLIPS}
+            ORDER BY created_at DESC
+            "#
+        ))?;
+
+        let entries = stmt
+            .query_map([PREVIEW_CHARS as i64], |row| {
+                let id: i64 = row.get(0)?;
+                let category: Option<String> = row.get(2)?;
+                let summary: Option<String> = row.get(3)?;
+                let tags_json: Option<String> = row.get(4)?;
+                let title: Option<String> = row.get(6)?;
+                let text_preview: Option<String> = row.get(7)?;
+
+                let tags: Vec<String> = tags_json
+                    .and_then(|tags| serde_json::from_str(&tags).ok())
+                    .unwrap_or_default();
+
+                let preview = text_preview.or_else(|| summary.clone()).unwrap_or_default();
+
+                let haystack = [
+                    title.as_deref().unwrap_or(""),
+                    category.as_deref().unwrap_or(""),
+                    summary.as_deref().unwrap_or(""),
+                    tags.join(" ").as_str(),
+                    preview.as_str(),
+                ]
+                .join("\n")
+                .to_lowercase();
+
+                Ok(IndexEntry {
+                    result: QuickSearchResult {
+                        id: id.to_string(),
+                        content_type: row.get(5)?,
+                        category,
+                        preview,
+                        created_at: row.get(1)?,
+                    },
+                    tags: tags.iter().map(|tag| tag.to_lowercase()).collect(),
+                    haystack,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(entries)
+    }
+
+    pub fn search(&self, query: &str, limit: usize) -> rusqlite::Result<Vec<QuickSearchResult>> {
+        self.refresh_if_stale()?;
+
+        let terms: Vec<String> = query
+            .split_whitespace()
+            .map(|term| term.to_lowercase())
+            .collect();
+
+        let entries = self.entries.read().unwrap();
+
+        if terms.is_empty() {
+            return Ok(entries
+                .iter()
+                .take(limit)
+                .map(|entry| entry.result.clone())
+                .collect());
+        }
+
+        // entries are newest first, so the position doubles as a recency rank
+        let mut scored: Vec<(f64, &IndexEntry)> = entries
+            .iter()
+            .enumerate()
+            .filter_map(|(rank, entry)| {
+                let quality = match_quality(entry, &terms)?;
+                let recency = 1.0 / (1.0 + rank as f64 / 50.0);
+                Some((quality + recency, entry))
+            })
+            .collect();
+
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        Ok(scored
+            .into_iter()
+            .take(limit)
+            .map(|(_, entry)| entry.result.clone())
+            .collect())
+    }
+}
+
+/// Score how well an entry matches all terms, or None if any term is missing
+fn match_quality(entry: &IndexEntry, terms: &[String]) -> Option<f64> {
+    let mut quality = 0.0;
+
+    for term in terms {
+        quality += if entry.tags.iter().any(|tag| tag == term) {
+            3.0
+        } else if entry.tags.iter().any(|tag| tag.starts_with(term.as_str())) {
+            2.0
+        } else if entry
+            .haystack
+            .split(|c: char| !c.is_alphanumeric())
+            .any(|word| word.starts_with(term.as_str()))
+        {
+            1.5
+        } else if entry.haystack.contains(term.as_str()) {
+            1.0
+        } else {
+            return None;
+        };
+    }
+
+    Some(quality)
+}
+
+pub struct QuickSearchState(pub Arc<QuickSearchIndex>);
+
+pub fn init_quick_search(app_handle: AppHandle, db_path: PathBuf) {
+    let index = Arc::new(QuickSearchIndex::new(db_path));
+
+    for event in MUTATION_EVENTS {
+        let index = index.clone();
+        app_handle.listen_any(*event, move |_| index.invalidate());
+    }
+
+    app_handle.manage(QuickSearchState(index));
+}
+
+#[tauri::command]
+pub fn quick_search(
+    quick_search: State<'_, QuickSearchState>,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<QuickSearchResult>, String> {
+    quick_search
+        .0
+        .search(&query, limit.unwrap_or(DEFAULT_QUICK_LIMIT))
+        .map_err(|e| format!("Failed to search clips: {e}"))
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchHistoryEntry {
+    pub id: i64,
+    pub query: String,
+    pub filters: Option<String>,
+    pub result_count: i64,
+    pub searched_at: String,
+}
+
+fn escape_like(term: &str) -> String {
+    term.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Require every term of `query` to appear somewhere in a clip; image content is
+/// base64 so only its metadata counts
+fn push_term_conditions(query: &str, conditions: &mut Vec<String>, values: &mut Vec<Value>) {
+    for term in query.split_whitespace() {
+        values.push(Value::Text(format!("%{}%", escape_like(term))));
+        let n = values.len();
+        conditions.push(format!(
+            "(title LIKE ?{n} ESCAPE '\\' OR category LIKE ?{n} ESCAPE '\\' OR summary LIKE ?{n} ESCAPE '\\' OR tags LIKE ?{n} ESCAPE '\\' \
+             OR (content_type != 'image' AND json_extract(clip, '$.content') LIKE ?{n} ESCAPE '\\'))"
+        ));
+    }
+}
+
+/// Store a search, folding it into the previous entry when the same query is repeated
+fn record_search(
+    conn: &Connection,
+    query: &str,
+    filters: Option<&str>,
+    result_count: usize,
+) -> rusqlite::Result<()> {
+    let latest: Option<(i64, String, Option<String>)> = conn
+        .query_row(
+            "SELECT id, query, filters FROM search_history ORDER BY searched_at DESC, id DESC LIMIT 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()?;
+
+    match latest {
+        Some((id, latest_query, latest_filters))
+            if latest_query == query && latest_filters.as_deref() == filters =>
+        {
+            conn.execute(
+                "UPDATE search_history SET result_count = ?, searched_at = CURRENT_TIMESTAMP WHERE id = ?",
+                params![result_count as i64, id],
+            )?;
+        }
+        _ => {
+            conn.execute(
+                "INSERT INTO search_history (query, filters, result_count) VALUES (?, ?, ?)",
+                params![query, filters, result_count as i64],
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Clips containing every word of `query`, anywhere and in any order, best match
+/// first. Image clips match on their metadata only. Without FTS5 the words are
+/// matched as substrings and the newest clips come first.
+#[tauri::command]
+pub fn search_items(
+    state: State<'_, AppState>,
+    settings_manager: State<'_, SettingsManagerState>,
+    query: String,
+    category: Option<String>,
+    workspace: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<ClipItem>, String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    let mut conditions = vec![VISIBLE_CLIPS.to_string()];
+    let mut values: Vec<Value> = Vec::new();
+
+    // ranked by relevance through the full-text index when there is one
+    let full_text = !query.trim().is_empty()
+        && has_full_text_index(&conn).map_err(|e| format!("Failed to check search index: {e}"))?;
+
+    let (source, order) = if full_text {
+        values.push(Value::Text(fts_query(&query)));
+        (fts_matches(values.len()), "matches.match_rank")
+    } else {
+        push_term_conditions(&query, &mut conditions, &mut values);
+        ("clips".to_string(), "created_at DESC")
+    };
+
+    if let Some(category) = &category {
+        values.push(Value::Text(category.clone()));
+        conditions.push(format!("category = ?{}", values.len()));
+    }
+
+    if let Some(workspace) = &workspace {
+        values.push(Value::Text(workspace.clone()));
+        conditions.push(format!("workspace = ?{}", values.len()));
+    }
+
+    values.push(Value::Integer(limit.unwrap_or(DEFAULT_SEARCH_LIMIT) as i64));
+
+    let sql = format!(
+        "SELECT {CLIP_ITEM_COLUMNS} FROM {source} WHERE {} ORDER BY {order} LIMIT ?{}",
+        conditions.join(" AND "),
+        values.len()
+    );
+
+    let mut stmt = conn
+        .prepare(&sql)
+        .map_err(|e| format!("Failed to prepare statement: {e}"))?;
+
+    let items = stmt
+        .query_map(params_from_iter(values.iter()), clip_item_from_row)
+        .map_err(|e| format!("Failed to execute query: {e}"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to process row: {e}"))?;
+
+    let history_enabled = settings_manager.0.get_bool("search_history_enabled");
+
+    if history_enabled && !query.trim().is_empty() {
+        let filters = (category.is_some() || workspace.is_some()).then(|| {
+            let mut filters = serde_json::Map::new();
+            if let Some(category) = category {
+                filters.insert("category".to_string(), category.into());
+            }
+            if let Some(workspace) = workspace {
+                filters.insert("workspace".to_string(), workspace.into());
+            }
+            serde_json::Value::Object(filters).to_string()
+        });
+        if let Err(e) = record_search(&conn, query.trim(), filters.as_deref(), items.len()) {
+            eprintln!("Failed to record search history: {}", e);
+        }
+    }
+
+    Ok(items)
+}
+
+/// Whether the `clips_fts` index exists, it does not when SQLite lacks FTS5
+fn has_full_text_index(conn: &Connection) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'clips_fts')",
+        [],
+        |row| row.get(0),
+    )
+}
+
+/// Turn user input into an FTS5 query matching every term as a prefix, with
+/// quoting so operators and punctuation in the input are taken literally
+fn fts_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Clips joined with their full-text matches for the FTS5 query bound to ?{n},
+/// exposing `matches.match_rank`. The index shares column names with clips, so
+/// it stays inside the subquery.
+fn fts_matches(n: usize) -> String {
+    format!(
+        "clips JOIN (SELECT rowid AS match_id, rank AS match_rank FROM clips_fts WHERE clips_fts MATCH ?{n}) AS matches \
+         ON matches.match_id = clips.id"
+    )
+}
+
+/// Full-text search over content, title, category, summary and tags, newest first unless
+/// `newest_first` is false, then best match first. An empty query lists every clip,
+/// newest first and only capped by an explicit `limit`; without FTS5 every term is
+/// matched as a substring instead, newest first.
+#[tauri::command]
+pub fn search_clips(
+    state: State<'_, AppState>,
+    query: String,
+    limit: Option<u32>,
+    newest_first: Option<bool>,
+) -> Result<Vec<ClipItem>, String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+    let limit = match limit {
+        Some(limit) => i64::from(limit),
+        // a negative LIMIT has SQLite return every row
+        None if query.trim().is_empty() => -1,
+        None => DEFAULT_SEARCH_LIMIT as i64,
+    };
+
+    let full_text =
+        has_full_text_index(&conn).map_err(|e| format!("Failed to check search index: {e}"))?;
+
+    let mut values: Vec<Value> = Vec::new();
+    let sql = if query.trim().is_empty() || !full_text {
+        let mut conditions = vec![VISIBLE_CLIPS.to_string()];
+        push_term_conditions(&query, &mut conditions, &mut values);
+        format!(
+            "SELECT {CLIP_ITEM_COLUMNS} FROM clips WHERE {} ORDER BY created_at DESC LIMIT ?{}",
+            conditions.join(" AND "),
+            values.len() + 1
+        )
+    } else {
+        values.push(Value::Text(fts_query(&query)));
+        let order = if newest_first.unwrap_or(true) {
+            "clips.created_at DESC"
+        } else {
+            "matches.match_rank"
+        };
+        format!(
+            "SELECT {CLIP_ITEM_COLUMNS} FROM {} WHERE {VISIBLE_CLIPS} ORDER BY {order} LIMIT ?2",
+            fts_matches(1)
+        )
+    };
+    values.push(Value::Integer(limit));
+
+    let mut stmt = conn
+        .prepare(&sql)
+        .map_err(|e| format!("Failed to prepare statement: {e}"))?;
+
+    let items = stmt
+        .query_map(params_from_iter(values.iter()), clip_item_from_row)
+        .map_err(|e| format!("Failed to execute query: {e}"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to process row: {e}"))?;
+
+    Ok(items)
+}
+
+#[tauri::command]
+pub fn get_search_history(
+    state: State<'_, AppState>,
+    limit: Option<usize>,
+) -> Result<Vec<SearchHistoryEntry>, String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, query, filters, result_count, searched_at FROM search_history ORDER BY searched_at DESC, id DESC LIMIT ?",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {e}"))?;
+
+    let entries = stmt
+        .query_map(
+            params![limit.unwrap_or(DEFAULT_HISTORY_LIMIT) as i64],
+            |row| {
+                Ok(SearchHistoryEntry {
+                    id: row.get(0)?,
+                    query: row.get(1)?,
+                    filters: row.get(2)?,
+                    result_count: row.get(3)?,
+                    searched_at: row.get(4)?,
+                })
+            },
+        )
+        .map_err(|e| format!("Failed to execute query: {e}"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to process row: {e}"))?;
+
+    Ok(entries)
+}
+
+#[tauri::command]
+pub fn delete_search_history_entry(state: State<'_, AppState>, id: i64) -> Result<(), String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    let rows_affected = conn
+        .execute("DELETE FROM search_history WHERE id = ?", params![id])
+        .map_err(|e| format!("Failed to delete search history entry: {e}"))?;
+
+    if rows_affected == 0 {
+        return Err("Search history entry not found".to_string());
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn clear_search_history(state: State<'_, AppState>) -> Result<usize, String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    conn.execute("DELETE FROM search_history", [])
+        .map_err(|e| format!("Failed to clear search history: {e}"))
+}
+
+/// Clips whose text is the given URL or mentions it, comparing canonical forms so
+/// http/https, www. and (with `clean_urls` on) tracking parameters do not matter
+#[tauri::command]
+pub fn find_clips_with_url(
+    state: State<'_, AppState>,
+    settings_manager: State<'_, SettingsManagerState>,
+    url: String,
+) -> Result<Vec<ClipItem>, String> {
+    let strip_tracking = settings_manager.0.get_bool("clean_urls");
+    let target = urls::canonical_url(&url, strip_tracking)
+        .ok_or_else(|| format!("'{}' is not an http(s) URL", url.trim()))?;
+    let host = Url::parse(&target)
+        .ok()
+        .and_then(|parsed| parsed.host_str().map(|host| host.to_string()))
+        .ok_or_else(|| format!("'{}' has no host", url.trim()))?;
+
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    // narrow down to clips mentioning the host, then compare each URL in them exactly
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {CLIP_ITEM_COLUMNS} FROM clips WHERE content_type != 'image' AND json_extract(clip, '$.content') LIKE ? ESCAPE '\\' AND {VISIBLE_CLIPS} ORDER BY created_at DESC"
+        ))
+        .map_err(|e| format!("Failed to prepare statement: {e}"))?;
+
+    let candidates = stmt
+        .query_map(
+            params![format!("%{}%", escape_like(&host))],
+            clip_item_from_row,
+        )
+        .map_err(|e| format!("Failed to execute query: {e}"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to process row: {e}"))?;
+
+    Ok(candidates
+        .into_iter()
+        .filter(|item| match &item.clip {
+            Clip::Text { plain } => urls::extract_urls(plain).iter().any(|found| {
+                urls::canonical_url(found, strip_tracking).as_deref() == Some(target.as_str())
+            }),
+            Clip::Image { .. } => false,
+        })
+        .collect())
+}
@@ -1,11 +1,16 @@
-use rusqlite::{params, Connection};
+use crate::commands::CategoryInfo;
+use crate::database;
+use crate::llm::ModelPrice;
+use crate::shortcut::{HotkeyAction, HotkeyBinding};
+use crate::AppState;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
     path::PathBuf,
     sync::{Arc, Mutex},
 };
-use tauri::{AppHandle, Manager, State};
-use tauri_plugin_global_shortcut::GlobalShortcutExt;
+use tauri::{AppHandle, Emitter, Manager, State};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -18,8 +23,364 @@ pub enum SettingsError {
 
 type Result<T, E = SettingsError> = std::result::Result<T, E>;
 
+/// What values a setting accepts
+#[derive(Clone, Copy)]
+pub enum SettingKind {
+    Bool,
+    Number {
+        min: u32,
+        max: u32,
+    },
+    Choice(&'static [&'static str]),
+    /// free-form text checked by a validator returning the problem, if any
+    Text(fn(&str) -> Option<String>),
+}
+
+pub struct SettingSchema {
+    pub key: &'static str,
+    pub kind: SettingKind,
+    /// written to the table on first start, None for settings that stay unset until the user sets them
+    pub default: Option<&'static str>,
+}
+
+/// Every setting the backend reads
+pub const SETTINGS_SCHEMA: &[SettingSchema] = &[
+    SettingSchema {
+        key: "global_hotkey",
+        kind: SettingKind::Text(check_hotkey),
+        default: Some("CommandOrControl+Shift+S"),
+    },
+    SettingSchema {
+        key: "hotkey_actions",
+        kind: SettingKind::Text(check_hotkey_actions),
+        default: Some("[]"),
+    },
+    SettingSchema {
+        key: "reregister_on_wake",
+        kind: SettingKind::Bool,
+        default: Some("true"),
+    },
+    SettingSchema {
+        key: "scheduled_capture_enabled",
+        kind: SettingKind::Bool,
+        default: Some("false"),
+    },
+    SettingSchema {
+        key: "scheduled_capture_interval_minutes",
+        kind: SettingKind::Number {
+            min: crate::schedule::MIN_INTERVAL_MINUTES,
+            max: crate::schedule::MAX_INTERVAL_MINUTES,
+        },
+        default: Some("15"),
+    },
+    // unset by default, capture falls back to timings suited to the platform
+    SettingSchema {
+        key: "copy_delay_ms",
+        kind: SettingKind::Number { min: 0, max: 2000 },
+        default: None,
+    },
+    SettingSchema {
+        key: "clipboard_read_attempts",
+        kind: SettingKind::Number { min: 1, max: 20 },
+        default: None,
+    },
+    SettingSchema {
+        key: "clipboard_retry_delay_ms",
+        kind: SettingKind::Number { min: 0, max: 1000 },
+        default: None,
+    },
+    // wait for focus to go back to the previous app before pasting into it
+    SettingSchema {
+        key: "paste_delay_ms",
+        kind: SettingKind::Number { min: 0, max: 2000 },
+        default: Some("150"),
+    },
+    SettingSchema {
+        key: "capture_debounce_ms",
+        kind: SettingKind::Number {
+            min: 0,
+            max: 60_000,
+        },
+        default: Some("500"),
+    },
+    SettingSchema {
+        key: "app_cooldowns",
+        kind: SettingKind::Text(check_app_cooldowns),
+        default: Some("{}"),
+    },
+    SettingSchema {
+        key: "llm_api_key",
+        kind: SettingKind::Text(check_api_key),
+        default: None,
+    },
+    SettingSchema {
+        key: "llm_prices",
+        kind: SettingKind::Text(check_prices),
+        default: None,
+    },
+    SettingSchema {
+        key: "fallback_model",
+        kind: SettingKind::Text(check_model_name),
+        default: None,
+    },
+    // read on every request, a change applies to the next capture
+    SettingSchema {
+        key: "llm_provider",
+        kind: SettingKind::Choice(&["openai", "ollama", "anthropic"]),
+        default: Some("openai"),
+    },
+    SettingSchema {
+        key: "llm_api_key_anthropic",
+        kind: SettingKind::Text(check_api_key),
+        default: None,
+    },
+    // model of the openai provider
+    SettingSchema {
+        key: "openai_model",
+        kind: SettingKind::Text(check_model_name),
+        default: Some(crate::llm::DEFAULT_MODEL),
+    },
+    // model of the anthropic provider
+    SettingSchema {
+        key: "llm_model",
+        kind: SettingKind::Text(check_model_name),
+        default: Some("claude-3-5-haiku-latest"),
+    },
+    SettingSchema {
+        key: "ollama_url",
+        kind: SettingKind::Text(check_http_url),
+        default: Some("http://localhost:11434"),
+    },
+    SettingSchema {
+        key: "ollama_model",
+        kind: SettingKind::Text(check_model_name),
+        default: Some("llama3.2"),
+    },
+    SettingSchema {
+        key: "send_images_to_llm",
+        kind: SettingKind::Bool,
+        default: Some("true"),
+    },
+    SettingSchema {
+        key: "search_history_enabled",
+        kind: SettingKind::Bool,
+        default: Some("true"),
+    },
+    SettingSchema {
+        key: "preferred_flavor",
+        kind: SettingKind::Choice(&["text", "image", "rich"]),
+        default: Some("text"),
+    },
+    SettingSchema {
+        key: "permission_error_action",
+        kind: SettingKind::Choice(&["notify", "open_settings", "ignore"]),
+        default: Some("notify"),
+    },
+    SettingSchema {
+        key: "dedupe_urls",
+        kind: SettingKind::Choice(&["off", "bump"]),
+        default: Some("bump"),
+    },
+    SettingSchema {
+        key: "summarize_on_capture",
+        kind: SettingKind::Bool,
+        default: Some("true"),
+    },
+    // hotkey captures open the toolbar to confirm the category before saving
+    SettingSchema {
+        key: "review_captures",
+        kind: SettingKind::Bool,
+        default: Some("false"),
+    },
+    SettingSchema {
+        key: "fragment_detection",
+        kind: SettingKind::Choice(&["off", "suggest", "merge"]),
+        default: Some("off"),
+    },
+    SettingSchema {
+        key: "fragment_window_seconds",
+        kind: SettingKind::Number {
+            min: crate::fragments::MIN_WINDOW_SECONDS,
+            max: crate::fragments::MAX_WINDOW_SECONDS,
+        },
+        default: Some("60"),
+    },
+    SettingSchema {
+        key: "clean_urls",
+        kind: SettingKind::Bool,
+        default: Some("true"),
+    },
+    SettingSchema {
+        key: "url_fetch_allowlist",
+        kind: SettingKind::Text(check_domain_list),
+        default: Some("[]"),
+    },
+    SettingSchema {
+        key: "url_fetch_blocklist",
+        kind: SettingKind::Text(check_domain_list),
+        default: Some("[]"),
+    },
+    SettingSchema {
+        key: "url_fetch_block_private",
+        kind: SettingKind::Bool,
+        default: Some("true"),
+    },
+    // clips are deleted once older than this or past this many, 0 keeps them all
+    SettingSchema {
+        key: "max_clip_age_days",
+        kind: SettingKind::Number {
+            min: 0,
+            max: crate::database::MAX_CLIP_AGE_DAYS,
+        },
+        default: Some("0"),
+    },
+    SettingSchema {
+        key: "max_clip_count",
+        kind: SettingKind::Number {
+            min: 0,
+            max: crate::database::MAX_CLIP_COUNT,
+        },
+        default: Some("0"),
+    },
+    SettingSchema {
+        key: "url_summary_refresh_days",
+        kind: SettingKind::Number { min: 0, max: 3650 },
+        default: Some("30"),
+    },
+    SettingSchema {
+        key: "clip_version_retention",
+        kind: SettingKind::Number { min: 0, max: 100 },
+        default: Some("5"),
+    },
+    SettingSchema {
+        key: "tag_rules",
+        kind: SettingKind::Text(check_tag_rules),
+        default: Some("[]"),
+    },
+    SettingSchema {
+        key: "tag_synonyms",
+        kind: SettingKind::Text(check_tag_synonyms),
+        default: Some("{}"),
+    },
+    // read once at startup, a change takes effect after a restart
+    SettingSchema {
+        key: "max_db_connections",
+        kind: SettingKind::Number {
+            min: crate::database::MIN_DB_CONNECTIONS,
+            max: crate::database::MAX_DB_CONNECTIONS,
+        },
+        default: Some("4"),
+    },
+    SettingSchema {
+        key: "thumbnail_max_dimension",
+        kind: SettingKind::Number {
+            min: crate::thumbnails::MIN_THUMBNAIL_SIZE,
+            max: crate::thumbnails::MAX_THUMBNAIL_SIZE,
+        },
+        default: Some("256"),
+    },
+    // characters of a clip sent to the LLM, longer text is cut off
+    SettingSchema {
+        key: "llm_max_input_chars",
+        kind: SettingKind::Number {
+            min: crate::llm::MIN_INPUT_CHARS_LIMIT,
+            max: crate::llm::MAX_INPUT_CHARS_LIMIT,
+        },
+        default: Some("4000"),
+    },
+    // "cursor" opens the review toolbar on the monitor under the cursor, a monitor
+    // name pins it to that monitor while it is connected
+    SettingSchema {
+        key: "toolbar_monitor",
+        kind: SettingKind::Text(check_monitor_name),
+        default: Some(crate::toolbar::FOLLOW_CURSOR),
+    },
+    // hours a cached LLM answer for the same content is reused, 0 always asks the LLM
+    SettingSchema {
+        key: "llm_cache_ttl_hours",
+        kind: SettingKind::Number {
+            min: 0,
+            max: crate::llm::cache::MAX_TTL_HOURS,
+        },
+        default: Some("24"),
+    },
+    SettingSchema {
+        key: "llm_retry_attempts",
+        kind: SettingKind::Number {
+            min: 1,
+            max: crate::llm::MAX_RETRY_ATTEMPTS,
+        },
+        default: Some("3"),
+    },
+    SettingSchema {
+        key: "llm_context_clips",
+        kind: SettingKind::Number { min: 0, max: 20 },
+        default: Some("0"),
+    },
+    SettingSchema {
+        key: "llm_context_window_minutes",
+        kind: SettingKind::Number { min: 1, max: 1440 },
+        default: Some("10"),
+    },
+    // text clips longer than this many lines are cut down on capture, 0 keeps them whole
+    SettingSchema {
+        key: "capture_line_limit",
+        kind: SettingKind::Number {
+            min: 0,
+            max: crate::transforms::MAX_LINE_LIMIT,
+        },
+        default: Some("0"),
+    },
+    SettingSchema {
+        key: "capture_line_keep",
+        kind: SettingKind::Choice(&["head", "tail", "both"]),
+        default: Some("head"),
+    },
+    SettingSchema {
+        key: "capture_transforms",
+        kind: SettingKind::Text(check_transforms),
+        default: Some("[]"),
+    },
+    SettingSchema {
+        key: "clip_json_format",
+        kind: SettingKind::Choice(&["compact", "pretty"]),
+        default: Some("compact"),
+    },
+    SettingSchema {
+        key: "active_workspace",
+        kind: SettingKind::Text(crate::workspaces::check_workspace_name),
+        default: Some(""),
+    },
+];
+
+pub fn setting_schema(key: &str) -> Option<&'static SettingSchema> {
+    SETTINGS_SCHEMA.iter().find(|schema| schema.key == key)
+}
+
+impl SettingKind {
+    /// What is wrong with `value` for a setting of this kind, or None when it is fine
+    pub fn validate(&self, value: &str) -> Option<String> {
+        match self {
+            SettingKind::Bool => check_one_of(value, &["true", "false"]),
+            SettingKind::Number { min, max } => check_range(value, *min, *max),
+            SettingKind::Choice(allowed) => check_one_of(value, allowed),
+            SettingKind::Text(check) => check(value),
+        }
+    }
+}
+
+/// A setting value parsed according to its schema
+#[derive(Debug, Clone)]
+enum TypedValue {
+    Bool(bool),
+    Number(u32),
+    Text(String),
+}
+
 pub struct SettingsManager {
     settings: Mutex<HashMap<String, String>>,
+    /// parsed values, dropped whenever the raw value changes
+    typed: Mutex<HashMap<String, TypedValue>>,
     db_path: PathBuf,
 }
 
@@ -27,12 +388,13 @@ impl SettingsManager {
     pub fn new(db_path: PathBuf) -> Self {
         Self {
             settings: Mutex::new(HashMap::new()),
+            typed: Mutex::new(HashMap::new()),
             db_path,
         }
     }
 
-    fn get_connection(&self) -> Result<Connection> {
-        Connection::open(&self.db_path).map_err(SettingsError::Database)
+    fn get_connection(&self) -> Result<database::DbConnection> {
+        database::open_connection(&self.db_path).map_err(SettingsError::Database)
     }
 
     pub fn initialize(&self) -> Result<()> {
@@ -58,7 +420,9 @@ impl SettingsManager {
                 }
             }
         }
-        let defaults = vec![("global_hotkey", "CommandOrControl+Shift+S")];
+        let defaults = SETTINGS_SCHEMA
+            .iter()
+            .filter_map(|schema| schema.default.map(|default| (schema.key, default)));
 
         for (key, default_value) in defaults {
             if !settings.contains_key(key) {
@@ -73,6 +437,7 @@ impl SettingsManager {
         }
         // release the lock
         drop(settings);
+        self.typed.lock().unwrap().clear();
 
         Ok(())
     }
@@ -92,6 +457,18 @@ impl SettingsManager {
 
         let mut settings = self.settings.lock().unwrap();
         settings.insert(key.to_string(), value.to_string());
+        self.typed.lock().unwrap().remove(key);
+
+        Ok(())
+    }
+
+    pub fn delete_setting(&self, key: &str) -> Result<()> {
+        let conn = self.get_connection()?;
+
+        conn.execute("DELETE FROM settings WHERE key = ?", params![key])?;
+
+        self.settings.lock().unwrap().remove(key);
+        self.typed.lock().unwrap().remove(key);
 
         Ok(())
     }
@@ -102,8 +479,68 @@ impl SettingsManager {
     }
 
     pub fn get_global_hotkey(&self) -> String {
-        self.get_setting("global_hotkey")
-            .unwrap_or_else(|| "CommandOrControl+Shift+C".to_string())
+        self.get_string("global_hotkey")
+    }
+
+    /// Parse a setting according to its schema. Missing or invalid values fall back
+    /// to the schema default, keys without a schema have no typed value.
+    fn typed(&self, key: &str) -> Option<TypedValue> {
+        if let Some(value) = self.typed.lock().unwrap().get(key) {
+            return Some(value.clone());
+        }
+
+        let schema = setting_schema(key)?;
+        let value = match self.get_setting(key) {
+            Some(value) => match schema.kind.validate(&value) {
+                None => value,
+                Some(problem) => {
+                    eprintln!("Invalid setting {}: {}, using the default", key, problem);
+                    schema.default?.to_string()
+                }
+            },
+            None => schema.default?.to_string(),
+        };
+
+        let typed = match schema.kind {
+            SettingKind::Bool => TypedValue::Bool(value == "true"),
+            SettingKind::Number { .. } => TypedValue::Number(value.trim().parse().ok()?),
+            SettingKind::Choice(_) | SettingKind::Text(_) => TypedValue::Text(value),
+        };
+
+        self.typed
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), typed.clone());
+
+        Some(typed)
+    }
+
+    pub fn get_bool(&self, key: &str) -> bool {
+        matches!(self.typed(key), Some(TypedValue::Bool(true)))
+    }
+
+    pub fn get_u32(&self, key: &str) -> u32 {
+        match self.typed(key) {
+            Some(TypedValue::Number(number)) => number,
+            _ => 0,
+        }
+    }
+
+    /// Like `get_u32`, with a fallback for settings whose default depends on the platform
+    pub fn get_u32_or(&self, key: &str, fallback: u32) -> u32 {
+        match self.typed(key) {
+            Some(TypedValue::Number(number)) => number,
+            _ => fallback,
+        }
+    }
+
+    pub fn get_string(&self, key: &str) -> String {
+        match self.typed(key) {
+            Some(TypedValue::Text(text)) => text,
+            Some(TypedValue::Bool(value)) => value.to_string(),
+            Some(TypedValue::Number(number)) => number.to_string(),
+            None => String::new(),
+        }
     }
 }
 
@@ -122,14 +559,52 @@ pub fn init_settings(
     Ok(())
 }
 
+/// Keys holding credentials. The general settings commands only ever return them
+/// redacted, get_secret_setting has the real value.
+const SECRET_SETTINGS: &[&str] = &["llm_api_key", "llm_api_key_anthropic"];
+
+/// Stands in for a secret that is set, an empty one stays empty
+pub const REDACTED: &str = "********";
+
+fn is_secret(key: &str) -> bool {
+    SECRET_SETTINGS.contains(&key)
+}
+
+fn redacted(key: &str, value: String) -> String {
+    if is_secret(key) && !value.is_empty() {
+        REDACTED.to_string()
+    } else {
+        value
+    }
+}
+
 #[tauri::command]
 pub async fn get_setting(
     key: String,
     settings_manager: State<'_, SettingsManagerState>,
 ) -> Result<Option<String>, String> {
-    let key = settings_manager.0.get_setting(&key);
-    println!("the value {:?}", key);
-    Ok(key)
+    Ok(settings_manager
+        .0
+        .get_setting(&key)
+        .map(|value| redacted(&key, value)))
+}
+
+/// The real value of a secret setting. Only the main window may read it, so a
+/// secret never reaches the toolbar or other windows.
+#[tauri::command]
+pub async fn get_secret_setting(
+    key: String,
+    window: tauri::WebviewWindow,
+    settings_manager: State<'_, SettingsManagerState>,
+) -> Result<Option<String>, String> {
+    if !is_secret(&key) {
+        return Err(format!("{} is not a secret setting", key));
+    }
+    if window.label() != "main" {
+        return Err("Secret settings can only be read from the main window".to_string());
+    }
+
+    Ok(settings_manager.0.get_setting(&key))
 }
 
 #[tauri::command]
@@ -138,6 +613,11 @@ pub async fn set_setting(
     value: String,
     settings_manager: State<'_, SettingsManagerState>,
 ) -> Result<(), String> {
+    // a redacted secret sent back unchanged keeps the stored one
+    if is_secret(&key) && value == REDACTED {
+        return Ok(());
+    }
+
     settings_manager
         .0
         .set_setting(&key, &value)
@@ -148,7 +628,15 @@ pub async fn set_setting(
 pub async fn get_all_settings(
     settings_manager: State<'_, SettingsManagerState>,
 ) -> Result<HashMap<String, String>, String> {
-    Ok(settings_manager.0.get_all_settings())
+    Ok(settings_manager
+        .0
+        .get_all_settings()
+        .into_iter()
+        .map(|(key, value)| {
+            let value = redacted(&key, value);
+            (key, value)
+        })
+        .collect())
 }
 
 #[tauri::command]
@@ -157,47 +645,530 @@ pub async fn set_global_hotkey(
     settings_manager: State<'_, SettingsManagerState>,
     app: AppHandle,
 ) -> Result<(), String> {
-    if let Err(e) = crate::shortcut::parse_hotkey_string(&hotkey) {
-        return Err(format!("Invalid hotkey format: {}", e));
-    }
+    let shortcut = crate::shortcut::parse_hotkey_string(&hotkey)
+        .map_err(|e| format!("Invalid hotkey format: {}", e))?;
+    // checked before saving, so a taken shortcut leaves the current one in place
+    crate::shortcut::validate_hotkey_availability(&shortcut, &app)?;
 
     settings_manager
         .0
         .set_setting("global_hotkey", &hotkey)
         .map_err(|e| format!("Failed to save hotkey: {}", e))?;
 
-    update_global_shortcut(app, &hotkey)
+    // re-registers the extra hotkey actions as well
+    crate::shortcut::register_hotkeys(&app)
         .map_err(|e| format!("Failed to register hotkey: {}", e))?;
 
     Ok(())
 }
 
+/// Register the configured hotkeys again, for when another app took one over or
+/// the registration was lost
+#[tauri::command]
+pub async fn reregister_shortcut(app: AppHandle) -> Result<(), String> {
+    crate::shortcut::register_hotkeys(&app).map_err(|e| format!("Failed to register hotkey: {}", e))
+}
+
 #[tauri::command]
 pub async fn get_global_hotkey(
     settings_manager: State<'_, SettingsManagerState>,
 ) -> Result<String, String> {
-    Ok(settings_manager
-        .0
-        .get_setting("global_hotkey")
-        .unwrap_or_else(|| "CommandOrControl+Shift+S".to_string()))
+    Ok(settings_manager.0.get_global_hotkey())
 }
 
+/// Whether `hotkey` parses and is free to register
 #[tauri::command]
-pub async fn test_global_hotkey(hotkey: String) -> Result<(), String> {
-    crate::shortcut::parse_hotkey_string(&hotkey).map_err(|e| format!("Invalid hotkey: {}", e))?;
-    Ok(())
+pub async fn test_global_hotkey(hotkey: String, app: AppHandle) -> Result<(), String> {
+    let shortcut = crate::shortcut::parse_hotkey_string(&hotkey)
+        .map_err(|e| format!("Invalid hotkey: {}", e))?;
+    crate::shortcut::validate_hotkey_availability(&shortcut, &app)
+}
+
+#[tauri::command]
+pub async fn get_hotkey_actions(
+    settings_manager: State<'_, SettingsManagerState>,
+) -> Result<Vec<HotkeyBinding>, String> {
+    Ok(crate::shortcut::hotkey_bindings(&settings_manager.0))
 }
 
-fn update_global_shortcut(
+#[tauri::command]
+pub async fn set_hotkey_actions(
+    actions: Vec<HotkeyBinding>,
+    settings_manager: State<'_, SettingsManagerState>,
     app: AppHandle,
-    hotkey_str: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let global_shortcut = app.global_shortcut();
+) -> Result<(), String> {
+    for binding in &actions {
+        let shortcut = crate::shortcut::parse_hotkey_string(&binding.hotkey)
+            .map_err(|e| format!("Invalid hotkey format '{}': {}", binding.hotkey, e))?;
+        crate::shortcut::validate_hotkey_availability(&shortcut, &app)
+            .map_err(|e| format!("Hotkey '{}': {}", binding.hotkey, e))?;
+        match &binding.action {
+            HotkeyAction::CaptureToCategory { category } if category.trim().is_empty() => {
+                return Err(format!("Hotkey '{}' needs a category", binding.hotkey));
+            }
+            HotkeyAction::CaptureWithExpiry { minutes: 0 } => {
+                return Err(format!(
+                    "Hotkey '{}' needs an expiry of at least one minute",
+                    binding.hotkey
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    let value = serde_json::to_string(&actions)
+        .map_err(|e| format!("Failed to serialize hotkey actions: {}", e))?;
 
-    global_shortcut.unregister_all()?;
+    settings_manager
+        .0
+        .set_setting("hotkey_actions", &value)
+        .map_err(|e| format!("Failed to save hotkey actions: {}", e))?;
 
-    let shortcut = crate::shortcut::parse_hotkey_string(hotkey_str)?;
-    global_shortcut.register(shortcut)?;
+    crate::shortcut::register_hotkeys(&app)
+        .map_err(|e| format!("Failed to register hotkeys: {}", e))?;
 
     Ok(())
 }
+
+#[derive(Debug, Serialize)]
+pub struct SettingProblem {
+    pub key: String,
+    pub value: String,
+    pub problem: String,
+    /// the value may still work, like a model this version does not know
+    pub warning: bool,
+}
+
+fn check_one_of(value: &str, allowed: &[&str]) -> Option<String> {
+    (!allowed.contains(&value)).then(|| format!("Expected one of: {}", allowed.join(", ")))
+}
+
+fn check_range(value: &str, min: u32, max: u32) -> Option<String> {
+    match value.trim().parse::<u32>() {
+        Ok(number) if (min..=max).contains(&number) => None,
+        Ok(_) => Some(format!("Expected a number between {} and {}", min, max)),
+        Err(_) => Some("Expected a whole number".to_string()),
+    }
+}
+
+fn check_json<T: serde::de::DeserializeOwned>(value: &str, expected: &str) -> Option<String> {
+    serde_json::from_str::<T>(value)
+        .err()
+        .map(|e| format!("Expected {}: {}", expected, e))
+}
+
+fn check_hotkey(value: &str) -> Option<String> {
+    crate::shortcut::parse_hotkey_string(value)
+        .err()
+        .map(|e| format!("Invalid hotkey: {}", e))
+}
+
+fn check_hotkey_actions(value: &str) -> Option<String> {
+    match serde_json::from_str::<Vec<HotkeyBinding>>(value) {
+        Ok(bindings) => bindings.iter().find_map(|binding| {
+            crate::shortcut::parse_hotkey_string(&binding.hotkey)
+                .err()
+                .map(|e| format!("Invalid hotkey '{}': {}", binding.hotkey, e))
+        }),
+        Err(e) => Some(format!("Expected a list of hotkey actions: {}", e)),
+    }
+}
+
+/// An empty key is fine here, only the selected provider needs one, see
+/// provider_problems
+fn check_api_key(value: &str) -> Option<String> {
+    value
+        .trim()
+        .contains(char::is_whitespace)
+        .then(|| "API keys cannot contain spaces".to_string())
+}
+
+fn check_prices(value: &str) -> Option<String> {
+    if value.trim().is_empty() {
+        return None;
+    }
+    check_json::<HashMap<String, ModelPrice>>(value, "a JSON object of model prices")
+}
+
+fn check_app_cooldowns(value: &str) -> Option<String> {
+    check_json::<HashMap<String, u64>>(value, "a JSON object of app names to milliseconds")
+}
+
+fn check_transforms(value: &str) -> Option<String> {
+    check_json::<Vec<crate::transforms::Transform>>(
+        value,
+        "a JSON array of trim, normalize-newlines, strip-ansi, lowercase-urls or tabs-to-spaces",
+    )
+}
+
+fn check_http_url(value: &str) -> Option<String> {
+    match url::Url::parse(value.trim()) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => None,
+        _ => Some("Expected an http or https URL".to_string()),
+    }
+}
+
+fn check_model_name(value: &str) -> Option<String> {
+    value
+        .trim()
+        .contains(char::is_whitespace)
+        .then(|| "Model names cannot contain spaces".to_string())
+}
+
+fn check_monitor_name(value: &str) -> Option<String> {
+    value
+        .trim()
+        .is_empty()
+        .then(|| "Expected \"cursor\" or a monitor name".to_string())
+}
+
+fn check_domain_list(value: &str) -> Option<String> {
+    check_json::<Vec<String>>(value, "a JSON array of domains")
+}
+
+fn check_tag_rules(value: &str) -> Option<String> {
+    match serde_json::from_str::<Vec<crate::rules::TagRule>>(value) {
+        Ok(rules) => rules.iter().find_map(|rule| rule.problem()),
+        Err(e) => Some(format!("Expected a JSON array of rules: {}", e)),
+    }
+}
+
+fn check_tag_synonyms(value: &str) -> Option<String> {
+    check_json::<HashMap<String, String>>(value, "a JSON object of tags")
+}
+
+/// What is wrong with a setting value, or None when it is fine. Unknown keys are
+/// left alone since the frontend stores its own settings in the same table.
+fn validate_setting(key: &str, value: &str) -> Option<String> {
+    setting_schema(key).and_then(|schema| schema.kind.validate(value))
+}
+
+/// The key and model settings the selected `llm_provider` reads. Ollama needs no
+/// key and runs any model, so it has nothing to check.
+fn provider_problems(settings: &HashMap<String, String>) -> Vec<SettingProblem> {
+    let provider = settings
+        .get("llm_provider")
+        .map_or("openai", String::as_str);
+    let value = |key: &str| settings.get(key).cloned().unwrap_or_default();
+    let mut problems = Vec::new();
+
+    let (key_setting, model_settings): (Option<&str>, &[&str]) = match provider {
+        "openai" if std::env::var("OPENAI_API_KEY").is_ok_and(|key| !key.trim().is_empty()) => {
+            (None, &["openai_model", "fallback_model"])
+        }
+        "openai" => (Some("llm_api_key"), &["openai_model", "fallback_model"]),
+        "anthropic" => (Some("llm_api_key_anthropic"), &["llm_model"]),
+        _ => (None, &[]),
+    };
+
+    if let Some(key) = key_setting.filter(|key| value(key).trim().is_empty()) {
+        problems.push(SettingProblem {
+            key: key.to_string(),
+            value: String::new(),
+            problem: format!("API key is empty, {} requests will fail", provider),
+            warning: false,
+        });
+    }
+
+    let known = crate::providers::known_models(provider).unwrap_or_default();
+    for key in model_settings {
+        let model = value(key);
+        let model = model.trim();
+        if !model.is_empty() && !known.contains(&model) {
+            problems.push(SettingProblem {
+                key: key.to_string(),
+                value: model.to_string(),
+                problem: format!(
+                    "Not a known {} model, expected one of: {}",
+                    provider,
+                    known.join(", ")
+                ),
+                warning: true,
+            });
+        }
+    }
+
+    problems
+}
+
+/// Check every stored setting against what the backend expects of it, and the
+/// settings of the selected LLM provider against each other
+#[tauri::command]
+pub async fn validate_settings(
+    settings_manager: State<'_, SettingsManagerState>,
+) -> Result<Vec<SettingProblem>, String> {
+    let settings = settings_manager.0.get_all_settings();
+    let mut problems = provider_problems(&settings);
+    problems.extend(settings.into_iter().filter_map(|(key, value)| {
+        validate_setting(&key, &value).map(|problem| SettingProblem {
+            value: redacted(&key, value),
+            key,
+            problem,
+            warning: false,
+        })
+    }));
+
+    problems.sort_by(|a, b| a.key.cmp(&b.key));
+
+    Ok(problems)
+}
+
+/// Version of the export_settings format, bumped when it changes incompatibly
+const SETTINGS_EXPORT_VERSION: u32 = 1;
+
+/// Everything that makes up a spiegel configuration, for moving it between machines
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SettingsSnapshot {
+    pub version: u32,
+    pub settings: HashMap<String, String>,
+    #[serde(default)]
+    pub categories: Vec<CategoryInfo>,
+}
+
+#[tauri::command]
+pub async fn export_settings(
+    settings_manager: State<'_, SettingsManagerState>,
+    state: State<'_, AppState>,
+    include_secrets: Option<bool>,
+) -> Result<SettingsSnapshot, String> {
+    let include_secrets = include_secrets.unwrap_or(false);
+
+    let settings = settings_manager
+        .0
+        .get_all_settings()
+        .into_iter()
+        .filter(|(key, _)| include_secrets || !is_secret(key))
+        // resume cursors point at clip ids of this database only
+        .filter(|(key, _)| !key.starts_with(crate::maintenance::CURSOR_SETTING_PREFIX))
+        .collect();
+
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+    let mut stmt = conn
+        .prepare("SELECT name, color, icon FROM categories ORDER BY name")
+        .map_err(|e| format!("Failed to prepare statement: {e}"))?;
+    let categories = stmt
+        .query_map([], |row| {
+            Ok(CategoryInfo {
+                category: row.get(0)?,
+                color: row.get(1)?,
+                icon: row.get(2)?,
+            })
+        })
+        .map_err(|e| format!("Failed to execute query: {e}"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to process row: {e}"))?;
+
+    Ok(SettingsSnapshot {
+        version: SETTINGS_EXPORT_VERSION,
+        settings,
+        categories,
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportReport {
+    pub applied: usize,
+    /// keys this version has no schema for, like the frontend's own or ones from a
+    /// newer version; they are left out
+    pub skipped: Vec<String>,
+}
+
+/// What is wrong with the settings and categories of a snapshot, and the keys to
+/// skip. An empty secret is kept as it is, export_settings writes unset keys that way.
+fn check_snapshot(snapshot: &SettingsSnapshot) -> (Vec<String>, Vec<String>) {
+    let mut problems = Vec::new();
+    let mut skipped = Vec::new();
+
+    for (key, value) in &snapshot.settings {
+        match setting_schema(key) {
+            None => skipped.push(key.clone()),
+            Some(_) if is_secret(key) && value.is_empty() => {}
+            Some(schema) => {
+                if let Some(problem) = schema.kind.validate(value) {
+                    problems.push(format!("{}: {}", key, problem));
+                }
+            }
+        }
+    }
+    problems.extend(
+        snapshot
+            .categories
+            .iter()
+            .filter_map(|category| match &category.color {
+                Some(color) if !crate::commands::is_hex_color(color) => Some(format!(
+                    "category {}: invalid color '{}'",
+                    category.category, color
+                )),
+                _ => None,
+            }),
+    );
+
+    problems.sort();
+    skipped.sort();
+    (problems, skipped)
+}
+
+/// Apply a snapshot from export_settings. Nothing is written unless every known
+/// setting and category in it is valid, unknown settings are skipped.
+#[tauri::command]
+pub async fn import_settings(
+    settings_manager: State<'_, SettingsManagerState>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+    snapshot: String,
+) -> Result<ImportReport, String> {
+    let snapshot: SettingsSnapshot =
+        serde_json::from_str(&snapshot).map_err(|e| format!("Invalid settings snapshot: {e}"))?;
+
+    if snapshot.version > SETTINGS_EXPORT_VERSION {
+        return Err(format!(
+            "Settings snapshot version {} is newer than this version of spiegel supports",
+            snapshot.version
+        ));
+    }
+
+    let (problems, skipped) = check_snapshot(&snapshot);
+    if !problems.is_empty() {
+        return Err(format!("Invalid settings: {}", problems.join("; ")));
+    }
+
+    let mut conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {e}"))?;
+
+    let applied: Vec<_> = snapshot
+        .settings
+        .iter()
+        .filter(|(key, _)| !skipped.contains(*key))
+        .collect();
+    for (key, value) in &applied {
+        tx.execute(
+            "INSERT OR REPLACE INTO settings (key, value, updated_at) VALUES (?, ?, CURRENT_TIMESTAMP)",
+            params![key, value],
+        )
+        .map_err(|e| format!("Failed to save setting {}: {}", key, e))?;
+    }
+    for category in &snapshot.categories {
+        tx.execute(
+            "INSERT INTO categories (name, color, icon) VALUES (?1, ?2, ?3)
+             ON CONFLICT(name) DO UPDATE SET color = excluded.color, icon = excluded.icon",
+            params![category.category, category.color, category.icon],
+        )
+        .map_err(|e| format!("Failed to save category {}: {}", category.category, e))?;
+    }
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {e}"))?;
+
+    settings_manager
+        .0
+        .initialize()
+        .map_err(|e| format!("Failed to reload settings: {}", e))?;
+
+    crate::shortcut::register_hotkeys(&app)
+        .map_err(|e| format!("Failed to register hotkeys: {}", e))?;
+
+    if !snapshot.categories.is_empty() {
+        app.emit("categories-changed", ()).ok();
+    }
+
+    Ok(ImportReport {
+        applied: applied.len(),
+        skipped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn problem_keys(settings: &HashMap<String, String>) -> Vec<(String, bool)> {
+        provider_problems(settings)
+            .into_iter()
+            .map(|problem| (problem.key, problem.warning))
+            .collect()
+    }
+
+    #[test]
+    fn only_the_selected_provider_needs_a_key() {
+        let ollama = settings(&[("llm_provider", "ollama"), ("llm_api_key", "")]);
+        assert!(problem_keys(&ollama).is_empty());
+
+        let anthropic = settings(&[
+            ("llm_provider", "anthropic"),
+            ("llm_api_key", ""),
+            ("llm_api_key_anthropic", ""),
+            ("llm_model", "claude-3-5-haiku-latest"),
+        ]);
+        assert_eq!(
+            problem_keys(&anthropic),
+            vec![("llm_api_key_anthropic".to_string(), false)]
+        );
+    }
+
+    #[test]
+    fn unknown_models_are_a_warning() {
+        let anthropic = settings(&[
+            ("llm_provider", "anthropic"),
+            ("llm_api_key_anthropic", "sk-ant"),
+            ("llm_model", "gpt-4o"),
+        ]);
+        assert_eq!(
+            problem_keys(&anthropic),
+            vec![("llm_model".to_string(), true)]
+        );
+
+        let openai = settings(&[
+            ("llm_provider", "openai"),
+            ("llm_api_key", "sk-test"),
+            ("openai_model", "gpt-4o-mini"),
+            ("fallback_model", "gpt-4o-nano"),
+        ]);
+        assert_eq!(
+            problem_keys(&openai),
+            vec![("fallback_model".to_string(), true)]
+        );
+
+        // Ollama runs whatever model was pulled
+        let ollama = settings(&[("llm_provider", "ollama"), ("ollama_model", "my-model")]);
+        assert!(problem_keys(&ollama).is_empty());
+    }
+
+    #[test]
+    fn imports_empty_secrets_and_skips_unknown_keys() {
+        let snapshot = SettingsSnapshot {
+            version: SETTINGS_EXPORT_VERSION,
+            settings: settings(&[
+                ("llm_api_key", ""),
+                ("llm_provider", "openai"),
+                ("theme", "dark"),
+            ]),
+            categories: Vec::new(),
+        };
+        let (problems, skipped) = check_snapshot(&snapshot);
+        assert!(problems.is_empty(), "{:?}", problems);
+        assert_eq!(skipped, vec!["theme"]);
+
+        let snapshot = SettingsSnapshot {
+            settings: settings(&[("llm_provider", "gemini")]),
+            ..snapshot
+        };
+        assert_eq!(check_snapshot(&snapshot).0.len(), 1);
+    }
+
+    #[test]
+    fn an_empty_api_key_is_a_valid_value() {
+        assert_eq!(validate_setting("llm_api_key", ""), None);
+        assert!(validate_setting("llm_api_key", "sk abc").is_some());
+    }
+}
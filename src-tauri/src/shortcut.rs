@@ -1,4 +1,15 @@
+use crate::ansi;
+use crate::database;
+use crate::fragments::{self, FragmentMode, FragmentSuggestion};
+use crate::heuristic;
 use crate::llm;
+use crate::maintenance;
+use crate::permissions;
+use crate::rules;
+use crate::settings::{SettingsManager, SettingsManagerState};
+use crate::thumbnails;
+use crate::transforms;
+use crate::urls;
 use arboard::{Clipboard, ImageData};
 use base64::{engine::general_purpose, Engine};
 use enigo::{
@@ -6,11 +17,16 @@ use enigo::{
     Enigo, Key, Keyboard, Settings,
 };
 use image::{ImageBuffer, ImageFormat, Rgba};
-use rusqlite::{params, Connection};
+use rusqlite::{params, OptionalExtension};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
 use std::io::Cursor;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Instant, SystemTime};
 use std::{path::PathBuf, thread, time::Duration};
 use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut};
 use url::Url;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -22,94 +38,842 @@ pub enum Clip {
         data: String,
         width: usize,
         height: usize,
+        /// text flavor copied alongside the image, only kept with the "rich" preferred flavor
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        text: Option<String>,
     },
 }
 
+impl Clip {
+    /// Nothing worth saving: whitespace-only text, or an image without pixels.
+    /// A failed copy can still leave "" on the text flavor.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Clip::Text { plain } => plain.trim().is_empty(),
+            Clip::Image { width, height, .. } => *width == 0 || *height == 0,
+        }
+    }
+}
+
+/// Which clipboard representation wins when the source offers both text and an image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClipboardFlavor {
+    Text,
+    Image,
+    Rich,
+}
+
+impl ClipboardFlavor {
+    pub fn from_setting(value: &str) -> Self {
+        match value {
+            "image" => ClipboardFlavor::Image,
+            "rich" => ClipboardFlavor::Rich,
+            _ => ClipboardFlavor::Text,
+        }
+    }
+}
+
+/// Capture details stored next to a clip that are not part of its content. The
+/// review toolbar hands them back to submit_clip unchanged, apart from the expiry.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClipMeta {
+    pub source_app: Option<String>,
+    pub expires_in_minutes: Option<u32>,
+    /// sampled by scheduled capture rather than captured by the user
+    pub scheduled: bool,
+    /// model that categorized the clip when the configured one was rate limited
+    pub fallback_model: Option<String>,
+    /// when the content was first seen on the clipboard, in Unix seconds. Only
+    /// scheduled capture watches the clipboard between captures; a hotkey capture
+    /// copies the selection itself, so it has none.
+    pub copied_at: Option<i64>,
+    /// lines the text had before `capture_line_limit` cut it, None when it was kept whole
+    pub original_line_count: Option<usize>,
+}
+
+/// What a registered hotkey does when pressed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum HotkeyAction {
+    /// Copy the selection and let the LLM pick category and tags
+    Capture,
+    /// Copy the selection straight into a preset category, skipping the LLM categorization
+    CaptureToCategory { category: String },
+    /// Copy the selection and delete the clip again after `minutes`, for secrets
+    CaptureWithExpiry { minutes: u32 },
+    /// Clear the clipboard and delete the most recent capture, see panic_clear
+    PanicClear,
+}
+
+/// An extra hotkey configured in the `hotkey_actions` setting
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HotkeyBinding {
+    pub hotkey: String,
+    #[serde(flatten)]
+    pub action: HotkeyAction,
+}
+
+/// Registered shortcuts and the action each one triggers
+#[derive(Default)]
+pub struct HotkeyActionsState(pub Mutex<Vec<(Shortcut, HotkeyAction)>>);
+
+/// When each source app last had a capture go through, keyed by lowercase app name
+static LAST_CAPTURE: LazyLock<Mutex<HashMap<String, Instant>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Content hashes of the captures between being read and being saved
+static IN_FLIGHT: LazyLock<Mutex<Vec<String>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+
+/// In-flight captures panic_clear threw away, process_clip drops them unsaved
+static DISCARDED: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Marks a capture as in flight until it is dropped
+struct InFlight(String);
+
+impl InFlight {
+    fn start(hash: String) -> Self {
+        IN_FLIGHT.lock().unwrap().push(hash.clone());
+        InFlight(hash)
+    }
+
+    /// Whether panic_clear ran since the capture started
+    fn discarded(&self) -> bool {
+        DISCARDED.lock().unwrap().contains(&self.0)
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        let mut in_flight = IN_FLIGHT.lock().unwrap();
+        if let Some(index) = in_flight.iter().position(|hash| *hash == self.0) {
+            in_flight.swap_remove(index);
+        }
+        if !in_flight.contains(&self.0) {
+            DISCARDED.lock().unwrap().remove(&self.0);
+        }
+    }
+}
+
+/// Mark every capture in flight as discarded, false when there is none
+fn discard_in_flight() -> bool {
+    let in_flight = IN_FLIGHT.lock().unwrap();
+    DISCARDED.lock().unwrap().extend(in_flight.iter().cloned());
+    !in_flight.is_empty()
+}
+
+/// A capture shown in the review toolbar, with what the LLM suggested for it
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ClipContext {
     pub suggested_category: Option<String>,
+    pub summary: String,
+    pub tags: Vec<String>,
     pub clip: Clip,
+    pub meta: ClipMeta,
 }
 
-pub fn handle_shortcut(app_handle: &AppHandle, event: tauri_plugin_global_shortcut::ShortcutEvent) {
+pub fn handle_shortcut(
+    app_handle: &AppHandle,
+    shortcut: &Shortcut,
+    event: tauri_plugin_global_shortcut::ShortcutEvent,
+) {
     use tauri_plugin_global_shortcut::ShortcutState;
 
     match event.state {
         ShortcutState::Pressed => {
-            handle_capture(&app_handle);
+            let action = app_handle
+                .state::<HotkeyActionsState>()
+                .0
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|(registered, _)| registered == shortcut)
+                .map(|(_, action)| action.clone())
+                .unwrap_or(HotkeyAction::Capture);
+
+            match action {
+                HotkeyAction::Capture => handle_capture(&app_handle, None, None),
+                HotkeyAction::CaptureToCategory { category } => {
+                    handle_capture(&app_handle, Some(category), None)
+                }
+                HotkeyAction::CaptureWithExpiry { minutes } => {
+                    handle_capture(&app_handle, None, Some(minutes))
+                }
+                HotkeyAction::PanicClear => {
+                    let db_path = app_handle.state::<crate::AppState>().db_path.clone();
+                    if let Err(e) = panic_clear(app_handle, &db_path) {
+                        eprintln!("Failed to clear last capture: {}", e);
+                    }
+                }
+            }
         }
         _ => {}
     }
 }
 
+/// Delete the most recent capture and clear the clipboard, for when a secret was
+/// copied and saved by accident. The LLM answers cached for the content go too.
+/// A capture still being categorized is the most recent one, it is dropped before
+/// it is saved and the clips already saved stay. Returns the id of the deleted
+/// clip, None when nothing saved was deleted.
+pub fn panic_clear(app_handle: &AppHandle, db_path: &PathBuf) -> Result<Option<i64>, String> {
+    let deleted = if !discard_in_flight() {
+        let conn = database::open_connection(db_path)
+            .map_err(|e| format!("Failed to open database: {e}"))?;
+        let deleted: Option<(i64, Option<String>)> = conn
+            .query_row(
+                "DELETE FROM clips WHERE id = (SELECT id FROM clips ORDER BY created_at DESC, id DESC LIMIT 1) RETURNING id, content_hash",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(|e| format!("Failed to delete last capture: {e}"))?;
+        if let Some(hash) = deleted.as_ref().and_then(|(_, hash)| hash.as_deref()) {
+            if let Err(e) = llm::cache::remove_llm_cache(&conn, hash) {
+                eprintln!("Failed to remove cached LLM response: {}", e);
+            }
+        }
+        deleted.map(|(id, _)| id)
+    } else {
+        None
+    };
+
+    if let Some(id) = deleted {
+        app_handle.emit("clip-deleted", id.to_string()).ok();
+        app_handle.emit("tags-changed", ()).ok();
+    }
+
+    Clipboard::new()
+        .and_then(|mut clipboard| clipboard.clear())
+        .map_err(|e| format!("Failed to clear clipboard: {e}"))?;
+    app_handle.emit("clipboard-cleared", ()).ok();
+
+    Ok(deleted)
+}
+
+/// Extra hotkeys from the `hotkey_actions` setting, a JSON array of bindings
+pub fn hotkey_bindings(settings: &SettingsManager) -> Vec<HotkeyBinding> {
+    settings
+        .get_setting("hotkey_actions")
+        .and_then(|value| serde_json::from_str(&value).ok())
+        .unwrap_or_default()
+}
+
+/// (Re)register the global capture hotkey and every configured hotkey action
+pub fn register_hotkeys(app_handle: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
+    let settings = app_handle.state::<SettingsManagerState>();
+
+    let hotkey_str = settings.0.get_global_hotkey();
+    let capture = parse_hotkey_string(&hotkey_str)
+        .map_err(|e| format!("Failed to parse hotkey '{}': {}", hotkey_str, e))?;
+
+    let global_shortcut = app_handle.global_shortcut();
+    global_shortcut.unregister_all()?;
+    global_shortcut.register(capture)?;
+
+    let mut actions = vec![(capture, HotkeyAction::Capture)];
+
+    // a broken or conflicting extra binding should not take the main hotkey down with it
+    for binding in hotkey_bindings(&settings.0) {
+        let registered = parse_hotkey_string(&binding.hotkey).and_then(|shortcut| {
+            global_shortcut.register(shortcut)?;
+            Ok(shortcut)
+        });
+
+        match registered {
+            Ok(shortcut) => actions.push((shortcut, binding.action)),
+            Err(e) => eprintln!("Failed to register hotkey '{}': {}", binding.hotkey, e),
+        }
+    }
+
+    *app_handle.state::<HotkeyActionsState>().0.lock().unwrap() = actions;
+
+    Ok(())
+}
+
+const WAKE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A tick that arrives this much later than scheduled means the machine was asleep
+const WAKE_THRESHOLD: Duration = Duration::from_secs(90);
+
+/// Re-register the hotkeys after the machine wakes up, macOS sometimes drops global
+/// shortcuts over sleep. Sleep is noticed through the wall clock jumping ahead,
+/// the thread itself does not run while the machine is asleep.
+pub fn start_wake_watcher(app_handle: AppHandle) {
+    thread::spawn(move || {
+        let mut last_tick = SystemTime::now();
+        loop {
+            thread::sleep(WAKE_CHECK_INTERVAL);
+
+            let now = SystemTime::now();
+            let woke_up = now
+                .duration_since(last_tick)
+                .is_ok_and(|elapsed| elapsed > WAKE_THRESHOLD);
+            last_tick = now;
+
+            let enabled = app_handle
+                .state::<SettingsManagerState>()
+                .0
+                .get_bool("reregister_on_wake");
+            if woke_up && enabled {
+                println!("[clipper] Woke up from sleep, re-registering hotkeys");
+                if let Err(e) = register_hotkeys(&app_handle) {
+                    eprintln!("Failed to re-register hotkeys after sleep: {}", e);
+                }
+            }
+        }
+    });
+}
+
+/// Modifier of the copy and paste shortcuts, and its name for error messages
+#[cfg(target_os = "macos")]
+fn shortcut_modifier() -> (Key, &'static str) {
+    (Key::Meta, "Cmd")
+}
+
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+fn shortcut_modifier() -> (Key, &'static str) {
+    (Key::Control, "Ctrl")
+}
+
+/// Press `key` with the shortcut modifier held, as Cmd+C or Ctrl+V
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+fn simulate_shortcut(key: char) -> Result<(), String> {
+    let (modifier, name) = shortcut_modifier();
+    let mut enigo = Enigo::new(&Settings::default())
+        .map_err(|e| format!("Failed to create input simulator: {e}"))?;
+
+    enigo
+        .key(modifier, Press)
+        .map_err(|e| format!("Failed to press {name}: {e}"))?;
+    let pressed = enigo.key(Key::Unicode(key), Click);
+    // always release the modifier, even when the click failed
+    enigo
+        .key(modifier, Release)
+        .map_err(|e| format!("Failed to release {name}: {e}"))?;
+
+    pressed.map_err(|e| format!("Failed to press {}: {e}", key.to_ascii_uppercase()))
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+fn simulate_copy() -> Result<(), String> {
+    simulate_shortcut('c')
+}
+
+/// Paste into the application that has focus
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+pub fn simulate_paste() -> Result<(), String> {
+    simulate_shortcut('v')
+}
+
+/// No known copy shortcut, the capture reads whatever is already on the clipboard
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+fn simulate_copy() -> Result<(), String> {
+    eprintln!(
+        "[clipper] Simulating copy is not supported on {}, capturing the clipboard as it is",
+        std::env::consts::OS
+    );
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+pub fn simulate_paste() -> Result<(), String> {
+    Err(format!(
+        "Simulating paste is not supported on {}",
+        std::env::consts::OS
+    ))
+}
+
+/// Name of the application that had focus when the hotkey fired
+#[cfg(target_os = "macos")]
+fn frontmost_app() -> Option<String> {
+    let output = std::process::Command::new("osascript")
+        .args([
+            "-e",
+            "tell application \"System Events\" to get name of first application process whose frontmost is true",
+        ])
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let name = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!name.is_empty()).then_some(name)
+}
+
+#[cfg(not(target_os = "macos"))]
+fn frontmost_app() -> Option<String> {
+    None
+}
+
+/// Whether a capture from `source_app` comes too soon after the previous one from
+/// the same app. The `app_cooldowns` setting maps app names to milliseconds, apps
+/// without a rule use `capture_debounce_ms`.
+fn in_cooldown(settings: &SettingsManager, source_app: Option<&str>) -> bool {
+    let app = source_app.unwrap_or_default().to_lowercase();
+
+    let cooldowns: HashMap<String, u64> =
+        serde_json::from_str(&settings.get_string("app_cooldowns")).unwrap_or_default();
+    let cooldown_ms = cooldowns
+        .into_iter()
+        .find(|(name, _)| name.to_lowercase() == app)
+        .map(|(_, ms)| ms)
+        .unwrap_or_else(|| u64::from(settings.get_u32("capture_debounce_ms")));
+
+    let now = Instant::now();
+    let mut last_capture = LAST_CAPTURE.lock().unwrap();
+    if last_capture
+        .get(&app)
+        .is_some_and(|at| now.duration_since(*at) < Duration::from_millis(cooldown_ms))
+    {
+        return true;
+    }
+
+    last_capture.insert(app, now);
+    false
+}
+
+/// Per-platform defaults for CaptureTiming: (copy delay ms, read attempts, retry delay ms).
+/// The pasteboard updates quickly on macOS, Windows clipboard chains and X11/Wayland
+/// selection owners take noticeably longer to hand over a fresh copy.
 #[cfg(target_os = "macos")]
-fn simulate_copy() {
-    let mut enigo = Enigo::new(&Settings::default()).unwrap();
-    let _ = enigo.key(Key::Meta, Press);
-    let _ = enigo.key(Key::Unicode('c'), Click);
-    let _ = enigo.key(Key::Meta, Release);
+const DEFAULT_CAPTURE_TIMING: (u32, u32, u32) = (120, 5, 50);
+#[cfg(target_os = "windows")]
+const DEFAULT_CAPTURE_TIMING: (u32, u32, u32) = (200, 8, 50);
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const DEFAULT_CAPTURE_TIMING: (u32, u32, u32) = (250, 10, 75);
+
+/// How long a simulated copy gets to reach the clipboard, and how the clipboard
+/// is polled afterwards until its content settles
+struct CaptureTiming {
+    copy_delay: Duration,
+    read_attempts: usize,
+    retry_delay: Duration,
+}
+
+impl CaptureTiming {
+    fn from_settings(settings: &SettingsManager) -> Self {
+        let (copy_delay_ms, read_attempts, retry_delay_ms) = DEFAULT_CAPTURE_TIMING;
+        Self {
+            copy_delay: Duration::from_millis(
+                settings.get_u32_or("copy_delay_ms", copy_delay_ms).into(),
+            ),
+            read_attempts: settings.get_u32_or("clipboard_read_attempts", read_attempts) as usize,
+            retry_delay: Duration::from_millis(
+                settings
+                    .get_u32_or("clipboard_retry_delay_ms", retry_delay_ms)
+                    .into(),
+            ),
+        }
+    }
 }
 
-pub fn handle_capture(app: &AppHandle) {
-    simulate_copy();
-    thread::sleep(Duration::from_millis(120));
-    if let Some(clip) = read_clipboard_with_retry(5, Duration::from_millis(50)) {
+/// Copy the current selection and run it through process_clip
+pub fn handle_capture(
+    app: &AppHandle,
+    forced_category: Option<String>,
+    expires_in_minutes: Option<u32>,
+) {
+    // osascript takes a moment, look up the source app while the copy propagates
+    let source_app = thread::spawn(frontmost_app);
+
+    // without accessibility access the copy keystroke is silently dropped, so
+    // stop here instead of polling the clipboard for a selection that never arrives
+    if !permissions::accessibility_granted() {
+        permissions::report_permission_error(
+            app,
+            "accessibility",
+            "Accessibility access not granted",
+        );
+        return;
+    }
+
+    if let Err(e) = simulate_copy() {
+        permissions::report_permission_error(app, "accessibility", &e);
+        return;
+    }
+
+    let timing = CaptureTiming::from_settings(&app.state::<SettingsManagerState>().0);
+
+    thread::sleep(timing.copy_delay);
+
+    let flavor = ClipboardFlavor::from_setting(
+        &app.state::<SettingsManagerState>()
+            .0
+            .get_string("preferred_flavor"),
+    );
+
+    let source_app = source_app.join().ok().flatten();
+
+    if in_cooldown(
+        &app.state::<SettingsManagerState>().0,
+        source_app.as_deref(),
+    ) {
+        println!(
+            "[clipper] Capture from {} skipped, still in cooldown.",
+            source_app.as_deref().unwrap_or("unknown app")
+        );
+        return;
+    }
+
+    if let Some(clip) = read_clipboard_with_retry(timing.read_attempts, timing.retry_delay, flavor)
+    {
+        if clip.is_empty() {
+            println!("[clipper] Captured clip is empty, not saving it.");
+            app.emit("capture-empty", &source_app).ok();
+            return;
+        }
+
         let db_path = app.state::<crate::AppState>().db_path.clone();
+        let meta = ClipMeta {
+            source_app,
+            expires_in_minutes,
+            ..Default::default()
+        };
 
         let app_handle = app.clone();
-        let clip_clone = clip.clone();
         tauri::async_runtime::spawn(async move {
-            // Get category and tags from LLM
-            let (category, tags) = match llm::get_llm_category(&clip_clone).await {
-                Ok(category_response) => (category_response.category, category_response.tags),
-                Err(e) => {
-                    eprintln!("LLM categorization failed: {}", e);
-                    ("other".to_string(), vec!["uncategorized".to_string()])
+            if let Err(e) = process_clip(&app_handle, &db_path, &clip, forced_category, &meta).await
+            {
+                eprintln!("Failed to save clip: {}", e);
+            }
+        });
+    } else {
+        println!("[clipper] Nothing captured (no selection or copy failed).");
+    }
+}
+
+/// Transform, categorize, summarize and save a clip, the pipeline behind every
+/// capture. With a forced category, or one set by a tag rule, the LLM categorization
+/// is skipped and the clip only gets heuristic tags. Tags from matching rules are
+/// added either way. Content captured before reuses the LLM's earlier answers, see
+/// `llm::cache`. A capture continuing the previous clip is merged into it or
+/// reported with "fragment-detected", depending on `fragment_detection`.
+pub async fn process_clip(
+    app_handle: &AppHandle,
+    db_path: &PathBuf,
+    clip: &Clip,
+    forced_category: Option<String>,
+    meta: &ClipMeta,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let settings = app_handle.state::<SettingsManagerState>().0.clone();
+    let clip = &transforms::apply_transforms(clip, &transforms::configured_transforms(&settings));
+    let (clip, original_line_count) = transforms::limit_lines(clip, &settings);
+    let clip = &clip;
+
+    let hash = content_hash(clip);
+    let capture = InFlight::start(hash.clone());
+
+    if dedupe_url_capture(app_handle, db_path, clip).await {
+        return Ok(());
+    }
+    if capture.discarded() {
+        println!("Capture discarded by panic clear");
+        return Ok(());
+    }
+
+    let continues = fragments::find_continuation(db_path, &settings, clip).unwrap_or_else(|e| {
+        eprintln!("Failed to check for a continued capture: {}", e);
+        None
+    });
+
+    // the merged clip keeps the category and tags of its first part
+    if let Some((previous_id, merged)) = &continues {
+        if FragmentMode::from_settings(&settings) == FragmentMode::Merge {
+            let conn = database::open_connection(db_path)?;
+            if let Some(folded) = fragments::set_clip_text(&conn, *previous_id, merged)? {
+                app_handle.emit("clip-deleted", folded.to_string()).ok();
+            }
+            app_handle
+                .emit("clip-updated", previous_id.to_string())
+                .ok();
+            println!("Capture merged into clip {}", previous_id);
+            return Ok(());
+        }
+    }
+
+    let mut meta = meta.clone();
+    meta.original_line_count = original_line_count;
+
+    // a category from a tag rule makes the LLM call unnecessary
+    let ruled = rules::apply_rules(
+        &rules::configured_rules(&settings),
+        clip,
+        meta.source_app.as_deref(),
+    );
+    let forced_category = forced_category.or(ruled.category);
+
+    // content saved before is bumped without asking the LLM again
+    let bumped = bump_clip(
+        &database::open_connection(db_path)?,
+        &hash,
+        forced_category.as_deref(),
+        &ruled.tags,
+        meta.expires_in_minutes,
+    )?;
+    if let Some(id) = bumped {
+        emit_save_result(app_handle, SaveResult::Bumped(id));
+        println!("Clip already saved, bumped clip {}", id);
+        return Ok(());
+    }
+
+    // the same content captured before reuses the LLM's answers
+    let cached = database::open_connection(db_path)
+        .ok()
+        .and_then(|conn| llm::cache::check_llm_cache(&conn, &hash))
+        .unwrap_or_default();
+    let mut to_cache = cached.clone();
+    let mut fetched = false;
+
+    // Get category and tags from LLM
+    let (category, mut tags) = match (forced_category, cached.category) {
+        (Some(category), _) => (category, heuristic::suggest_tags(clip)),
+        (None, Some((category, tags))) => {
+            println!("Using cached LLM category");
+            (category, tags)
+        }
+        (None, None) => match llm::get_llm_category_with_context(
+            clip,
+            &recent_clip_context(app_handle, db_path),
+        )
+        .await
+        {
+            Ok(category_response) => {
+                meta.fallback_model = category_response.fallback_model;
+                // local guesses and unusable answers are not worth keeping
+                if llm::has_credentials() && !category_response.unparsed {
+                    to_cache.category = Some((
+                        category_response.category.clone(),
+                        category_response.tags.clone(),
+                    ));
+                    fetched = true;
                 }
-            };
+                (category_response.category, category_response.tags)
+            }
+            Err(e) => {
+                eprintln!("LLM categorization failed: {}", e);
+                let guess = heuristic::categorize(clip);
+                (guess.category, guess.tags)
+            }
+        },
+    };
+    tags.extend(ruled.tags);
 
-            let mut summary: String = String::new();
-
-            match &clip_clone {
-                Clip::Text { plain } => {
-                    if is_url(plain) {
-                        match llm::get_clip_summary(&clip_clone).await {
-                            Ok(suggested_summary) => summary = suggested_summary,
-                            Err(e) => {
-                                eprintln!("LLM summarization failed: {}", e);
-                                summary = "No summary available".to_string();
-                            }
-                        };
-                    }
+    // links and images are summarized, unless that is left for summarize_clip to do on demand
+    let summarize = settings.get_bool("summarize_on_capture")
+        && match clip {
+            Clip::Text { plain } => is_url(plain),
+            Clip::Image { .. } => true,
+        };
+
+    let summary = match (summarize, cached.summary) {
+        (false, _) => String::new(),
+        (true, Some(cached_summary)) => cached_summary,
+        (true, None) => match llm::get_clip_summary(clip).await {
+            Ok(suggested_summary) => {
+                if suggested_summary != llm::NO_SUMMARY {
+                    to_cache.summary = Some(suggested_summary.clone());
+                    fetched = true;
                 }
-                Clip::Image { .. } => {
-                    match llm::get_clip_summary(&clip_clone).await {
-                        Ok(suggested_summary) => summary = suggested_summary,
-                        Err(e) => {
-                            eprintln!("LLM summarization failed: {}", e);
-                            summary = "No summary available".to_string();
+                suggested_summary
+            }
+            Err(e) => {
+                eprintln!("LLM summarization failed: {}", e);
+                "No summary available".to_string()
+            }
+        },
+    };
+
+    if fetched {
+        let stored = database::open_connection(db_path)
+            .and_then(|conn| llm::cache::store_llm_cache(&conn, &hash, &to_cache));
+        if let Err(e) = stored {
+            eprintln!("Failed to cache LLM response: {}", e);
+        }
+    }
+
+    // panic_clear ran while the LLM was answering, its answers go too
+    if capture.discarded() {
+        let removed = database::open_connection(db_path)
+            .and_then(|conn| llm::cache::remove_llm_cache(&conn, &hash));
+        if let Err(e) = removed {
+            eprintln!("Failed to remove cached LLM response: {}", e);
+        }
+        println!("Capture discarded by panic clear");
+        return Ok(());
+    }
+
+    // the user confirms or changes the category in the toolbar, submit_clip saves it
+    if settings.get_bool("review_captures") && !meta.scheduled {
+        let context = ClipContext {
+            suggested_category: Some(category),
+            summary,
+            tags,
+            clip: clip.clone(),
+            meta,
+        };
+        crate::toolbar::review_clip(app_handle, context).await?;
+        return Ok(());
+    }
+
+    let saved = save_clip(app_handle, db_path, clip, &category, &summary, &tags, &meta).await?;
+    emit_save_result(app_handle, saved);
+    let id = match saved {
+        SaveResult::Inserted(id) => id,
+        SaveResult::Bumped(id) => {
+            println!("Clip already saved, bumped clip {}", id);
+            return Ok(());
+        }
+    };
+    println!("Clip saved to category: {} with tags: {:?}", category, tags);
+
+    if let Some((previous_id, _)) = continues {
+        let suggestion = FragmentSuggestion {
+            previous_id,
+            fragment_id: id,
+        };
+        app_handle.emit("fragment-detected", suggestion).ok();
+    }
+
+    Ok(())
+}
+
+/// Short descriptions of the clips captured right before this one, for the
+/// categorization prompt. Off unless `llm_context_clips` is above 0, since it
+/// costs tokens and can pull unrelated captures into the tags.
+fn recent_clip_context(app_handle: &AppHandle, db_path: &PathBuf) -> Vec<String> {
+    let settings = app_handle.state::<SettingsManagerState>();
+    let count = settings.0.get_u32("llm_context_clips");
+    if count == 0 {
+        return Vec::new();
+    }
+    let window_minutes = settings.0.get_u32("llm_context_window_minutes");
+
+    let recent = database::open_connection(db_path).and_then(|conn| {
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT category, summary, CASE WHEN content_type = 'image' THEN NULL ELSE json_extract(clip, '$.content') END
+            FROM clips
+            WHERE created_at >= datetime('now', ?1)
+            ORDER BY created_at DESC
+            LIMIT ?2
+            "#,
+        )?;
+        let rows = stmt
+            .query_map(params![format!("-{} minutes", window_minutes), count], |row| {
+                Ok((
+                    row.get::<_, Option<String>>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows)
+    });
+
+    match recent {
+        Ok(rows) => rows
+            .into_iter()
+            .filter_map(|(category, summary, content)| {
+                let description = summary
+                    .filter(|summary| !summary.trim().is_empty())
+                    .or_else(|| content.map(|content| truncate_chars(&content, 200)))?;
+                Some(format!(
+                    "[{}] {}",
+                    category.unwrap_or_else(|| "other".to_string()),
+                    description
+                ))
+            })
+            .collect(),
+        Err(e) => {
+            eprintln!("Failed to load recent clips for context: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+pub(crate) fn truncate_chars(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((byte_index, _)) => format!("{}...", &text[..byte_index]),
+        None => text.to_string(),
+    }
+}
+
+/// Handle a captured link that was already saved, according to the `dedupe_urls`
+/// setting. Returns true when the capture was folded into the existing clip.
+///
+/// - "bump" (default): move the existing clip to the top, count the reuse and
+///   refresh its summary once it is older than `url_summary_refresh_days`
+/// - "off": keep every copy whose text differs, identical ones are bumped by save_clip
+async fn dedupe_url_capture(app_handle: &AppHandle, db_path: &PathBuf, clip: &Clip) -> bool {
+    let normalized = match clip {
+        Clip::Text { plain } if is_url(plain) => match urls::normalize_url(plain) {
+            Some(normalized) => normalized,
+            None => return false,
+        },
+        _ => return false,
+    };
+
+    let settings = app_handle.state::<SettingsManagerState>();
+    let mode = settings.0.get_string("dedupe_urls");
+    let refresh_days = f64::from(settings.0.get_u32("url_summary_refresh_days"));
+    let summarize = settings.0.get_bool("summarize_on_capture");
+
+    let existing = database::open_connection(db_path).and_then(|conn| {
+        conn.query_row(
+            "SELECT id, julianday('now') - julianday(COALESCE(summary_updated_at, created_at)) FROM clips WHERE normalized_url = ? ORDER BY created_at DESC LIMIT 1",
+            params![normalized],
+            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, f64>(1)?)),
+        )
+        .optional()
+    });
+
+    let (id, summary_age_days) = match existing {
+        Ok(Some(existing)) => existing,
+        Ok(None) => return false,
+        Err(e) => {
+            eprintln!("Failed to look up existing link: {}", e);
+            return false;
+        }
+    };
+
+    match mode.as_str() {
+        "bump" => {
+            let bumped = database::open_connection(db_path).and_then(|conn| {
+                conn.execute(
+                    "UPDATE clips SET created_at = CURRENT_TIMESTAMP, created_date = date('now', 'localtime'), use_count = COALESCE(use_count, 1) + 1 WHERE id = ?",
+                    params![id],
+                )
+            });
+            if let Err(e) = bumped {
+                eprintln!("Failed to bump existing link: {}", e);
+                return false;
+            }
+
+            if summarize && refresh_days > 0.0 && summary_age_days > refresh_days {
+                match llm::get_clip_summary(clip).await {
+                    Ok(summary) => {
+                        let refreshed = database::open_connection(db_path).and_then(|conn| {
+                            conn.execute(
+                                "UPDATE clips SET summary = ?1, summary_updated_at = CURRENT_TIMESTAMP, clip = json_set(clip, '$.summary', ?1) WHERE id = ?2",
+                                params![summary, id],
+                            )
+                        });
+                        if let Err(e) = refreshed {
+                            eprintln!("Failed to refresh link summary: {}", e);
                         }
-                    };
+                    }
+                    Err(e) => eprintln!("LLM summarization failed: {}", e),
                 }
             }
 
-            if let Err(e) = save_clip(
-                &app_handle,
-                &db_path,
-                &clip_clone,
-                &category,
-                &summary,
-                &tags,
-            )
-            .await
-            {
-                eprintln!("Failed to save clip: {}", e);
-            } else {
-                println!("Clip saved to category: {} with tags: {:?}", category, tags);
-            }
-        });
-    } else {
-        println!("[clipper] Nothing captured (no selection or copy failed).");
+            println!("Link already saved, bumped clip {}", id);
+            app_handle.emit("clip-updated", id.to_string()).ok();
+            true
+        }
+        _ => false,
     }
 }
 
@@ -122,11 +886,46 @@ pub fn is_url(text: &str) -> bool {
     }
 }
 
-fn read_clipboard_with_retry(attempts: usize, delay: Duration) -> Option<Clip> {
+/// Coarse hint of what a clip holds so the UI can pick a renderer
+pub fn content_type(clip: &Clip) -> &'static str {
+    match clip {
+        Clip::Text { plain } if ansi::contains_ansi(plain) => "terminal",
+        Clip::Text { plain } if is_url(plain) => "url",
+        Clip::Text { .. } => "text",
+        Clip::Image { .. } => "image",
+    }
+}
+
+/// Stable fingerprint of a clip's content, identical clips share the same hash
+pub fn content_hash(clip: &Clip) -> String {
+    match clip {
+        Clip::Text { plain } => hash_content("text", &ansi::strip_ansi(plain)),
+        Clip::Image { data, .. } => hash_content("image", data),
+    }
+}
+
+/// Hash the stored form of a clip: the ANSI-stripped text or the base64 image data
+pub fn hash_content(kind: &str, content: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(kind.as_bytes());
+    hasher.update([0]);
+    hasher.update(content.as_bytes());
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn read_clipboard_with_retry(
+    attempts: usize,
+    delay: Duration,
+    flavor: ClipboardFlavor,
+) -> Option<Clip> {
     let mut last_len: Option<usize> = None;
 
     for i in 0..attempts {
-        if let Some(clip) = read_clipboard_once() {
+        if let Some(clip) = read_clipboard_once(flavor) {
             // compare lengths for text / byte size for image
             let cur_len = match &clip {
                 Clip::Text { plain } => plain.len(),
@@ -144,38 +943,54 @@ fn read_clipboard_with_retry(attempts: usize, delay: Duration) -> Option<Clip> {
     None
 }
 
-fn read_clipboard_once() -> Option<Clip> {
-    let mut cb = Clipboard::new().ok()?;
+pub(crate) fn read_clipboard_once(flavor: ClipboardFlavor) -> Option<Clip> {
+    let mut cb = match Clipboard::new() {
+        Ok(cb) => cb,
+        Err(e) => {
+            eprintln!("Failed to access clipboard: {}", e);
+            return None;
+        }
+    };
 
-    if let Ok(txt) = cb.get_text() {
-        return Some(Clip::Text { plain: txt });
+    match flavor {
+        ClipboardFlavor::Text => match cb.get_text() {
+            Ok(txt) => Some(Clip::Text { plain: txt }),
+            Err(_) => read_clipboard_image(&mut cb, None),
+        },
+        ClipboardFlavor::Image => read_clipboard_image(&mut cb, None)
+            .or_else(|| cb.get_text().ok().map(|txt| Clip::Text { plain: txt })),
+        ClipboardFlavor::Rich => {
+            let text = cb.get_text().ok();
+            read_clipboard_image(&mut cb, text.clone())
+                .or_else(|| text.map(|txt| Clip::Text { plain: txt }))
+        }
     }
+}
 
-    if let Ok(ImageData {
+fn read_clipboard_image(cb: &mut Clipboard, text: Option<String>) -> Option<Clip> {
+    let ImageData {
         bytes,
         width,
         height,
-    }) = cb.get_image()
-    {
-        // clipboard returns raw rgba pixels which we need to convert into a png to then base64 encode it and save it.
-        let png_data = match raw_pixels_to_png(&bytes, width, height) {
-            Ok(data) => data,
-            Err(e) => {
-                eprintln!("Failed to convert pixels to PNG: {}", e);
-                return None;
-            }
-        };
+    } = cb.get_image().ok()?;
 
-        let base64_data = general_purpose::STANDARD.encode(&png_data);
+    // clipboard returns raw rgba pixels which we need to convert into a png to then base64 encode it and save it.
+    let png_data = match raw_pixels_to_png(&bytes, width, height) {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("Failed to convert pixels to PNG: {}", e);
+            return None;
+        }
+    };
 
-        return Some(Clip::Image {
-            data: base64_data,
-            width,
-            height,
-        });
-    }
+    let base64_data = general_purpose::STANDARD.encode(&png_data);
 
-    None
+    Some(Clip::Image {
+        data: base64_data,
+        width,
+        height,
+        text,
+    })
 }
 
 fn raw_pixels_to_png(
@@ -194,54 +1009,228 @@ fn raw_pixels_to_png(
     Ok(png_data)
 }
 
-pub async fn save_clip(
-    app_handle: &AppHandle,
-    db_path: &PathBuf,
+/// Serialize the clip column. Readers go through serde_json or SQLite's JSON
+/// functions, which accept both layouts; rows rewritten with json_set end up compact.
+pub fn clip_json_string(value: &serde_json::Value, pretty: bool) -> serde_json::Result<String> {
+    if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    }
+}
+
+/// What save_clip did with a clip
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveResult {
+    /// stored as a new clip with this id
+    Inserted(i64),
+    /// already saved under this id, which was moved to the top instead
+    Bumped(i64),
+}
+
+impl SaveResult {
+    pub fn id(self) -> i64 {
+        match self {
+            SaveResult::Inserted(id) | SaveResult::Bumped(id) => id,
+        }
+    }
+}
+
+/// Tell the UI about a saved clip: "clip-saved" for a new one, "clip-updated"
+/// with the id for one that was bumped
+pub fn emit_save_result(app_handle: &AppHandle, result: SaveResult) {
+    match result {
+        SaveResult::Inserted(_) => {
+            app_handle.emit("clip-saved", {}).ok();
+            app_handle.emit("tags-changed", ()).ok();
+        }
+        SaveResult::Bumped(id) => {
+            app_handle.emit("clip-updated", id.to_string()).ok();
+        }
+    }
+}
+
+/// The clip column of a clips row: the content with the category, summary and
+/// tags it was saved with
+pub(crate) fn clip_json(
     clip: &Clip,
     category: &str,
     summary: &str,
     tags: &[String],
-) -> Result<(), Box<dyn std::error::Error>> {
-    let json_data = match clip {
+) -> serde_json::Value {
+    match clip {
         Clip::Text { plain } => {
-            serde_json::json!({
+            let mut value = serde_json::json!({
                 "type": "text",
-                "content": plain,
+                "content": ansi::strip_ansi(plain),
                 "category": category,
                 "summary": summary,
                 "tags": tags
-            })
+            });
+            // keep the escape sequences around so the UI can render terminal colors
+            if ansi::contains_ansi(plain) {
+                value["raw"] = serde_json::json!(plain);
+            }
+            value
         }
         Clip::Image {
             data,
             width,
             height,
+            text,
         } => {
             // let b64 = general_purpose::STANDARD.encode(data);
-            serde_json::json!({
+            let mut value = serde_json::json!({
                 "type": "image",
                 "content": data,
                 "width": width,
                 "height": height,
                 "category": category,
                 "summary": summary
-            })
+            });
+            if let Some(text) = text {
+                value["text"] = serde_json::json!(text);
+            }
+            value
         }
-    };
+    }
+}
+
+/// Store a clip, or when a clip with the same content is already saved, move
+/// that one to the top and count the reuse instead of storing it twice
+pub async fn save_clip(
+    app_handle: &AppHandle,
+    db_path: &PathBuf,
+    clip: &Clip,
+    category: &str,
+    summary: &str,
+    tags: &[String],
+    meta: &ClipMeta,
+) -> Result<SaveResult, Box<dyn std::error::Error>> {
+    let hash = content_hash(clip);
+    let conn = database::open_connection(db_path)?;
+
+    // the model occasionally repeats a tag in another casing
+    let tags = maintenance::clean_tags(tags);
+
+    let mut json_data = clip_json(clip, category, summary, &tags);
+
+    if let Some(model) = &meta.fallback_model {
+        json_data["fallback_model"] = serde_json::json!(model);
+    }
 
     // Convert tags to JSON string
-    let tags_json = serde_json::to_string(tags)?;
+    let tags_json = serde_json::to_string(&tags)?;
 
-    let conn = Connection::open(db_path)?;
+    let expiry_modifier = meta.expires_in_minutes.map(|m| format!("+{} minutes", m));
 
-    conn.execute(
-        "INSERT INTO clips(clip, category, summary, tags) VALUES (?,?,?,?)",
-        params![json_data.to_string(), category, summary, tags_json],
+    let normalized_url = match clip {
+        Clip::Text { plain } if is_url(plain) => urls::normalize_url(plain),
+        _ => None,
+    };
+
+    let code_language = match clip {
+        Clip::Text { plain } => heuristic::detect_language(plain),
+        Clip::Image { .. } => None,
+    };
+
+    let settings = app_handle.state::<SettingsManagerState>().0.clone();
+
+    let thumbnail = match clip {
+        Clip::Image { data, .. } => {
+            let size = thumbnails::thumbnail_size(&settings);
+            match thumbnails::make_thumbnail(data, size) {
+                Ok(thumbnail) => Some(thumbnail),
+                Err(e) => {
+                    eprintln!("Failed to create thumbnail: {}", e);
+                    None
+                }
+            }
+        }
+        Clip::Text { .. } => None,
+    };
+
+    let workspace = crate::workspaces::active_workspace(&settings);
+
+    // content saved in the meantime, e.g. by a capture still being categorized, is
+    // bumped and takes the categorization and expiry of this one
+    let (id, use_count): (i64, i64) = conn.query_row(
+        "INSERT INTO clips(clip, category, summary, tags, expires_at, content_type, source_app, normalized_url, summary_updated_at, content_hash, thumbnail, workspace, code_language, scheduled, copied_at, is_truncated, original_line_count) VALUES (?1, ?2, ?3, ?4, CASE WHEN ?5 IS NULL THEN NULL ELSE datetime('now', ?5) END, ?6, ?7, ?8, CURRENT_TIMESTAMP, ?9, ?10, ?11, ?12, ?13, CASE WHEN ?14 IS NULL THEN NULL ELSE datetime(?14, 'unixepoch') END, ?15, ?16) \
+         ON CONFLICT(content_hash) DO UPDATE SET created_at = CURRENT_TIMESTAMP, created_date = date('now', 'localtime'), use_count = COALESCE(clips.use_count, 1) + 1, \
+         category = excluded.category, tags = excluded.tags, summary = COALESCE(NULLIF(excluded.summary, ''), clips.summary), expires_at = COALESCE(excluded.expires_at, clips.expires_at) \
+         RETURNING id, use_count",
+        params![
+            clip_json_string(&json_data, settings.get_string("clip_json_format") == "pretty")?,
+            category,
+            summary,
+            tags_json,
+            expiry_modifier,
+            content_type(clip),
+            meta.source_app,
+            normalized_url,
+            hash,
+            thumbnail,
+            workspace,
+            code_language,
+            meta.scheduled,
+            meta.copied_at,
+            meta.original_line_count.is_some(),
+            meta.original_line_count.map(|count| count as i64)
+        ],
+        |row| Ok((row.get(0)?, row.get(1)?)),
     )?;
 
-    app_handle.emit("clip-saved", {}).unwrap();
+    Ok(if use_count > 1 {
+        SaveResult::Bumped(id)
+    } else {
+        SaveResult::Inserted(id)
+    })
+}
+
+/// Move the clip saved with `hash` to the top and count the reuse, None when no
+/// clip has that content. A category and expiry given replace the clip's own,
+/// tags are added to its tags.
+fn bump_clip(
+    conn: &rusqlite::Connection,
+    hash: &str,
+    category: Option<&str>,
+    tags: &[String],
+    expires_in_minutes: Option<u32>,
+) -> Result<Option<i64>, Box<dyn std::error::Error>> {
+    let existing: Option<(i64, Option<String>)> = conn
+        .query_row(
+            "SELECT id, tags FROM clips WHERE content_hash = ?",
+            params![hash],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    let Some((id, existing_tags)) = existing else {
+        return Ok(None);
+    };
 
-    Ok(())
+    let tags_json = if tags.is_empty() {
+        None
+    } else {
+        let mut merged: Vec<String> = existing_tags
+            .and_then(|existing| serde_json::from_str(&existing).ok())
+            .unwrap_or_default();
+        merged.extend(tags.iter().cloned());
+        Some(serde_json::to_string(&maintenance::clean_tags(&merged))?)
+    };
+
+    conn.execute(
+        "UPDATE clips SET created_at = CURRENT_TIMESTAMP, created_date = date('now', 'localtime'), use_count = COALESCE(use_count, 1) + 1, \
+         category = COALESCE(?2, category), tags = COALESCE(?3, tags), expires_at = CASE WHEN ?4 IS NULL THEN expires_at ELSE datetime('now', ?4) END \
+         WHERE id = ?1",
+        params![
+            id,
+            category,
+            tags_json,
+            expires_in_minutes.map(|m| format!("+{} minutes", m))
+        ],
+    )?;
+
+    Ok(Some(id))
 }
 
 pub fn parse_hotkey_string(
@@ -279,6 +1268,23 @@ pub fn parse_hotkey_string(
     ))
 }
 
+/// Check that no other application holds `shortcut` by registering it and letting
+/// go of it straight away. Shortcuts this app registered already count as available.
+pub fn validate_hotkey_availability(shortcut: &Shortcut, app: &AppHandle) -> Result<(), String> {
+    let global_shortcut = app.global_shortcut();
+    if global_shortcut.is_registered(*shortcut) {
+        return Ok(());
+    }
+
+    if let Err(e) = global_shortcut.register(*shortcut) {
+        eprintln!("Failed to register hotkey {:?}: {}", shortcut, e);
+        return Err("Shortcut already in use by another application.".to_string());
+    }
+    global_shortcut
+        .unregister(*shortcut)
+        .map_err(|e| format!("Failed to release hotkey after checking it: {}", e))
+}
+
 fn parse_key_code(
     key: &str,
 ) -> Result<tauri_plugin_global_shortcut::Code, Box<dyn std::error::Error>> {
@@ -339,3 +1345,49 @@ fn parse_key_code(
         _ => Err(format!("Unsupported key: {}", key).into()),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn review_keeps_the_capture_details() {
+        let context = ClipContext {
+            suggested_category: Some("code".to_string()),
+            summary: String::new(),
+            tags: Vec::new(),
+            clip: Clip::Text {
+                plain: "fn main() {}".to_string(),
+            },
+            meta: ClipMeta {
+                source_app: Some("Terminal".to_string()),
+                expires_in_minutes: Some(10),
+                scheduled: false,
+                fallback_model: Some("gpt-4o-mini".to_string()),
+                copied_at: Some(1_700_000_000),
+                original_line_count: Some(120),
+            },
+        };
+
+        // what the toolbar receives and sends back
+        let sent = serde_json::to_value(&context).unwrap();
+        let meta: ClipMeta = serde_json::from_value(sent["meta"].clone()).unwrap();
+        assert_eq!(meta.fallback_model.as_deref(), Some("gpt-4o-mini"));
+        assert_eq!(meta.copied_at, Some(1_700_000_000));
+        assert_eq!(meta.original_line_count, Some(120));
+        assert_eq!(meta.source_app.as_deref(), Some("Terminal"));
+    }
+
+    #[test]
+    fn panic_clear_discards_captures_in_flight() {
+        let capture = InFlight::start("in flight secret".to_string());
+        assert!(!capture.discarded());
+
+        assert!(discard_in_flight());
+        assert!(capture.discarded());
+
+        // a later capture of the same content is saved as usual
+        drop(capture);
+        assert!(!InFlight::start("in flight secret".to_string()).discarded());
+    }
+}
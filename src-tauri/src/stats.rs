@@ -0,0 +1,450 @@
+use crate::commands::VISIBLE_CLIPS;
+use crate::database;
+use crate::AppState;
+use regex::Regex;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::LazyLock;
+use tauri::State;
+
+#[derive(Debug, Serialize)]
+pub struct ActivityDay {
+    pub date: String,
+    pub total: i64,
+    pub by_type: HashMap<String, i64>,
+    pub by_category: HashMap<String, i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BusiestDay {
+    pub date: String,
+    pub total: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Activity {
+    pub from: String,
+    pub to: String,
+    pub days: Vec<ActivityDay>,
+    pub current_streak: u32,
+    pub busiest_day: Option<BusiestDay>,
+}
+
+/// Normalize a user supplied date to YYYY-MM-DD, using SQLite's own date parser
+pub(crate) fn normalize_date(conn: &Connection, value: &str) -> Result<String, String> {
+    conn.query_row("SELECT date(?)", params![value], |row| {
+        row.get::<_, Option<String>>(0)
+    })
+    .map_err(|e| format!("Failed to parse date: {e}"))?
+    .ok_or_else(|| format!("Invalid date '{}', expected YYYY-MM-DD", value))
+}
+
+/// RFC 3339 date-time: date, time with optional fraction, and a zone. SQLite's own
+/// parser also takes bare dates, Julian day numbers and modifiers like 'now'.
+static RFC_3339: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\d{4}-\d{2}-\d{2}[Tt]\d{2}:\d{2}:\d{2}(\.\d+)?([Zz]|[+-]\d{2}:\d{2})$").unwrap()
+});
+
+/// Normalize a user supplied RFC 3339 timestamp to UTC in the `YYYY-MM-DD HH:MM:SS`
+/// form CURRENT_TIMESTAMP stores, so it compares as text against `created_at`
+pub(crate) fn normalize_timestamp(conn: &Connection, value: &str) -> Result<String, String> {
+    let invalid = || {
+        format!(
+            "Invalid timestamp '{}', expected RFC 3339 such as 2024-05-01T09:30:00Z",
+            value
+        )
+    };
+    if !RFC_3339.is_match(value) {
+        return Err(invalid());
+    }
+
+    // datetime() is NULL for out of range fields such as month 13
+    conn.query_row("SELECT datetime(?)", params![value], |row| {
+        row.get::<_, Option<String>>(0)
+    })
+    .map_err(|e| format!("Failed to parse timestamp: {e}"))?
+    .ok_or_else(invalid)
+}
+
+/// Consecutive days with at least one capture, ending today (or yesterday if
+/// nothing has been captured yet today)
+fn current_streak(conn: &Connection) -> rusqlite::Result<u32> {
+    let today: i64 = conn.query_row(
+        "SELECT CAST(julianday(date('now', 'localtime')) AS INTEGER)",
+        [],
+        |row| row.get(0),
+    )?;
+
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT CAST(julianday(created_date) AS INTEGER) FROM clips WHERE created_date IS NOT NULL ORDER BY 1 DESC",
+    )?;
+    let days = stmt.query_map([], |row| row.get::<_, i64>(0))?;
+
+    let mut streak = 0;
+    let mut expected: Option<i64> = None;
+
+    for day in days {
+        let day = day?;
+        match expected {
+            None if day == today || day == today - 1 => {}
+            None => break,
+            Some(expected_day) if day == expected_day => {}
+            Some(_) => break,
+        }
+        streak += 1;
+        expected = Some(day - 1);
+    }
+
+    Ok(streak)
+}
+
+#[tauri::command]
+pub fn get_activity(
+    state: State<'_, AppState>,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<Activity, String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    let to = match to {
+        Some(to) => normalize_date(&conn, &to)?,
+        None => conn
+            .query_row("SELECT date('now', 'localtime')", [], |row| row.get(0))
+            .map_err(|e| format!("Failed to compute end date: {e}"))?,
+    };
+    let from = match from {
+        Some(from) => normalize_date(&conn, &from)?,
+        None => conn
+            .query_row("SELECT date(?, '-1 year')", params![to], |row| row.get(0))
+            .map_err(|e| format!("Failed to compute start date: {e}"))?,
+    };
+
+    let mut stmt = conn
+        .prepare(&format!(
+            r#"
+        SELECT
+          created_date,
+          CASE content_type WHEN 'image' THEN 'image' ELSE 'text' END,
+          COALESCE(NULLIF(category, ''), 'uncategorized'),
+          COUNT(*)
+        FROM clips
+        WHERE created_date BETWEEN ?1 AND ?2
+          AND {VISIBLE_CLIPS}
+        GROUP BY 1, 2, 3
+        "#
+        ))
+        .map_err(|e| format!("Failed to prepare statement: {e}"))?;
+
+    let rows = stmt
+        .query_map(params![from, to], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, i64>(3)?,
+            ))
+        })
+        .map_err(|e| format!("Failed to execute query: {e}"))?;
+
+    let mut days: BTreeMap<String, ActivityDay> = BTreeMap::new();
+    for row in rows {
+        let (date, clip_type, category, count) =
+            row.map_err(|e| format!("Failed to process row: {e}"))?;
+
+        let day = days.entry(date.clone()).or_insert_with(|| ActivityDay {
+            date,
+            total: 0,
+            by_type: HashMap::new(),
+            by_category: HashMap::new(),
+        });
+        day.total += count;
+        *day.by_type.entry(clip_type).or_insert(0) += count;
+        *day.by_category.entry(category).or_insert(0) += count;
+    }
+
+    let busiest_day = days
+        .values()
+        .max_by_key(|day| day.total)
+        .map(|day| BusiestDay {
+            date: day.date.clone(),
+            total: day.total,
+        });
+
+    let current_streak =
+        current_streak(&conn).map_err(|e| format!("Failed to compute streak: {e}"))?;
+
+    Ok(Activity {
+        from,
+        to,
+        days: days.into_values().collect(),
+        current_streak,
+        busiest_day,
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub struct LanguageCount {
+    pub language: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LanguageStats {
+    /// most copied language first
+    pub languages: Vec<LanguageCount>,
+    /// code clips no language was detected in
+    pub unknown: i64,
+    pub total: i64,
+}
+
+/// How many code_snippet clips were detected as each programming language
+#[tauri::command]
+pub fn get_language_stats(state: State<'_, AppState>) -> Result<LanguageStats, String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            r#"
+        SELECT code_language, COUNT(*)
+        FROM clips
+        WHERE category = 'code_snippet'
+          AND {VISIBLE_CLIPS}
+        GROUP BY code_language
+        ORDER BY COUNT(*) DESC, code_language
+        "#
+        ))
+        .map_err(|e| format!("Failed to prepare statement: {e}"))?;
+
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, Option<String>>(0)?, row.get::<_, i64>(1)?))
+        })
+        .map_err(|e| format!("Failed to execute query: {e}"))?;
+
+    let mut stats = LanguageStats {
+        languages: Vec::new(),
+        unknown: 0,
+        total: 0,
+    };
+    for row in rows {
+        let (language, count) = row.map_err(|e| format!("Failed to process row: {e}"))?;
+        stats.total += count;
+        match language {
+            Some(language) => stats.languages.push(LanguageCount { language, count }),
+            None => stats.unknown += count,
+        }
+    }
+
+    Ok(stats)
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct StorageUsage {
+    pub clips: i64,
+    /// clip data plus summary, tags and title
+    pub content_bytes: i64,
+    pub thumbnail_bytes: i64,
+    pub total_bytes: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StorageBreakdown {
+    pub total: StorageUsage,
+    /// keyed by text, url or image
+    pub by_type: HashMap<String, StorageUsage>,
+    pub by_category: HashMap<String, StorageUsage>,
+}
+
+/// Bytes each clip takes up in the clips table, split by clip type and category.
+/// Expired clips still waiting to be pruned are counted, they take space until then.
+#[tauri::command]
+pub fn get_storage_breakdown(state: State<'_, AppState>) -> Result<StorageBreakdown, String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    // length() counts characters for text, casting to a blob counts bytes
+    let mut stmt = conn
+        .prepare(
+            r#"
+        SELECT
+          COALESCE(content_type, 'text'),
+          COALESCE(NULLIF(category, ''), 'uncategorized'),
+          COUNT(*),
+          SUM(
+            COALESCE(length(CAST(clip AS BLOB)), 0)
+            + COALESCE(length(CAST(summary AS BLOB)), 0)
+            + COALESCE(length(CAST(tags AS BLOB)), 0)
+            + COALESCE(length(CAST(title AS BLOB)), 0)
+          ),
+          SUM(COALESCE(length(CAST(thumbnail AS BLOB)), 0))
+        FROM clips
+        GROUP BY 1, 2
+        "#,
+        )
+        .map_err(|e| format!("Failed to prepare statement: {e}"))?;
+
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, i64>(3)?,
+                row.get::<_, i64>(4)?,
+            ))
+        })
+        .map_err(|e| format!("Failed to execute query: {e}"))?;
+
+    let mut breakdown = StorageBreakdown {
+        total: StorageUsage::default(),
+        by_type: HashMap::new(),
+        by_category: HashMap::new(),
+    };
+
+    for row in rows {
+        let (clip_type, category, clips, content_bytes, thumbnail_bytes) =
+            row.map_err(|e| format!("Failed to process row: {e}"))?;
+
+        for usage in [
+            &mut breakdown.total,
+            breakdown.by_type.entry(clip_type).or_default(),
+            breakdown.by_category.entry(category).or_default(),
+        ] {
+            usage.clips += clips;
+            usage.content_bytes += content_bytes;
+            usage.thumbnail_bytes += thumbnail_bytes;
+            usage.total_bytes += content_bytes + thumbnail_bytes;
+        }
+    }
+
+    Ok(breakdown)
+}
+
+/// Quote a CSV field when it contains a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Write the recorded LLM requests between two local dates (inclusive, both
+/// optional) to a CSV file. Returns the number of rows written.
+#[tauri::command]
+pub fn export_usage_csv(
+    state: State<'_, AppState>,
+    path: String,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<usize, String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    let from = from.map(|from| normalize_date(&conn, &from)).transpose()?;
+    let to = to.map(|to| normalize_date(&conn, &to)).transpose()?;
+
+    let mut stmt = conn
+        .prepare(
+            r#"
+        SELECT
+          datetime(created_at, 'localtime'),
+          model,
+          stage,
+          input_tokens,
+          output_tokens,
+          cost,
+          latency_ms,
+          success
+        FROM llm_usage
+        WHERE (?1 IS NULL OR date(created_at, 'localtime') >= ?1)
+          AND (?2 IS NULL OR date(created_at, 'localtime') <= ?2)
+        ORDER BY created_at
+        "#,
+        )
+        .map_err(|e| format!("Failed to prepare statement: {e}"))?;
+
+    let file = File::create(&path).map_err(|e| format!("Failed to create {}: {}", path, e))?;
+    let mut writer = BufWriter::new(file);
+
+    writeln!(
+        writer,
+        "timestamp,model,stage,input_tokens,output_tokens,cost,latency_ms,success"
+    )
+    .map_err(|e| format!("Failed to write CSV: {e}"))?;
+
+    let mut rows = stmt
+        .query(params![from, to])
+        .map_err(|e| format!("Failed to execute query: {e}"))?;
+
+    let mut written = 0;
+    while let Some(row) = rows
+        .next()
+        .map_err(|e| format!("Failed to read row: {e}"))?
+    {
+        let read = |index: usize| -> Result<String, String> {
+            let value: Option<rusqlite::types::Value> = row
+                .get(index)
+                .map_err(|e| format!("Failed to read row: {e}"))?;
+            Ok(match value {
+                Some(rusqlite::types::Value::Text(text)) => csv_field(&text),
+                Some(rusqlite::types::Value::Integer(number)) => number.to_string(),
+                Some(rusqlite::types::Value::Real(number)) => number.to_string(),
+                _ => String::new(),
+            })
+        };
+
+        let fields = (0..8).map(read).collect::<Result<Vec<_>, _>>()?;
+        writeln!(writer, "{}", fields.join(","))
+            .map_err(|e| format!("Failed to write CSV: {e}"))?;
+        written += 1;
+    }
+
+    writer
+        .flush()
+        .map_err(|e| format!("Failed to write CSV: {e}"))?;
+
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_rfc_3339_timestamps_to_utc() {
+        let conn = Connection::open_in_memory().unwrap();
+        let cases = [
+            ("2024-05-01T09:30:00Z", "2024-05-01 09:30:00"),
+            ("2024-05-01t09:30:00.250z", "2024-05-01 09:30:00"),
+            ("2024-05-01T09:30:00+02:00", "2024-05-01 07:30:00"),
+            ("2024-05-01T23:30:00-01:00", "2024-05-02 00:30:00"),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(normalize_timestamp(&conn, value).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn rejects_anything_else() {
+        let conn = Connection::open_in_memory().unwrap();
+        for value in [
+            "now",
+            "2460432.5",
+            "2024-05-01",
+            "2024-05-01 09:30:00",
+            "2024-05-01T09:30:00",
+            "2024-05-01T09:30Z",
+            "2024-13-01T09:30:00Z",
+            "2024-05-01T09:30:00Z', '+1 day",
+            " 2024-05-01T09:30:00Z",
+        ] {
+            assert!(normalize_timestamp(&conn, value).is_err(), "{value}");
+        }
+    }
+}
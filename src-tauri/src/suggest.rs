@@ -0,0 +1,330 @@
+use crate::database;
+use crate::heuristic;
+use crate::shortcut::{content_type, Clip};
+use crate::AppState;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use tauri::{AppHandle, Listener, Manager, State};
+
+const SUGGESTION_COUNT: usize = 3;
+const DEFAULT_TAG_LIMIT: usize = 8;
+
+#[derive(Debug, Serialize)]
+pub struct CategorySuggestion {
+    pub category: String,
+    pub score: f64,
+}
+
+/// Signals describing where a clip came from and what it looks like; the
+/// category users pick for each signal is counted in `category_stats`
+fn clip_features(clip: &Clip, source_app: Option<&str>) -> Vec<String> {
+    let mut features = vec![format!("type:{}", content_type(clip))];
+
+    if let Some(app) = source_app {
+        features.push(format!("app:{}", app.to_lowercase()));
+    }
+
+    if let Clip::Text { plain } = clip {
+        if let Some(domain) = heuristic::url_domain(plain.trim()) {
+            features.push(format!("domain:{}", domain));
+        }
+    }
+
+    for tag in heuristic::suggest_tags(clip) {
+        features.push(format!("tag:{}", tag));
+    }
+
+    features
+}
+
+/// Remember which category was picked for a clip so future suggestions lean towards it
+pub fn record_category_choice(
+    conn: &Connection,
+    clip: &Clip,
+    source_app: Option<&str>,
+    category: &str,
+) -> rusqlite::Result<()> {
+    for feature in clip_features(clip, source_app) {
+        conn.execute(
+            "INSERT INTO category_stats (feature, category, count) VALUES (?1, ?2, 1)
+             ON CONFLICT(feature, category) DO UPDATE SET count = count + 1",
+            params![feature, category],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Average share of each category across all features that have been seen before
+fn historical_scores(
+    conn: &Connection,
+    features: &[String],
+) -> rusqlite::Result<HashMap<String, f64>> {
+    let mut stmt = conn.prepare(
+        "SELECT category, count, SUM(count) OVER () FROM category_stats WHERE feature = ?",
+    )?;
+
+    let mut scores: HashMap<String, f64> = HashMap::new();
+    let mut known_features = 0;
+
+    for feature in features {
+        let rows = stmt
+            .query_map(params![feature], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        if rows.is_empty() {
+            continue;
+        }
+        known_features += 1;
+
+        for (category, count, total) in rows {
+            *scores.entry(category).or_insert(0.0) += count as f64 / total as f64;
+        }
+    }
+
+    if known_features > 0 {
+        for score in scores.values_mut() {
+            *score /= known_features as f64;
+        }
+    }
+
+    Ok(scores)
+}
+
+pub fn suggest(
+    conn: &Connection,
+    clip: &Clip,
+    source_app: Option<&str>,
+) -> rusqlite::Result<Vec<CategorySuggestion>> {
+    let history = historical_scores(conn, &clip_features(clip, source_app))?;
+    let heuristic_weight = if history.is_empty() { 1.0 } else { 0.5 };
+
+    let mut combined: HashMap<String, f64> = HashMap::new();
+    for (category, score) in heuristic::score_categories(clip) {
+        *combined.entry(category).or_insert(0.0) += score * heuristic_weight;
+    }
+    for (category, score) in history {
+        *combined.entry(category).or_insert(0.0) += score * (1.0 - heuristic_weight);
+    }
+
+    let mut suggestions: Vec<CategorySuggestion> = combined
+        .into_iter()
+        .map(|(category, score)| CategorySuggestion { category, score })
+        .collect();
+    suggestions.sort_by(|a, b| b.score.total_cmp(&a.score));
+    suggestions.truncate(SUGGESTION_COUNT);
+
+    Ok(suggestions)
+}
+
+#[tauri::command]
+pub fn suggest_categories(
+    state: State<'_, AppState>,
+    clip_json: String,
+    source_app: Option<String>,
+) -> Result<Vec<CategorySuggestion>, String> {
+    let clip: Clip = serde_json::from_str(&clip_json)
+        .map_err(|e| format!("Failed to deserialize clip: {}", e))?;
+
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    suggest(&conn, &clip, source_app.as_deref())
+        .map_err(|e| format!("Failed to suggest categories: {e}"))
+}
+
+#[derive(Debug, Serialize)]
+pub struct TagSuggestion {
+    pub tag: String,
+    pub count: i64,
+    pub score: f64,
+}
+
+struct TagStats {
+    name: String,
+    count: i64,
+    age_days: f64,
+}
+
+#[derive(Default)]
+struct TagSnapshot {
+    tags: Vec<TagStats>,
+    // for every tag, the other tags it was used together with and how often
+    cooccurrence: HashMap<String, Vec<(String, i64)>>,
+}
+
+/// Usage counts and co-occurrence of all tags held in memory, since tag
+/// suggestions fire on every keystroke. Rebuilt lazily after `tags-changed`.
+pub struct TagIndex {
+    db_path: PathBuf,
+    snapshot: RwLock<TagSnapshot>,
+    stale: AtomicBool,
+}
+
+impl TagIndex {
+    pub fn new(db_path: PathBuf) -> Self {
+        Self {
+            db_path,
+            snapshot: RwLock::new(TagSnapshot::default()),
+            stale: AtomicBool::new(true),
+        }
+    }
+
+    pub fn invalidate(&self) {
+        self.stale.store(true, Ordering::SeqCst);
+    }
+
+    fn refresh_if_stale(&self) -> rusqlite::Result<()> {
+        if !self.stale.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        match self.load_snapshot() {
+            Ok(snapshot) => {
+                *self.snapshot.write().unwrap() = snapshot;
+                Ok(())
+            }
+            Err(e) => {
+                self.stale.store(true, Ordering::SeqCst);
+                Err(e)
+            }
+        }
+    }
+
+    fn load_snapshot(&self) -> rusqlite::Result<TagSnapshot> {
+        let conn = database::open_connection(&self.db_path)?;
+
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT tags.name, COUNT(*), julianday('now') - julianday(MAX(clips.created_at))
+            FROM tags
+            JOIN clip_tags ON clip_tags.tag_id = tags.id
+            JOIN clips ON clips.id = clip_tags.clip_id
+            GROUP BY tags.id
+            "#,
+        )?;
+        let tags = stmt
+            .query_map([], |row| {
+                Ok(TagStats {
+                    name: row.get(0)?,
+                    count: row.get(1)?,
+                    age_days: row.get::<_, Option<f64>>(2)?.unwrap_or(0.0),
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT a.name, b.name, COUNT(*)
+            FROM clip_tags x
+            JOIN clip_tags y ON y.clip_id = x.clip_id AND y.tag_id != x.tag_id
+            JOIN tags a ON a.id = x.tag_id
+            JOIN tags b ON b.id = y.tag_id
+            GROUP BY x.tag_id, y.tag_id
+            "#,
+        )?;
+        let mut cooccurrence: HashMap<String, Vec<(String, i64)>> = HashMap::new();
+        for row in stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+            ))
+        })? {
+            let (tag, other, count) = row?;
+            cooccurrence.entry(tag).or_default().push((other, count));
+        }
+
+        Ok(TagSnapshot { tags, cooccurrence })
+    }
+
+    pub fn suggest(
+        &self,
+        prefix: &str,
+        clip_preview: Option<&str>,
+        limit: usize,
+    ) -> rusqlite::Result<Vec<TagSuggestion>> {
+        self.refresh_if_stale()?;
+
+        let prefix = prefix.trim().to_lowercase();
+        let snapshot = self.snapshot.read().unwrap();
+
+        // tags named in the preview, and the tags usually seen next to them, get a boost
+        let preview_words: HashSet<String> = clip_preview
+            .unwrap_or("")
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric() && c != '-' && c != '_')
+            .filter(|word| !word.is_empty())
+            .map(|word| word.to_string())
+            .collect();
+
+        let mut boost: HashMap<&str, f64> = HashMap::new();
+        for stats in &snapshot.tags {
+            if preview_words.contains(&stats.name) {
+                *boost.entry(&stats.name).or_insert(0.0) += 2.0;
+                for (other, count) in snapshot.cooccurrence.get(&stats.name).into_iter().flatten() {
+                    *boost.entry(other).or_insert(0.0) += 0.5 * (1.0 + *count as f64).ln();
+                }
+            }
+        }
+
+        let mut suggestions: Vec<TagSuggestion> = snapshot
+            .tags
+            .iter()
+            .filter(|stats| stats.name.starts_with(&prefix))
+            .map(|stats| {
+                let usage = (1.0 + stats.count as f64).ln();
+                let recency = 1.0 / (1.0 + stats.age_days.max(0.0) / 30.0);
+                let score = usage + recency + boost.get(stats.name.as_str()).unwrap_or(&0.0);
+                TagSuggestion {
+                    tag: stats.name.clone(),
+                    count: stats.count,
+                    score,
+                }
+            })
+            .collect();
+
+        suggestions.sort_by(|a, b| b.score.total_cmp(&a.score));
+        suggestions.truncate(limit);
+
+        Ok(suggestions)
+    }
+}
+
+pub struct TagIndexState(pub Arc<TagIndex>);
+
+pub fn init_tag_index(app_handle: AppHandle, db_path: PathBuf) {
+    let index = Arc::new(TagIndex::new(db_path));
+
+    let listener_index = index.clone();
+    app_handle.listen_any("tags-changed", move |_| listener_index.invalidate());
+
+    app_handle.manage(TagIndexState(index));
+}
+
+#[tauri::command]
+pub fn suggest_tags(
+    tag_index: State<'_, TagIndexState>,
+    prefix: String,
+    clip_preview: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<TagSuggestion>, String> {
+    tag_index
+        .0
+        .suggest(
+            &prefix,
+            clip_preview.as_deref(),
+            limit.unwrap_or(DEFAULT_TAG_LIMIT),
+        )
+        .map_err(|e| format!("Failed to suggest tags: {e}"))
+}
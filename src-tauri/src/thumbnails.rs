@@ -0,0 +1,240 @@
+use crate::database;
+use crate::maintenance::{MaintenanceReport, TaskGuard};
+use crate::settings::{SettingsManager, SettingsManagerState};
+use crate::AppState;
+use base64::{engine::general_purpose, Engine};
+use image::ImageFormat;
+use rusqlite::params;
+use std::io::Cursor;
+use std::path::Path;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// Bounds for the `thumbnail_max_dimension` setting, past the upper one a
+/// "thumbnail" is close to a second full-size copy of the image
+pub const MIN_THUMBNAIL_SIZE: u32 = 64;
+pub const MAX_THUMBNAIL_SIZE: u32 = 1024;
+
+/// Longest edge of generated thumbnails, in pixels
+pub fn thumbnail_size(settings: &SettingsManager) -> u32 {
+    settings.get_u32("thumbnail_max_dimension")
+}
+
+/// Scale a base64 encoded image down so its longest edge is at most `max_dimension`,
+/// returned as base64 JPEG, a fraction of the size of a PNG for photos and
+/// screenshots. Transparency is flattened. Images already small enough are
+/// re-encoded as they are.
+pub fn make_thumbnail(
+    data: &str,
+    max_dimension: u32,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let bytes = general_purpose::STANDARD.decode(data)?;
+    let image = image::load_from_memory(&bytes)?;
+
+    let thumbnail = if image.width() > max_dimension || image.height() > max_dimension {
+        image.thumbnail(max_dimension, max_dimension)
+    } else {
+        image
+    };
+
+    let mut jpeg_data = Vec::new();
+    thumbnail
+        .to_rgb8()
+        .write_to(&mut Cursor::new(&mut jpeg_data), ImageFormat::Jpeg)?;
+
+    Ok(general_purpose::STANDARD.encode(&jpeg_data))
+}
+
+/// Thumbnail of an image clip, falling back to the full image for clips without one
+#[tauri::command]
+pub fn get_thumbnail(state: State<'_, AppState>, item_id: String) -> Result<String, String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    let (kind, thumbnail, data): (Option<String>, Option<String>, Option<String>) = conn
+        .query_row(
+            "SELECT json_extract(clip, '$.type'), thumbnail, json_extract(clip, '$.content') FROM clips WHERE id = ?",
+            params![item_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => "Item not found".to_string(),
+            e => format!("Failed to get clip: {e}"),
+        })?;
+
+    if kind.as_deref() != Some("image") {
+        return Err("Item is not an image".to_string());
+    }
+
+    thumbnail
+        .or(data)
+        .ok_or_else(|| "Image data not available".to_string())
+}
+
+/// Ids of the image clips to (re)generate thumbnails for
+fn thumbnail_candidates(
+    db_path: &Path,
+    only_missing: bool,
+    after_id: i64,
+) -> rusqlite::Result<Vec<i64>> {
+    let conn = database::open_connection(db_path)?;
+    let mut stmt = conn.prepare(
+        "SELECT id FROM clips WHERE content_type = 'image' AND (?1 = 0 OR thumbnail IS NULL) AND id > ?2 ORDER BY id",
+    )?;
+
+    let ids = stmt
+        .query_map(params![only_missing, after_id], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<i64>>>()?;
+
+    Ok(ids)
+}
+
+fn regenerate_thumbnail(db_path: &Path, id: i64, size: u32) -> Result<(), String> {
+    let conn =
+        database::open_connection(db_path).map_err(|e| format!("Failed to open database: {e}"))?;
+
+    let data: Option<String> = conn
+        .query_row(
+            "SELECT json_extract(clip, '$.content') FROM clips WHERE id = ?",
+            params![id],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to get clip: {e}"))?;
+
+    let data = data
+        .filter(|data| !data.is_empty())
+        .ok_or_else(|| "Image data not available".to_string())?;
+
+    let thumbnail =
+        make_thumbnail(&data, size).map_err(|e| format!("Failed to create thumbnail: {e}"))?;
+
+    conn.execute(
+        "UPDATE clips SET thumbnail = ? WHERE id = ?",
+        params![thumbnail, id],
+    )
+    .map_err(|e| format!("Failed to save thumbnail: {e}"))?;
+
+    Ok(())
+}
+
+async fn run_regeneration(
+    app_handle: &AppHandle,
+    db_path: &Path,
+    only_missing: bool,
+    restart: bool,
+) -> Result<MaintenanceReport, String> {
+    let task = "regenerate_thumbnails";
+    let guard = TaskGuard::acquire(app_handle, task)?;
+    let size = thumbnail_size(&app_handle.state::<SettingsManagerState>().0);
+
+    // clips that got a thumbnail drop out of an only_missing run by themselves
+    let resumed_after = if only_missing {
+        None
+    } else {
+        guard.resume_cursor(app_handle, restart)
+    };
+
+    let ids = thumbnail_candidates(db_path, only_missing, resumed_after.unwrap_or(0))
+        .map_err(|e| format!("Failed to load image clips: {e}"))?;
+
+    let mut report = MaintenanceReport {
+        task: task.to_string(),
+        total: ids.len(),
+        resumed_after,
+        ..Default::default()
+    };
+
+    for (processed, id) in ids.iter().enumerate() {
+        match regenerate_thumbnail(db_path, *id, size) {
+            Ok(()) => report.updated += 1,
+            Err(e) => {
+                eprintln!("Failed to regenerate thumbnail for clip {}: {}", id, e);
+                report.failed += 1;
+            }
+        }
+
+        if !only_missing {
+            guard.save_cursor(app_handle, *id);
+        }
+        guard.progress(app_handle, processed + 1, report.total);
+    }
+
+    if !only_missing {
+        guard.clear_cursor(app_handle);
+    }
+
+    if report.updated > 0 {
+        app_handle.emit("clips-updated", ()).ok();
+    }
+
+    Ok(report)
+}
+
+/// Generate thumbnails for image clips, either all of them or only those missing one
+#[tauri::command]
+pub async fn regenerate_thumbnails(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    only_missing: Option<bool>,
+    restart: Option<bool>,
+) -> Result<MaintenanceReport, String> {
+    run_regeneration(
+        &app_handle,
+        &state.db_path,
+        only_missing.unwrap_or(false),
+        restart.unwrap_or(false),
+    )
+    .await
+}
+
+/// Change the thumbnail size, optionally regenerating existing thumbnails at the new size
+#[tauri::command]
+pub async fn set_thumbnail_size(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    size: u32,
+    regenerate: Option<bool>,
+) -> Result<Option<MaintenanceReport>, String> {
+    if !(MIN_THUMBNAIL_SIZE..=MAX_THUMBNAIL_SIZE).contains(&size) {
+        return Err(format!(
+            "Thumbnail size must be between {} and {} pixels",
+            MIN_THUMBNAIL_SIZE, MAX_THUMBNAIL_SIZE
+        ));
+    }
+
+    app_handle
+        .state::<SettingsManagerState>()
+        .0
+        .set_setting("thumbnail_max_dimension", &size.to_string())
+        .map_err(|e| format!("Failed to save thumbnail size: {}", e))?;
+
+    if !regenerate.unwrap_or(false) {
+        return Ok(None);
+    }
+
+    // a new size makes the thumbnails of an interrupted run stale, start over
+    run_regeneration(&app_handle, &state.db_path, false, true)
+        .await
+        .map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thumbnails_are_scaled_down_jpegs() {
+        let image =
+            image::RgbaImage::from_fn(400, 200, |x, y| image::Rgba([x as u8, y as u8, 90, 128]));
+        let mut png = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+
+        let thumbnail = make_thumbnail(&general_purpose::STANDARD.encode(&png), 100).unwrap();
+        let bytes = general_purpose::STANDARD.decode(thumbnail).unwrap();
+        assert_eq!(image::guess_format(&bytes).unwrap(), ImageFormat::Jpeg);
+
+        let decoded = image::load_from_memory(&bytes).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (100, 50));
+    }
+}
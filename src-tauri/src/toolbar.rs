@@ -0,0 +1,193 @@
+use crate::settings::SettingsManagerState;
+use crate::shortcut::ClipContext;
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{
+    AppHandle, Emitter, LogicalSize, Manager, Monitor, PhysicalPosition, WebviewUrl, WebviewWindow,
+    WebviewWindowBuilder,
+};
+
+const TOOLBAR_LABEL: &str = "clip-toolbar";
+
+/// Size of the toolbar in logical pixels, scaled to each monitor's DPI
+const TOOLBAR_WIDTH: f64 = 420.0;
+const TOOLBAR_HEIGHT: f64 = 160.0;
+
+/// Gap between the cursor and the toolbar, in logical pixels
+const CURSOR_OFFSET: f64 = 16.0;
+
+/// Value of `toolbar_monitor` that follows the cursor instead of a fixed monitor
+pub const FOLLOW_CURSOR: &str = "cursor";
+
+/// The capture the toolbar is reviewing, kept for a toolbar that is still loading
+/// when clip-data is sent
+static PENDING_CLIP: Mutex<Option<ClipContext>> = Mutex::new(None);
+
+#[derive(Debug, Serialize)]
+pub struct MonitorInfo {
+    pub name: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f64,
+    pub is_primary: bool,
+}
+
+/// The monitor named by `toolbar_monitor`, or the one under the cursor. Monitors
+/// are looked up on every call, so unplugged ones fall back to the cursor and
+/// newly attached ones are picked up.
+fn target_monitor(
+    app_handle: &AppHandle,
+    cursor: Option<PhysicalPosition<f64>>,
+) -> tauri::Result<Option<Monitor>> {
+    let pinned = app_handle
+        .state::<SettingsManagerState>()
+        .0
+        .get_string("toolbar_monitor");
+
+    if pinned != FOLLOW_CURSOR {
+        let monitor = app_handle
+            .available_monitors()?
+            .into_iter()
+            .find(|monitor| monitor.name().is_some_and(|name| *name == pinned));
+        if monitor.is_some() {
+            return Ok(monitor);
+        }
+        eprintln!(
+            "Toolbar monitor '{}' is not connected, using the cursor",
+            pinned
+        );
+    }
+
+    if let Some(cursor) = cursor {
+        if let Some(monitor) = app_handle.monitor_from_point(cursor.x, cursor.y)? {
+            return Ok(Some(monitor));
+        }
+    }
+
+    app_handle.primary_monitor()
+}
+
+/// Top left corner of the toolbar on `monitor`: next to the cursor when it is on
+/// that monitor, centered in the upper third otherwise, and always fully on screen
+fn toolbar_position(
+    monitor: &Monitor,
+    cursor: Option<PhysicalPosition<f64>>,
+) -> PhysicalPosition<i32> {
+    let scale = monitor.scale_factor();
+    let (left, top) = (monitor.position().x, monitor.position().y);
+    let (width, height) = (monitor.size().width as i32, monitor.size().height as i32);
+    let toolbar_width = (TOOLBAR_WIDTH * scale) as i32;
+    let toolbar_height = (TOOLBAR_HEIGHT * scale) as i32;
+
+    let on_monitor = cursor.filter(|cursor| {
+        (left..left + width).contains(&(cursor.x as i32))
+            && (top..top + height).contains(&(cursor.y as i32))
+    });
+
+    let (x, y) = match on_monitor {
+        Some(cursor) => {
+            let offset = (CURSOR_OFFSET * scale) as i32;
+            (cursor.x as i32 + offset, cursor.y as i32 + offset)
+        }
+        None => (left + (width - toolbar_width) / 2, top + height / 3),
+    };
+
+    PhysicalPosition::new(
+        x.clamp(left, (left + width - toolbar_width).max(left)),
+        y.clamp(top, (top + height - toolbar_height).max(top)),
+    )
+}
+
+/// Size the toolbar for the monitor's DPI and move it there
+fn place_toolbar(app_handle: &AppHandle, window: &WebviewWindow) -> tauri::Result<()> {
+    let cursor = app_handle.cursor_position().ok();
+    let Some(monitor) = target_monitor(app_handle, cursor)? else {
+        return Ok(());
+    };
+
+    window.set_size(LogicalSize::new(TOOLBAR_WIDTH, TOOLBAR_HEIGHT))?;
+    window.set_position(toolbar_position(&monitor, cursor))
+}
+
+/// Open the review toolbar, or bring it back if it is already open, on the
+/// monitor the user is working on. Async because creating a window from a
+/// synchronous command deadlocks on Windows.
+#[tauri::command]
+pub async fn show_clip_toolbar(app_handle: AppHandle) -> Result<(), String> {
+    let window = match app_handle.get_webview_window(TOOLBAR_LABEL) {
+        Some(window) => window,
+        None => WebviewWindowBuilder::new(
+            &app_handle,
+            TOOLBAR_LABEL,
+            WebviewUrl::App("toolbar.html".into()),
+        )
+        .title("Spiegel")
+        .inner_size(TOOLBAR_WIDTH, TOOLBAR_HEIGHT)
+        .decorations(false)
+        .resizable(false)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .visible(false)
+        .build()
+        .map_err(|e| format!("Failed to open toolbar: {e}"))?,
+    };
+
+    place_toolbar(&app_handle, &window).map_err(|e| format!("Failed to place toolbar: {e}"))?;
+    window
+        .show()
+        .and_then(|_| window.set_focus())
+        .map_err(|e| format!("Failed to show toolbar: {e}"))
+}
+
+/// Show a capture in the toolbar for the user to confirm before it is saved
+pub async fn review_clip(app_handle: &AppHandle, context: ClipContext) -> Result<(), String> {
+    *PENDING_CLIP.lock().unwrap() = Some(context.clone());
+    show_clip_toolbar(app_handle.clone()).await?;
+    app_handle
+        .emit_to(TOOLBAR_LABEL, "clip-data", context)
+        .map_err(|e| format!("Failed to send clip to toolbar: {e}"))
+}
+
+/// The capture under review, asked for by the toolbar once it has loaded
+#[tauri::command]
+pub fn get_pending_clip() -> Option<ClipContext> {
+    PENDING_CLIP.lock().unwrap().clone()
+}
+
+/// Close the toolbar and drop the capture it was reviewing
+#[tauri::command]
+pub fn close_toolbar_window(app_handle: AppHandle) -> Result<(), String> {
+    PENDING_CLIP.lock().unwrap().take();
+    match app_handle.get_webview_window(TOOLBAR_LABEL) {
+        Some(window) => window
+            .close()
+            .map_err(|e| format!("Failed to close toolbar: {e}")),
+        None => Ok(()),
+    }
+}
+
+/// Connected monitors, for choosing the one `toolbar_monitor` pins the toolbar to
+#[tauri::command]
+pub fn get_monitors(app_handle: AppHandle) -> Result<Vec<MonitorInfo>, String> {
+    let primary = app_handle
+        .primary_monitor()
+        .map_err(|e| format!("Failed to get primary monitor: {e}"))?
+        .and_then(|monitor| monitor.name().cloned());
+
+    let monitors = app_handle
+        .available_monitors()
+        .map_err(|e| format!("Failed to list monitors: {e}"))?;
+
+    Ok(monitors
+        .iter()
+        .map(|monitor| MonitorInfo {
+            name: monitor.name().cloned(),
+            width: monitor.size().width,
+            height: monitor.size().height,
+            scale_factor: monitor.scale_factor(),
+            is_primary: monitor
+                .name()
+                .is_some_and(|name| Some(name) == primary.as_ref()),
+        })
+        .collect())
+}
@@ -0,0 +1,119 @@
+use crate::ansi;
+use crate::settings::SettingsManager;
+use crate::shortcut::Clip;
+use crate::urls;
+use serde::{Deserialize, Serialize};
+
+const TAB_WIDTH: usize = 4;
+
+/// Upper bound for the `capture_line_limit` setting, 0 turns the limit off
+pub const MAX_LINE_LIMIT: u32 = 100_000;
+
+/// Built-in rewrites of captured text, applied in the order the
+/// `capture_transforms` setting lists them
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Transform {
+    /// drop trailing whitespace from every line and blank lines around the text
+    Trim,
+    /// turn \r\n and lone \r line endings into \n
+    NormalizeNewlines,
+    /// remove terminal color codes, the colored original is not kept either
+    StripAnsi,
+    /// lowercase the scheme and host of links, paths are case sensitive and stay as they are
+    LowercaseUrls,
+    TabsToSpaces,
+}
+
+impl Transform {
+    fn apply(self, text: &str) -> String {
+        match self {
+            Transform::Trim => text
+                .lines()
+                .map(str::trim_end)
+                .collect::<Vec<_>>()
+                .join("\n")
+                .trim_matches('\n')
+                .to_string(),
+            Transform::NormalizeNewlines => text.replace("\r\n", "\n").replace('\r', "\n"),
+            Transform::StripAnsi => ansi::strip_ansi(text),
+            Transform::LowercaseUrls => lowercase_urls(text),
+            Transform::TabsToSpaces => text.replace('\t', &" ".repeat(TAB_WIDTH)),
+        }
+    }
+}
+
+fn lowercase_urls(text: &str) -> String {
+    let mut result = text.to_string();
+    for url in urls::extract_urls(text) {
+        let Some(authority_start) = url.find("://").map(|index| index + 3) else {
+            continue;
+        };
+        let authority_end = url[authority_start..]
+            .find(['/', '?', '#'])
+            .map_or(url.len(), |index| authority_start + index);
+
+        let lowered = format!(
+            "{}{}",
+            url[..authority_end].to_lowercase(),
+            &url[authority_end..]
+        );
+        if lowered != url {
+            result = result.replace(url, &lowered);
+        }
+    }
+    result
+}
+
+/// The `capture_transforms` setting, an empty list when unset or invalid
+pub fn configured_transforms(settings: &SettingsManager) -> Vec<Transform> {
+    serde_json::from_str(&settings.get_string("capture_transforms")).unwrap_or_default()
+}
+
+/// Run text clips through the transforms, images are left alone
+pub fn apply_transforms(clip: &Clip, transforms: &[Transform]) -> Clip {
+    match clip {
+        Clip::Text { plain } if !transforms.is_empty() => Clip::Text {
+            plain: transforms
+                .iter()
+                .fold(plain.clone(), |text, transform| transform.apply(&text)),
+        },
+        _ => clip.clone(),
+    }
+}
+
+/// Cut text clips longer than `capture_line_limit` lines down to their first
+/// lines, last lines or both, as `capture_line_keep` says, with a marker line
+/// where the rest was left out. The rest is not stored anywhere. Returns the
+/// clip and, when it was cut, how many lines it had.
+pub fn limit_lines(clip: &Clip, settings: &SettingsManager) -> (Clip, Option<usize>) {
+    let limit = settings.get_u32("capture_line_limit") as usize;
+    let Clip::Text { plain } = clip else {
+        return (clip.clone(), None);
+    };
+    let lines: Vec<&str> = plain.lines().collect();
+    if limit == 0 || lines.len() <= limit {
+        return (clip.clone(), None);
+    }
+
+    let (head, tail) = match settings.get_string("capture_line_keep").as_str() {
+        "tail" => (0, limit),
+        "both" => (limit.div_ceil(2), limit / 2),
+        _ => (limit, 0),
+    };
+    let marker = format!("[... {} lines omitted ...]", lines.len() - head - tail);
+
+    let kept: Vec<&str> = lines[..head]
+        .iter()
+        .copied()
+        .chain(std::iter::once(marker.as_str()))
+        .chain(lines[lines.len() - tail..].iter().copied())
+        .collect();
+
+    (
+        Clip::Text {
+            plain: kept.join("\n"),
+        },
+        Some(lines.len()),
+    )
+}
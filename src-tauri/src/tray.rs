@@ -0,0 +1,69 @@
+use crate::settings::SettingsManagerState;
+use std::thread;
+use tauri::menu::{CheckMenuItem, Menu};
+use tauri::tray::TrayIconBuilder;
+use tauri::{AppHandle, Manager, Wry};
+
+const SCHEDULED_CAPTURE_ID: &str = "scheduled_capture";
+
+/// Tray menu items whose state follows settings changed elsewhere
+pub struct TrayState {
+    scheduled_capture: CheckMenuItem<Wry>,
+}
+
+pub fn init_tray(app_handle: &AppHandle) -> tauri::Result<()> {
+    let enabled = app_handle
+        .state::<SettingsManagerState>()
+        .0
+        .get_bool("scheduled_capture_enabled");
+
+    let scheduled_capture = CheckMenuItem::with_id(
+        app_handle,
+        SCHEDULED_CAPTURE_ID,
+        "Scheduled capture",
+        true,
+        enabled,
+        None::<&str>,
+    )?;
+    let menu = Menu::with_items(app_handle, &[&scheduled_capture])?;
+
+    let mut tray = TrayIconBuilder::new()
+        .menu(&menu)
+        .tooltip("Spiegel")
+        .on_menu_event(|app, event| {
+            if event.id().as_ref() == SCHEDULED_CAPTURE_ID {
+                toggle_scheduled_capture(app);
+            }
+        });
+    if let Some(icon) = app_handle.default_window_icon() {
+        tray = tray.icon(icon.clone());
+    }
+    tray.build(app_handle)?;
+
+    app_handle.manage(TrayState { scheduled_capture });
+
+    Ok(())
+}
+
+fn toggle_scheduled_capture(app_handle: &AppHandle) {
+    let settings = app_handle.state::<SettingsManagerState>().0.clone();
+    let enabled = !settings.get_bool("scheduled_capture_enabled");
+
+    if let Err(e) = settings.set_setting("scheduled_capture_enabled", &enabled.to_string()) {
+        eprintln!("Failed to save scheduled capture: {}", e);
+        return;
+    }
+
+    // stopping waits for a capture in progress, keep that off the main thread
+    let app_handle = app_handle.clone();
+    thread::spawn(move || crate::schedule::apply_settings(&app_handle));
+}
+
+/// Show whether scheduled capture is on, however it was switched
+pub fn set_scheduled_capture_checked(app_handle: &AppHandle, checked: bool) {
+    if let Some(tray) = app_handle.try_state::<TrayState>() {
+        if let Err(e) = tray.scheduled_capture.set_checked(checked) {
+            eprintln!("Failed to update tray menu: {}", e);
+        }
+    }
+}
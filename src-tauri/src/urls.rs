@@ -0,0 +1,543 @@
+use crate::settings::SettingsManager;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::Duration;
+use url::{Host, Url};
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+/// Title and description sit in the head, there is no need to download whole pages
+const MAX_PAGE_BYTES: usize = 512 * 1024;
+const MAX_REDIRECTS: usize = 10;
+
+/// Query parameters that only track where a link was shared from
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid", "gclid", "dclid", "msclkid", "yclid", "igshid", "mc_cid", "mc_eid", "ref_src",
+    "_hsenc", "_hsmi",
+];
+
+fn is_tracking_param(key: &str) -> bool {
+    key.starts_with("utm_") || TRACKING_PARAMS.contains(&key)
+}
+
+/// Canonical form of an http(s) URL used to recognize the same link copied twice:
+/// https scheme, no www., no fragment, no tracking parameters, sorted query and
+/// no trailing slash
+pub fn normalize_url(text: &str) -> Option<String> {
+    canonical_url(text, true)
+}
+
+/// Like `normalize_url`, optionally keeping tracking parameters
+pub fn canonical_url(text: &str, strip_tracking: bool) -> Option<String> {
+    let mut url = Url::parse(text.trim()).ok()?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+
+    url.set_scheme("https").ok()?;
+    url.set_fragment(None);
+
+    if let Some(host) = url.host_str().and_then(|host| host.strip_prefix("www.")) {
+        let host = host.to_string();
+        url.set_host(Some(&host)).ok()?;
+    }
+
+    let mut pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(key, _)| !strip_tracking || !is_tracking_param(key))
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    pairs.sort();
+
+    if pairs.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+
+    let path = url.path().trim_end_matches('/').to_string();
+    url.set_path(if path.is_empty() { "/" } else { &path });
+
+    Some(url.to_string())
+}
+
+/// http(s) URLs appearing anywhere in a piece of text, with surrounding
+/// punctuation such as brackets, quotes or a sentence's full stop trimmed off
+pub fn extract_urls(text: &str) -> Vec<&str> {
+    text.split_whitespace()
+        .filter_map(|word| {
+            let start = word.find("http://").or_else(|| word.find("https://"))?;
+            let url = word[start..].trim_end_matches(|c: char| {
+                matches!(
+                    c,
+                    '.' | ',' | ';' | ':' | '!' | '?' | ')' | ']' | '>' | '"' | '\''
+                )
+            });
+            (url.len() > "https://".len()).then_some(url)
+        })
+        .collect()
+}
+
+/// What a page says about itself, used to give the summarizer more than a bare URL
+#[derive(Debug, Default)]
+pub struct PageDetails {
+    pub title: Option<String>,
+    pub description: Option<String>,
+}
+
+/// Which hosts links may be fetched from, read from the `url_fetch_allowlist`,
+/// `url_fetch_blocklist` and `url_fetch_block_private` settings
+#[derive(Debug, Clone)]
+pub struct FetchPolicy {
+    /// when not empty, only these domains (and their subdomains) are fetched
+    pub allowlist: Vec<String>,
+    pub blocklist: Vec<String>,
+    /// refuse loopback, private and link-local addresses, and names like localhost
+    pub block_private: bool,
+}
+
+impl FetchPolicy {
+    pub fn from_settings(settings: &SettingsManager) -> Self {
+        let domains = |key: &str| -> Vec<String> {
+            serde_json::from_str::<Vec<String>>(&settings.get_string(key))
+                .unwrap_or_default()
+                .iter()
+                .map(|domain| normalize_domain(domain))
+                .filter(|domain| !domain.is_empty())
+                .collect()
+        };
+
+        Self {
+            allowlist: domains("url_fetch_allowlist"),
+            blocklist: domains("url_fetch_blocklist"),
+            block_private: settings.get_bool("url_fetch_block_private"),
+        }
+    }
+
+    /// Why a URL may not be fetched, judged on its host name alone
+    fn refusal(&self, url: &Url) -> Option<String> {
+        let Some(host) = url.host() else {
+            return Some("URL has no host".to_string());
+        };
+
+        if self.block_private {
+            let private = match &host {
+                Host::Domain(domain) => is_private_name(domain),
+                Host::Ipv4(ip) => is_private_ip(IpAddr::V4(*ip)),
+                Host::Ipv6(ip) => is_private_ip(IpAddr::V6(*ip)),
+            };
+            if private {
+                return Some(format!("{} is a private address", host));
+            }
+        }
+
+        let host = normalize_domain(&host.to_string());
+        if self
+            .blocklist
+            .iter()
+            .any(|domain| domain_matches(&host, domain))
+        {
+            return Some(format!("{} is on the fetch blocklist", host));
+        }
+        if !self.allowlist.is_empty()
+            && !self
+                .allowlist
+                .iter()
+                .any(|domain| domain_matches(&host, domain))
+        {
+            return Some(format!("{} is not on the fetch allowlist", host));
+        }
+
+        None
+    }
+
+    /// Why a URL may not be fetched, or None when it may. With `block_private` the
+    /// host is also resolved, so public names pointing at private addresses fail
+    /// early; fetch_page_details filters the addresses it connects to as well.
+    pub async fn check(&self, url: &str) -> Result<(), String> {
+        let parsed = Url::parse(url).map_err(|e| format!("Invalid URL {url}: {e}"))?;
+        if let Some(refusal) = self.refusal(&parsed) {
+            return Err(refusal);
+        }
+
+        if self.block_private {
+            if let Some(Host::Domain(domain)) = parsed.host() {
+                let target = (
+                    domain.to_string(),
+                    parsed.port_or_known_default().unwrap_or(443),
+                );
+                let addresses = tauri::async_runtime::spawn_blocking(move || {
+                    target
+                        .to_socket_addrs()
+                        .map(|addresses| addresses.map(|address| address.ip()).collect::<Vec<_>>())
+                })
+                .await
+                .map_err(|e| format!("Failed to resolve {domain}: {e}"))?
+                .map_err(|e| format!("Failed to resolve {domain}: {e}"))?;
+
+                if addresses.into_iter().any(is_private_ip) {
+                    return Err(format!("{} resolves to a private address", domain));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn normalize_domain(domain: &str) -> String {
+    domain
+        .trim()
+        .trim_end_matches('.')
+        .to_lowercase()
+        .trim_start_matches("www.")
+        .to_string()
+}
+
+/// `host` is `domain` itself or one of its subdomains
+fn domain_matches(host: &str, domain: &str) -> bool {
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+fn is_private_name(domain: &str) -> bool {
+    let domain = domain.trim_end_matches('.').to_lowercase();
+    domain == "localhost"
+        || [".localhost", ".local", ".internal", ".lan", ".home.arpa"]
+            .iter()
+            .any(|suffix| domain.ends_with(suffix))
+}
+
+fn is_private_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_private_ipv4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_private_ipv4(ip),
+            None => is_private_ipv6(ip),
+        },
+    }
+}
+
+fn is_private_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        // carrier-grade NAT, 100.64.0.0/10
+        || (a == 100 && (64..128).contains(&b))
+}
+
+fn is_private_ipv6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    ip.is_loopback()
+        || ip.is_unspecified()
+        // unique local, fc00::/7
+        || (first & 0xfe00) == 0xfc00
+        // link-local, fe80::/10
+        || (first & 0xffc0) == 0xfe80
+}
+
+/// Resolves the names fetch_page_details connects to and drops private addresses,
+/// so neither a redirect nor a name resolving differently the second time
+/// (DNS rebinding) reaches one
+struct PublicAddressResolver;
+
+impl Resolve for PublicAddressResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let target = (host.clone(), 0);
+            let addresses = tauri::async_runtime::spawn_blocking(move || {
+                target
+                    .to_socket_addrs()
+                    .map(|addresses| addresses.collect::<Vec<_>>())
+            })
+            .await
+            .map_err(|e| e.to_string())??;
+
+            let public: Vec<SocketAddr> = addresses
+                .into_iter()
+                .filter(|address| !is_private_ip(address.ip()))
+                .collect();
+            if public.is_empty() {
+                return Err(format!("{} resolves to a private address", host).into());
+            }
+            Ok(Box::new(public.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Download the start of a page and read its title and meta description. Fails
+/// for dead links: unreachable hosts, timeouts and non-2xx responses. The URL
+/// itself has to pass `FetchPolicy::check` first, redirects are checked here.
+pub async fn fetch_page_details(url: &str, policy: &FetchPolicy) -> Result<PageDetails, String> {
+    // redirects are checked by host name, the resolver keeps them off private addresses
+    let redirect_policy = policy.clone();
+    let mut builder = reqwest::Client::builder();
+    if policy.block_private {
+        builder = builder.dns_resolver(Arc::new(PublicAddressResolver));
+    }
+    let client = builder
+        .timeout(FETCH_TIMEOUT)
+        .user_agent("Spiegel")
+        .redirect(reqwest::redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                attempt.error("too many redirects")
+            } else if let Some(refusal) = redirect_policy.refusal(attempt.url()) {
+                attempt.error(refusal)
+            } else {
+                attempt.follow()
+            }
+        }))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {e}"))?;
+
+    let mut response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch {url}: {e}"))?;
+
+    if !response.status().is_success() {
+        return Err(format!("{} returned {}", url, response.status()));
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("Failed to read {url}: {e}"))?
+    {
+        body.extend_from_slice(&chunk);
+        if body.len() >= MAX_PAGE_BYTES {
+            break;
+        }
+    }
+
+    let html = String::from_utf8_lossy(&body);
+    Ok(PageDetails {
+        title: html_title(&html),
+        description: meta_description(&html),
+    })
+}
+
+fn html_title(html: &str) -> Option<String> {
+    // ASCII lowercasing keeps byte offsets, so positions found in `lower` index `html`
+    let lower = html.to_ascii_lowercase();
+    let start = lower.find("<title")?;
+    let content_start = start + lower[start..].find('>')? + 1;
+    let content_end = content_start + lower[content_start..].find("</title")?;
+
+    clean_html_text(&html[content_start..content_end])
+}
+
+fn meta_description(html: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+
+    lower.match_indices("<meta").find_map(|(start, _)| {
+        let end = start + lower[start..].find('>')?;
+        let tag = &lower[start..end];
+        if !(tag.contains("\"description\"")
+            || tag.contains("'description'")
+            || tag.contains("\"og:description\""))
+        {
+            return None;
+        }
+
+        let value_start = start + tag.find("content=")? + "content=".len();
+        let quote = html[value_start..]
+            .chars()
+            .next()
+            .filter(|c| *c == '"' || *c == '\'')?;
+        let value_start = value_start + 1;
+        let value_end = value_start + html[value_start..end].find(quote)?;
+
+        clean_html_text(&html[value_start..value_end])
+    })
+}
+
+/// Collapse whitespace and decode the handful of entities common in titles
+fn clean_html_text(text: &str) -> Option<String> {
+    let text = text
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&");
+
+    (!text.is_empty()).then_some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn refusal(policy: &FetchPolicy, url: &str) -> Option<String> {
+        policy.refusal(&Url::parse(url).unwrap())
+    }
+
+    fn private_blocked() -> FetchPolicy {
+        FetchPolicy {
+            allowlist: Vec::new(),
+            blocklist: Vec::new(),
+            block_private: true,
+        }
+    }
+
+    #[test]
+    fn refuses_private_addresses() {
+        let policy = private_blocked();
+        for url in [
+            "http://127.0.0.1/",
+            "http://10.1.2.3/",
+            "http://192.168.0.1:8080/",
+            "http://169.254.169.254/latest/meta-data",
+            "http://100.64.0.1/",
+            "http://[::1]/",
+            "http://[fd00::1]/",
+            "http://[fe80::1]/",
+            "http://[::ffff:192.168.1.1]/",
+        ] {
+            assert!(refusal(&policy, url).is_some(), "{} was allowed", url);
+        }
+        assert_eq!(refusal(&policy, "https://93.184.215.14/"), None);
+        assert_eq!(refusal(&policy, "https://[2606:4700::1111]/"), None);
+
+        let open = FetchPolicy {
+            block_private: false,
+            ..policy
+        };
+        assert_eq!(refusal(&open, "http://127.0.0.1/"), None);
+    }
+
+    #[test]
+    fn refuses_local_names() {
+        let policy = private_blocked();
+        for url in [
+            "http://localhost:3000/",
+            "http://LOCALHOST./",
+            "http://app.localhost/",
+            "http://printer.local/",
+            "http://nas.home.arpa/",
+        ] {
+            assert!(refusal(&policy, url).is_some(), "{} was allowed", url);
+        }
+        assert_eq!(refusal(&policy, "https://localhost.example.com/"), None);
+    }
+
+    #[test]
+    fn allowlist_covers_subdomains() {
+        let policy = FetchPolicy {
+            allowlist: vec!["example.com".to_string()],
+            blocklist: Vec::new(),
+            block_private: false,
+        };
+        assert_eq!(refusal(&policy, "https://example.com/"), None);
+        assert_eq!(refusal(&policy, "https://www.example.com/"), None);
+        assert_eq!(refusal(&policy, "https://docs.example.com/a"), None);
+        assert!(refusal(&policy, "https://notexample.com/").is_some());
+        assert!(refusal(&policy, "https://example.com.evil.io/").is_some());
+    }
+
+    #[test]
+    fn blocklist_wins_over_allowlist() {
+        let policy = FetchPolicy {
+            allowlist: vec!["example.com".to_string()],
+            blocklist: vec!["private.example.com".to_string()],
+            block_private: false,
+        };
+        assert_eq!(refusal(&policy, "https://example.com/"), None);
+        assert!(refusal(&policy, "https://private.example.com/")
+            .unwrap()
+            .contains("blocklist"));
+        assert!(refusal(&policy, "https://a.private.example.com/")
+            .unwrap()
+            .contains("blocklist"));
+    }
+
+    #[test]
+    fn resolver_drops_private_addresses() {
+        let name: Name = "localhost".parse().unwrap();
+        let resolved = tauri::async_runtime::block_on(PublicAddressResolver.resolve(name));
+        assert!(resolved.is_err());
+    }
+
+    #[test]
+    fn variants_of_a_link_share_one_canonical_url() {
+        let canonical = normalize_url("https://example.com/docs");
+        assert_eq!(canonical.as_deref(), Some("https://example.com/docs"));
+
+        for variant in [
+            "http://example.com/docs",
+            "https://www.example.com/docs",
+            "https://example.com/docs/",
+            "https://example.com/docs#installation",
+            "https://example.com/docs?utm_source=newsletter&utm_medium=email",
+            "https://example.com/docs?fbclid=abc123",
+            "  http://www.example.com/docs/?gclid=xyz#top  ",
+        ] {
+            assert_eq!(normalize_url(variant), canonical, "{}", variant);
+        }
+    }
+
+    #[test]
+    fn query_parameters_are_sorted() {
+        assert_eq!(
+            normalize_url("https://example.com/search?q=rust&page=2"),
+            normalize_url("https://example.com/search?page=2&q=rust"),
+        );
+        assert_eq!(
+            normalize_url("https://example.com/search?q=rust&page=2").as_deref(),
+            Some("https://example.com/search?page=2&q=rust")
+        );
+    }
+
+    #[test]
+    fn different_pages_stay_different() {
+        assert_ne!(
+            normalize_url("https://example.com/docs"),
+            normalize_url("https://example.com/blog")
+        );
+        assert_ne!(
+            normalize_url("https://example.com/search?q=rust"),
+            normalize_url("https://example.com/search?q=go")
+        );
+    }
+
+    #[test]
+    fn root_keeps_its_slash() {
+        assert_eq!(
+            normalize_url("http://www.example.com").as_deref(),
+            Some("https://example.com/")
+        );
+    }
+
+    #[test]
+    fn only_web_links_are_normalized() {
+        assert_eq!(normalize_url("ftp://example.com/file.txt"), None);
+        assert_eq!(normalize_url("mailto:someone@example.com"), None);
+        assert_eq!(normalize_url("not a link"), None);
+    }
+
+    #[test]
+    fn tracking_parameters_can_be_kept() {
+        assert_eq!(
+            canonical_url("https://example.com/docs?utm_source=newsletter", false).as_deref(),
+            Some("https://example.com/docs?utm_source=newsletter")
+        );
+    }
+
+    #[test]
+    fn extracted_urls_lose_surrounding_punctuation() {
+        assert_eq!(
+            extract_urls("See (https://example.com/docs). Or \"http://example.org\", thanks!"),
+            vec!["https://example.com/docs", "http://example.org"]
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use crate::database;
+use crate::AppState;
+use rusqlite::{params, OptionalExtension};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+
+/// A previous category/summary/tags of a clip, recorded by the clips_versions_update
+/// trigger whenever one of them changes
+#[derive(Debug, Serialize)]
+pub struct ClipVersion {
+    pub id: i64,
+    pub clip_id: String,
+    pub category: Option<String>,
+    pub summary: Option<String>,
+    pub tags: Vec<String>,
+    pub created_at: String,
+}
+
+#[tauri::command]
+pub fn get_clip_versions(
+    state: State<'_, AppState>,
+    item_id: String,
+) -> Result<Vec<ClipVersion>, String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, clip_id, category, summary, tags, created_at FROM clip_versions WHERE clip_id = ? ORDER BY id DESC",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {e}"))?;
+
+    let versions = stmt
+        .query_map(params![item_id], |row| {
+            let tags_json: Option<String> = row.get(4)?;
+            Ok(ClipVersion {
+                id: row.get(0)?,
+                clip_id: row.get::<_, i64>(1)?.to_string(),
+                category: row.get(2)?,
+                summary: row.get(3)?,
+                tags: tags_json
+                    .and_then(|tags| serde_json::from_str(&tags).ok())
+                    .unwrap_or_default(),
+                created_at: row.get(5)?,
+            })
+        })
+        .map_err(|e| format!("Failed to execute query: {e}"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to process row: {e}"))?;
+
+    Ok(versions)
+}
+
+/// Restore a clip to one of its recorded versions. The values being replaced are
+/// recorded as a new version in turn, so a revert can itself be undone.
+#[tauri::command]
+pub fn revert_clip_version(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    item_id: String,
+    version_id: i64,
+) -> Result<(), String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    let version: Option<(Option<String>, Option<String>, Option<String>)> = conn
+        .query_row(
+            "SELECT category, summary, tags FROM clip_versions WHERE id = ? AND clip_id = ?",
+            params![version_id, item_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to get version: {e}"))?;
+
+    let (category, summary, tags) = version.ok_or_else(|| "Version not found".to_string())?;
+
+    let rows_affected = conn
+        .execute(
+            "UPDATE clips SET category = ?1, summary = ?2, tags = ?3, clip = json_set(clip, '$.category', ?1, '$.summary', ?2) WHERE id = ?4",
+            params![category, summary, tags, item_id],
+        )
+        .map_err(|e| format!("Failed to revert clip: {e}"))?;
+
+    if rows_affected == 0 {
+        return Err("Item not found".to_string());
+    }
+
+    app_handle
+        .emit("clip-updated", &item_id)
+        .map_err(|e| format!("Failed to emit event: {}", e))?;
+    app_handle.emit("tags-changed", ()).ok();
+
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use crate::commands::VISIBLE_CLIPS;
+use crate::database;
+use crate::settings::{SettingsManager, SettingsManagerState};
+use crate::AppState;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+
+const MAX_WORKSPACE_NAME_CHARS: usize = 64;
+
+/// Clip count of one workspace, `name` is None for clips captured outside any workspace
+#[derive(Debug, Serialize)]
+pub struct WorkspaceInfo {
+    pub name: Option<String>,
+    pub count: i64,
+}
+
+pub(crate) fn check_workspace_name(value: &str) -> Option<String> {
+    if value.chars().count() > MAX_WORKSPACE_NAME_CHARS {
+        return Some(format!(
+            "Workspace names are limited to {} characters",
+            MAX_WORKSPACE_NAME_CHARS
+        ));
+    }
+    if value.chars().any(char::is_control) {
+        return Some("Workspace names cannot contain control characters".to_string());
+    }
+    None
+}
+
+/// Workspace new captures are stamped with, None when no workspace is active
+pub fn active_workspace(settings: &SettingsManager) -> Option<String> {
+    let name = settings.get_string("active_workspace");
+    let name = name.trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Stamp subsequent captures with `name`, or stop stamping them when it is empty
+#[tauri::command]
+pub fn set_active_workspace(
+    app_handle: AppHandle,
+    settings_manager: State<'_, SettingsManagerState>,
+    name: Option<String>,
+) -> Result<(), String> {
+    let name = name.unwrap_or_default().trim().to_string();
+
+    if let Some(problem) = check_workspace_name(&name) {
+        return Err(problem);
+    }
+
+    settings_manager
+        .0
+        .set_setting("active_workspace", &name)
+        .map_err(|e| format!("Failed to save active workspace: {e}"))?;
+
+    app_handle
+        .emit("workspace-changed", (!name.is_empty()).then_some(&name))
+        .ok();
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_active_workspace(
+    settings_manager: State<'_, SettingsManagerState>,
+) -> Result<Option<String>, String> {
+    Ok(active_workspace(&settings_manager.0))
+}
+
+/// Every workspace with clips in it, largest first, plus the clips outside any workspace
+#[tauri::command]
+pub fn get_workspaces(state: State<'_, AppState>) -> Result<Vec<WorkspaceInfo>, String> {
+    let conn = database::open_connection(&state.db_path)
+        .map_err(|e| format!("Failed to open database: {e}"))?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT workspace, COUNT(*) FROM clips WHERE {VISIBLE_CLIPS} GROUP BY workspace ORDER BY COUNT(*) DESC, workspace"
+        ))
+        .map_err(|e| format!("Failed to prepare statement: {e}"))?;
+
+    let workspaces = stmt
+        .query_map([], |row| {
+            Ok(WorkspaceInfo {
+                name: row.get(0)?,
+                count: row.get(1)?,
+            })
+        })
+        .map_err(|e| format!("Failed to execute query: {e}"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to process row: {e}"))?;
+
+    Ok(workspaces)
+}
@@ -1,4 +1,4 @@
-import { useEffect, useState } from "react";
+import { useEffect, useRef, useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
 import "./globals.css";
@@ -19,8 +19,19 @@ export interface ClipItem {
   category?: string;
   summary?: string;
   tags?: string[];
+  thumbnail_data?: string;
 }
 
+interface ClipPage {
+  items: ClipItem[];
+  total_count: number;
+  has_more: boolean;
+  next_cursor: string | null;
+}
+
+// the search and category filters run over the whole list, so every page is loaded
+const PAGE_SIZE = 200;
+
 interface Settings {
   [key: string]: string | null;
 }
@@ -33,17 +44,35 @@ export default function App() {
   const [globalShortcut, setGlobalShortcut] = useState("");
   const [llmApiKey, setLlmApiKey] = useState("");
   const [hasChanges, setHasChanges] = useState(false);
+  const loadGeneration = useRef(0);
 
   const getItems = async () => {
+    // a newer load replaces this one, its remaining pages are not needed
+    const generation = ++loadGeneration.current;
     setIsLoadingItems(true);
     try {
-      const items = await invoke<ClipItem[]>("get_items");
-      setItems(items);
+      let page = await invoke<ClipPage>("get_items", { limit: PAGE_SIZE });
+      if (generation !== loadGeneration.current) return;
+      let loaded = page.items;
+      setItems(loaded);
+      setIsLoadingItems(false);
+
+      while (page.has_more && page.next_cursor !== null) {
+        page = await invoke<ClipPage>("get_items", {
+          limit: PAGE_SIZE,
+          cursor: page.next_cursor,
+        });
+        if (generation !== loadGeneration.current) return;
+        loaded = [...loaded, ...page.items];
+        setItems(loaded);
+      }
     } catch (error) {
       console.log("error", error);
       errorToast("Unable to fetch items");
     } finally {
-      setIsLoadingItems(false);
+      if (generation === loadGeneration.current) {
+        setIsLoadingItems(false);
+      }
     }
   };
 
@@ -55,7 +84,9 @@ export default function App() {
     try {
       const settings = await invoke<Settings>("get_all_settings");
       const shortcut = settings["global_hotkey"];
-      const apiKey = settings["llm_api_key"];
+      const apiKey = await invoke<string | null>("get_secret_setting", {
+        key: "llm_api_key",
+      });
 
       setGlobalShortcut(shortcut || "CommandOrControl+Shift+C");
       setLlmApiKey(apiKey || "");
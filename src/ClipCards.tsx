@@ -153,7 +153,8 @@ export default function ClipCard(props: ClipCardProps) {
                           true,
                           searchQuery,
                           item.category,
-                          item.id
+                          item.id,
+                          item.thumbnail_data
                         )}
                       </div>
                     </div>
@@ -6,6 +6,8 @@ import {
   DialogFooter,
   DialogClose,
 } from "./components/ui/dialog";
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
 import "./globals.css";
 import { formatDateTime } from "./lib/utils";
 import { Calendar, Folder, Tags, Trash } from "lucide-react";
@@ -33,6 +35,17 @@ export default function ClipDialog(props: ClipDialogProps) {
     searchQuery,
   } = props;
 
+  // lists only carry image thumbnails, load the full image for the dialog
+  const [fullClip, setFullClip] = useState<ClipItem["clip"] | null>(null);
+
+  useEffect(() => {
+    setFullClip(null);
+    if (!selectedItem?.clip.Image) return;
+    invoke<ClipItem>("get_item", { itemId: selectedItem.id })
+      .then((item) => setFullClip(item.clip))
+      .catch((error) => console.error("Failed to load clip:", error));
+  }, [selectedItem?.id]);
+
   return (
     <Dialog open={dialogOpen} onOpenChange={setDialogOpen}>
       <DialogContent className="max-w-3xl">
@@ -94,11 +107,12 @@ export default function ClipDialog(props: ClipDialogProps) {
               </div>
               <div className="mb-4 max-h-96 overflow-auto border rounded p-3 bg-gray-50">
                 {renderClipContent(
-                  selectedItem.clip,
+                  fullClip ?? selectedItem.clip,
                   false,
                   searchQuery,
                   selectedItem.category,
-                  selectedItem.id
+                  selectedItem.id,
+                  selectedItem.thumbnail_data
                 )}
               </div>
               {selectedItem.summary && (
@@ -279,7 +279,8 @@ export const renderClipContent = (
   truncate: boolean = true,
   searchQuery?: string,
   category?: string,
-  itemId?: string
+  itemId?: string,
+  thumbnail?: string
 ) => {
   if (clip.Text?.plain) {
     const text = clip.Text.plain;
@@ -317,7 +318,7 @@ export const renderClipContent = (
   if (clip.Image) {
     return (
       <ImageViewer
-        imageData={clip.Image.data}
+        imageData={clip.Image.data || thumbnail || ""}
         itemId={itemId || ""}
         width={clip.Image.width}
         height={clip.Image.height}
@@ -7,28 +7,58 @@ import { CategoryInput } from "./CategoryCombobox";
 import { categories } from "../CategoryFilters";
 import { Button } from "./ui/button";
 
+// capture details the backend needs back when the clip is saved
+interface ClipMeta {
+  source_app?: string | null;
+  expires_in_minutes?: number | null;
+  [key: string]: unknown;
+}
+
 interface ClipContext {
   suggested_category?: string;
+  summary: string;
+  tags: string[];
+  meta: ClipMeta;
   clip: {
     Text?: { plain: string };
     Image?: { data: number[]; width: number; height: number };
   };
 }
 
+// offered when the clip looks like a password, key or token
+const EXPIRY_OPTIONS = [
+  { label: "Keep", minutes: null },
+  { label: "Expire after 10 min", minutes: 10 },
+  { label: "Expire after 1 hour", minutes: 60 },
+  { label: "Expire after 1 day", minutes: 60 * 24 },
+];
+
 export const ClipToolbar: React.FC = () => {
   const [clipData, setClipData] = useState<ClipContext | null>(null);
   const [userCategory, setUserCategory] = useState("");
   const [_, setIsLoadingClipData] = useState(true);
   const [isSaving, setIsSaving] = useState(false);
+  const [expiresInMinutes, setExpiresInMinutes] = useState<number | null>(
+    null
+  );
 
   useEffect(() => {
-    const unlistenData = listen<ClipContext>("clip-data", (event) => {
-      setClipData(event.payload);
+    const showClip = (context: ClipContext) => {
+      setClipData(context);
       setIsLoadingClipData(false);
+      setUserCategory(context.suggested_category ?? "");
+      setExpiresInMinutes(
+        context.meta.expires_in_minutes ??
+          (context.suggested_category === "credentials" ? 60 : null)
+      );
+    };
 
-      if (event.payload.suggested_category && !userCategory) {
-        setUserCategory(event.payload.suggested_category);
-      }
+    const unlistenData = listen<ClipContext>("clip-data", (event) =>
+      showClip(event.payload)
+    );
+    // clip-data is missed when it arrives before the window has loaded
+    invoke<ClipContext | null>("get_pending_clip").then((context) => {
+      if (context) showClip(context);
     });
 
     return () => {
@@ -42,7 +72,11 @@ export const ClipToolbar: React.FC = () => {
       await invoke("submit_clip", {
         userCategory:
           userCategory.trim() || clipData?.suggested_category || "Other",
+        summary: clipData?.summary ?? "",
         clipJson: JSON.stringify(clipData?.clip),
+        tags: clipData?.tags ?? [],
+        expiresInMinutes,
+        meta: clipData?.meta ?? {},
       });
     } catch (error) {
       console.error("Failed to save clip:", error);
@@ -79,6 +113,22 @@ export const ClipToolbar: React.FC = () => {
         aiSuggestion={clipData?.suggested_category}
       />
 
+      {clipData?.suggested_category === "credentials" && (
+        <select
+          value={expiresInMinutes ?? ""}
+          onChange={(e) =>
+            setExpiresInMinutes(e.target.value ? Number(e.target.value) : null)
+          }
+          className="h-8 rounded-md border px-1 text-sm"
+        >
+          {EXPIRY_OPTIONS.map((option) => (
+            <option key={option.label} value={option.minutes ?? ""}>
+              {option.label}
+            </option>
+          ))}
+        </select>
+      )}
+
       <Button
         variant="ghost"
         onClick={handleCancel}
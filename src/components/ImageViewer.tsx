@@ -86,7 +86,11 @@ export const ImageViewer: React.FC<ImageViewerProps> = ({
     const handleZoomOut = () => setZoom(prev => Math.max(prev - 0.25, 0.25));
     const handleRotate = () => setRotation(prev => (prev + 90) % 360);
 
-    const imageSrc = useMemo(() => `data:image/png;base64,${imageData}`, [imageData]);
+    // thumbnails are JPEG, full images PNG; base64 JPEG data starts with "/9j/"
+    const imageSrc = useMemo(
+        () => `data:image/${imageData.startsWith("/9j/") ? "jpeg" : "png"};base64,${imageData}`,
+        [imageData]
+    );
 
     const LoadingPlaceholder = () => (
         <div className="flex items-center justify-center text-gray-400">